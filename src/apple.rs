@@ -0,0 +1,31 @@
+//! Apple extensions to ISO 9660, `AA` and `BA` entries in the system use area.
+
+use crate::susp;
+
+/// Finder information from an `AA` entry, or ProDOS information from a `BA`
+/// entry mapped the way HFS does it: type `p` followed by the ProDOS file type
+/// and auxiliary type, creator `pdos`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppleAttributes {
+    pub file_type: [u8; 4],
+    pub creator: [u8; 4],
+    pub finder_flags: u16,
+}
+
+impl AppleAttributes {
+    pub(crate) fn parse(area: &[u8]) -> Option<Self> {
+        susp::entries(area).find_map(|entry| match (&entry.signature, entry.data) {
+            (b"AA", [t0, t1, t2, t3, c0, c1, c2, c3, f0, f1, ..]) => Some(Self {
+                file_type: [*t0, *t1, *t2, *t3],
+                creator: [*c0, *c1, *c2, *c3],
+                finder_flags: u16::from_be_bytes([*f0, *f1]),
+            }),
+            (b"BA", [file_type, a0, a1, ..]) => Some(Self {
+                file_type: [b'p', *file_type, *a0, *a1],
+                creator: *b"pdos",
+                finder_flags: 0,
+            }),
+            _ => None,
+        })
+    }
+}
@@ -0,0 +1,114 @@
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+use crate::Result;
+use crate::core::{BlockLayout, LOGICAL_BLOCK_SIZE};
+use crate::descriptor::{VolumeDescriptorType, read_descriptors, read_sector};
+use crate::rock_ridge;
+use crate::susp;
+
+// upper bound on the sectors scanned for the volume recognition sequence
+const PROBE_LIMIT: u32 = 64;
+
+// identifiers of the Rock Ridge extension in an `ER` entry
+const ROCK_RIDGE_IDS: [&[u8]; 3] = [b"RRIP_1991A", b"IEEE_P1282", b"IEEE_1282"];
+
+/// Extensions found on an image, see [`IsoFileReader::capabilities`](crate::IsoFileReader::capabilities).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct IsoCapabilities {
+    /// A supplementary volume descriptor with UCS-2 escape sequences.
+    pub joliet: bool,
+    /// An ISO 9660:1999 enhanced volume descriptor with long names.
+    pub enhanced: bool,
+    /// SUSP entries announcing Rock Ridge in the root directory.
+    pub rock_ridge: bool,
+    /// An El Torito boot record, the image is bootable.
+    pub el_torito: bool,
+    /// An NSR descriptor in the volume recognition sequence.
+    pub udf: bool,
+}
+
+impl IsoCapabilities {
+    pub(crate) async fn probe<R: AsyncRead + AsyncSeekExt + Unpin>(
+        reader: &mut R,
+        layout: BlockLayout,
+        session_start: u32,
+        root_location: u64,
+    ) -> Result<Self> {
+        let mut capabilities = Self::default();
+
+        let descriptors = read_descriptors(reader, layout.base(), session_start).await?;
+
+        for descriptor in &descriptors {
+            match descriptor.descriptor_type() {
+                VolumeDescriptorType::BootRecord => {
+                    capabilities.el_torito |= descriptor.boot_catalog().is_some()
+                }
+                VolumeDescriptorType::Supplementary => {
+                    capabilities.joliet |= descriptor.is_joliet();
+                    capabilities.enhanced |= descriptor.is_enhanced();
+                }
+                _ => {}
+            }
+        }
+
+        // the volume recognition sequence of UDF follows the terminator
+        let terminator = descriptors
+            .last()
+            .filter(|t| t.descriptor_type() == VolumeDescriptorType::Terminator);
+
+        if let Some(terminator) = terminator {
+            let sectors = BlockLayout::new(layout.base(), LOGICAL_BLOCK_SIZE as u16);
+            let start = terminator.block() + 1;
+
+            for block in start..start + PROBE_LIMIT {
+                let Some(sector) = read_sector(reader, sectors.offset(block)).await? else {
+                    break;
+                };
+
+                match &sector[1..6] {
+                    b"NSR02" | b"NSR03" => capabilities.udf = true,
+                    b"BEA01" | b"BOOT2" | b"CD001" | b"CDW02" => {}
+                    _ => break,
+                }
+            }
+        }
+
+        capabilities.rock_ridge = probe_rock_ridge(reader, layout, root_location).await?;
+
+        Ok(capabilities)
+    }
+}
+
+// looks at the system use area of the root "." record and its continuations
+async fn probe_rock_ridge<R: AsyncRead + AsyncSeekExt + Unpin>(
+    reader: &mut R,
+    layout: BlockLayout,
+    root_location: u64,
+) -> Result<bool> {
+    let mut record = [0u8; 255];
+    reader.seek(SeekFrom::Start(root_location)).await?;
+    reader.read_exact(&mut record[..1]).await?;
+
+    let length = usize::from(record[0]).max(1);
+    reader.read_exact(&mut record[1..length]).await?;
+
+    let area = susp::system_use(&record);
+
+    let Some(skip) = rock_ridge::susp_skip(area) else {
+        return Ok(false);
+    };
+
+    let area = area.get(usize::from(skip)..).unwrap_or_default();
+    let entries = susp::read_entries(reader, layout, area).await?;
+
+    Ok(susp::entries(&entries).any(|entry| match &entry.signature {
+        b"RR" => true,
+        b"ER" if entry.data.len() >= 4 => {
+            let id_len = usize::from(entry.data[0]);
+            let id = entry.data.get(4..4 + id_len).unwrap_or_default();
+
+            ROCK_RIDGE_IDS.contains(&id)
+        }
+        _ => false,
+    }))
+}
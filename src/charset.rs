@@ -0,0 +1,25 @@
+//! Decoding of identifiers recorded in encodings other than UTF-8.
+
+/// Encoding of ISO 9660 identifiers and header strings, see
+/// [`IsoReadOptions::set_charset`](crate::IsoReadOptions::set_charset). Joliet
+/// names are always UCS-2.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Charset {
+    /// UTF-8, invalid sequences are replaced.
+    #[default]
+    Utf8,
+    /// ISO-8859-1, each byte is the code point of the same value.
+    Latin1,
+    /// Any other encoding.
+    Custom(fn(&[u8]) -> String),
+}
+
+impl Charset {
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            Self::Utf8 => String::from_utf8_lossy(bytes).to_string(),
+            Self::Latin1 => bytes.iter().map(|&t| char::from(t)).collect(),
+            Self::Custom(decode) => decode(bytes),
+        }
+    }
+}
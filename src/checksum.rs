@@ -0,0 +1,67 @@
+use std::ops::Range;
+
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+/// Application use area of the primary volume descriptor, read as spaces when
+/// hashing an image with an implanted MD5.
+pub(crate) const APPLICATION_USE: Range<usize> = 883..1395;
+
+// trailing sectors left out by implantisomd5 when not recorded
+const DEFAULT_SKIP_SECTORS: u64 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Md5,
+    Sha256,
+}
+
+pub(crate) enum Hasher {
+    Md5(Md5),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    pub(crate) fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Md5 => Self::Md5(Md5::new()),
+            ChecksumAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+        }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Md5(t) => t.update(data),
+            Self::Sha256(t) => t.update(data),
+        }
+    }
+
+    pub(crate) fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Md5(t) => t.finalize().to_vec(),
+            Self::Sha256(t) => t.finalize().to_vec(),
+        }
+    }
+}
+
+/// Hex digest and skipped trailing sectors recorded by `implantisomd5`.
+pub(crate) fn implanted_md5(application_use: &[u8]) -> Option<(String, u64)> {
+    let text = String::from_utf8_lossy(application_use);
+
+    let field = |name: &str| {
+        let start = text.find(name)? + name.len();
+        text[start..].split(';').next()
+    };
+
+    let digest = field("ISO MD5SUM = ")?;
+
+    if digest.len() != 32 || !digest.bytes().all(|t| t.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let skip = field("SKIPSECTORS = ")
+        .and_then(|t| t.trim().parse().ok())
+        .unwrap_or(DEFAULT_SKIP_SECTORS);
+
+    Some((digest.to_ascii_lowercase(), skip))
+}
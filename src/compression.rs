@@ -0,0 +1,323 @@
+use std::collections::VecDeque;
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+#[cfg(feature = "gzip")]
+use async_compression::tokio::bufread::GzipDecoder;
+#[cfg(feature = "xz")]
+use async_compression::tokio::bufread::XzDecoder;
+#[cfg(feature = "zstd")]
+use async_compression::tokio::bufread::ZstdDecoder;
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncSeek, BufReader, ReadBuf};
+
+/// Compression format of an image file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "xz")]
+    Xz,
+}
+
+enum Decoder<R> {
+    #[cfg(feature = "gzip")]
+    Gzip(GzipDecoder<BufReader<R>>),
+    #[cfg(feature = "zstd")]
+    Zstd(ZstdDecoder<BufReader<R>>),
+    #[cfg(feature = "xz")]
+    Xz(XzDecoder<BufReader<R>>),
+}
+
+impl<R: AsyncRead + Unpin> Decoder<R> {
+    fn new(compression: Compression, source: R) -> Self {
+        let source = BufReader::new(source);
+
+        match compression {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut decoder = GzipDecoder::new(source);
+                decoder.multiple_members(true);
+                Self::Gzip(decoder)
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let mut decoder = ZstdDecoder::new(source);
+                decoder.multiple_members(true);
+                Self::Zstd(decoder)
+            }
+            #[cfg(feature = "xz")]
+            Compression::Xz => {
+                let mut decoder = XzDecoder::new(source);
+                decoder.multiple_members(true);
+                Self::Xz(decoder)
+            }
+        }
+    }
+
+    fn into_source(self) -> R {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip(t) => t.into_inner().into_inner(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(t) => t.into_inner().into_inner(),
+            #[cfg(feature = "xz")]
+            Self::Xz(t) => t.into_inner().into_inner(),
+        }
+    }
+
+    fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip(t) => Pin::new(t).poll_read(cx, buf),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(t) => Pin::new(t).poll_read(cx, buf),
+            #[cfg(feature = "xz")]
+            Self::Xz(t) => Pin::new(t).poll_read(cx, buf),
+        }
+    }
+}
+
+// decompressed bytes kept together in the cache
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Decompressed bytes cached by default, see
+/// [`DecompressReader::set_cache_size`].
+pub const DEFAULT_CACHE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Seekable view over a compressed image.
+///
+/// Data is decompressed sequentially in blocks of 64 KiB and the most recently
+/// used blocks are kept, so the backward seeks of parsing the descriptors and
+/// directories are served from memory. A backward seek to a block that is no
+/// longer cached rewinds the source and decompresses from the start again.
+pub struct DecompressReader<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    compression: Compression,
+    decoder: Option<Decoder<R>>,
+    // source being rewound to its start before decoding again
+    rewinding: Option<R>,
+    // decompressed bytes produced by the current decoder
+    decoded: u64,
+    // block being decoded and the bytes of it filled so far
+    block: Vec<u8>,
+    filled: usize,
+    // position requested by the caller
+    target: u64,
+    // decompressed length, known once the end was reached
+    length: Option<u64>,
+    seek_end: Option<i64>,
+    // decoded blocks by index, the most recently used last
+    cache: VecDeque<(u64, Bytes)>,
+    cache_blocks: usize,
+}
+
+impl<R> DecompressReader<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    /// `source` must be positioned at the start of the compressed data.
+    pub fn new(source: R, compression: Compression) -> Self {
+        Self {
+            compression,
+            decoder: Some(Decoder::new(compression, source)),
+            rewinding: None,
+            decoded: 0,
+            block: vec![0u8; BLOCK_SIZE],
+            filled: 0,
+            target: 0,
+            length: None,
+            seek_end: None,
+            cache: VecDeque::new(),
+            cache_blocks: DEFAULT_CACHE_SIZE / BLOCK_SIZE,
+        }
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Decompressed bytes kept in memory, [`DEFAULT_CACHE_SIZE`] by default and
+    /// at least one block.
+    pub fn set_cache_size(&mut self, cache_size: usize) {
+        self.cache_blocks = cache_size.div_ceil(BLOCK_SIZE).max(1);
+
+        while self.cache.len() > self.cache_blocks {
+            self.cache.pop_front();
+        }
+    }
+
+    fn cached(&mut self, index: u64) -> Option<Bytes> {
+        let at = self.cache.iter().position(|t| t.0 == index)?;
+        let entry = self.cache.remove(at)?;
+        let block = entry.1.clone();
+        self.cache.push_back(entry);
+
+        Some(block)
+    }
+
+    // decodes the block at `decoded` into the cache
+    fn poll_decode_next(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(source) = self.rewinding.as_mut() {
+            ready!(Pin::new(source).poll_complete(cx))?;
+
+            let source = self.rewinding.take().expect("rewinding source");
+            self.decoder = Some(Decoder::new(self.compression, source));
+            self.decoded = 0;
+            self.filled = 0;
+        }
+
+        let decoder = self.decoder.as_mut().expect("active decoder");
+        let mut end = false;
+
+        while self.filled < BLOCK_SIZE {
+            let mut buf = ReadBuf::new(&mut self.block[self.filled..]);
+            ready!(decoder.poll_read(cx, &mut buf))?;
+
+            if buf.filled().is_empty() {
+                end = true;
+                break;
+            }
+
+            self.filled += buf.filled().len();
+        }
+
+        let index = self.decoded / BLOCK_SIZE as u64;
+        let block = Bytes::copy_from_slice(&self.block[..self.filled]);
+
+        self.decoded += self.filled as u64;
+        self.filled = 0;
+
+        if end {
+            self.length = Some(self.decoded);
+        }
+
+        if !block.is_empty() && self.cached(index).is_none() {
+            if self.cache.len() == self.cache_blocks {
+                self.cache.pop_front();
+            }
+
+            self.cache.push_back((index, block));
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    // block `index`, `None` past the end
+    fn poll_block(&mut self, cx: &mut Context<'_>, index: u64) -> Poll<io::Result<Option<Bytes>>> {
+        let start = index * BLOCK_SIZE as u64;
+
+        loop {
+            if let Some(block) = self.cached(index) {
+                return Poll::Ready(Ok(Some(block)));
+            }
+
+            if self.length.is_some_and(|t| start >= t) {
+                return Poll::Ready(Ok(None));
+            }
+
+            // blocks are only produced in order, an earlier one needs a rewind
+            if self.rewinding.is_none() && start < self.decoded {
+                let decoder = self.decoder.take().expect("active decoder");
+                let mut source = decoder.into_source();
+
+                Pin::new(&mut source).start_seek(SeekFrom::Start(0))?;
+                self.rewinding = Some(source);
+            }
+
+            ready!(self.poll_decode_next(cx))?;
+        }
+    }
+}
+
+impl<R> AsyncRead for DecompressReader<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        let index = this.target / BLOCK_SIZE as u64;
+        let offset = (this.target % BLOCK_SIZE as u64) as usize;
+
+        let Some(block) = ready!(this.poll_block(cx, index))? else {
+            return Poll::Ready(Ok(()));
+        };
+
+        if offset < block.len() {
+            let len = (block.len() - offset).min(buf.remaining());
+            buf.put_slice(&block[offset..offset + len]);
+            this.target += len as u64;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R> AsyncSeek for DecompressReader<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+
+        this.seek_end = None;
+
+        let target = match position {
+            SeekFrom::Start(t) => Some(t),
+            SeekFrom::Current(t) => this.target.checked_add_signed(t),
+            SeekFrom::End(t) => match this.length {
+                Some(length) => length.checked_add_signed(t),
+                None => {
+                    this.seek_end = Some(t);
+                    return Ok(());
+                }
+            },
+        };
+
+        this.target = target.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+
+        if let Some(offset) = this.seek_end {
+            // the length is only known after decompressing everything
+            while this.length.is_none() {
+                ready!(this.poll_decode_next(cx))?;
+            }
+
+            let length = this.length.expect("measured length");
+
+            this.seek_end = None;
+            this.target = length.checked_add_signed(offset).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "invalid seek to a negative or overflowing position",
+                )
+            })?;
+        }
+
+        Poll::Ready(Ok(this.target))
+    }
+}
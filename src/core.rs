@@ -1,26 +1,64 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::mem::transmute;
+use std::ops::Bound;
 use std::path::{Path, PathBuf};
 use std::{mem, slice};
 
-use async_recursion::async_recursion;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, Utc};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, SeekFrom};
 
-use crate::Result;
+use crate::apple::AppleAttributes;
+use crate::charset::Charset;
+use crate::options::Limits;
+use crate::rock_ridge::{self, PosixAttributes, RockRidge, RockRidgeTimes};
+use crate::susp;
+use crate::trans_tbl;
 use crate::types::DecDateTime;
 use crate::types::IsoDateTime;
 use crate::types::LsbMsb;
+use crate::xa::{XA_SIZE, XaAttributes};
+use crate::zisofs::Zisofs;
+use crate::{IsoFileError, Result};
 
 pub const LOGICAL_BLOCK_SIZE: usize = 2048;
 
-macro_rules! utf8_trimmed {
-    ($field:expr) => {
-        std::str::from_utf8($field)
-            .ok()
-            .map(|t| t.trim())
+/// Blocks scanned past the end of a session when looking for the next one,
+/// enough to cover the lead-out and lead-in gap between sessions.
+pub const SESSION_SCAN_LIMIT: u32 = 16384;
+
+/// Maps logical block numbers to byte offsets in the underlying reader.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BlockLayout {
+    base: u64,
+    logical_block_size: u16,
+}
+
+impl BlockLayout {
+    pub(crate) fn new(base: u64, logical_block_size: u16) -> Self {
+        Self {
+            base,
+            logical_block_size,
+        }
+    }
+
+    pub(crate) fn base(&self) -> u64 {
+        self.base
+    }
+
+    pub(crate) fn offset(&self, block: u32) -> u64 {
+        self.base + u64::from(block) * u64::from(self.logical_block_size)
+    }
+
+    pub(crate) fn logical_block_size(&self) -> u16 {
+        self.logical_block_size
+    }
+}
+
+macro_rules! trimmed {
+    ($charset:expr, $field:expr) => {
+        Some($charset.decode($field))
+            .map(|t| t.trim().to_string())
             .filter(|t| !t.is_empty())
-            .map(String::from)
     };
 }
 
@@ -81,6 +119,7 @@ pub(crate) struct RootDirectoryEntry {
     pub location_of_extent: usize,
     pub data_length: usize,
     pub datetime: DateTime<Utc>,
+    pub volume_seq_number: u16,
 }
 
 impl RootDirectoryEntry {
@@ -94,7 +133,7 @@ impl RootDirectoryEntry {
             flags: 2,
             unit_size: 0,
             interleave_gap_size: 0,
-            volume_seq_number: LsbMsb::new_u16(1),
+            volume_seq_number: LsbMsb::new_u16(self.volume_seq_number),
             file_identifier_length: 1,
             file_identifier: [0],
         })
@@ -140,16 +179,78 @@ pub(crate) struct IsoHeaderRaw {
 }
 
 impl IsoHeaderRaw {
-    pub fn root_entry_location(&self) -> u32 {
-        self.root_directory_entry.location_of_extent.lsb() * self.logical_block_size.lsb() as u32
+    pub fn root_entry_block(&self) -> u32 {
+        self.root_directory_entry.location_of_extent.lsb()
+    }
+
+    pub(crate) fn root_entry_length(&self) -> u32 {
+        self.root_directory_entry.data_length.lsb()
     }
 
     pub fn logical_block_size(&self) -> u16 {
         self.logical_block_size.lsb()
     }
 
-    pub fn loc_of_type_l_path_table(&self) -> u32 {
-        self.loc_of_type_l_path_table * self.logical_block_size.lsb() as u32
+    /// Both-endian fields whose halves differ.
+    pub(crate) fn endian_mismatches(&self) -> Vec<&'static str> {
+        [
+            ("volume_space_size", self.volume_space_size.is_consistent()),
+            ("volume_set_size", self.volume_set_size.is_consistent()),
+            (
+                "volume_sequence_number",
+                self.volume_sequence_number.is_consistent(),
+            ),
+            (
+                "logical_block_size",
+                self.logical_block_size.is_consistent(),
+            ),
+            ("path_table_size", self.path_table_size.is_consistent()),
+        ]
+        .into_iter()
+        .filter(|(_, consistent)| !consistent)
+        .map(|(field, _)| field)
+        .collect()
+    }
+
+    /// Block layout of the volume, the block size must be a power of two
+    /// between 512 and the 2048 byte sector size.
+    pub(crate) fn layout(&self, base: u64) -> Result<BlockLayout> {
+        let block_size = self.logical_block_size();
+
+        if !block_size.is_power_of_two() || !(512..=LOGICAL_BLOCK_SIZE as u16).contains(&block_size)
+        {
+            return Err(IsoFileError::InvalidLogicalBlockSize(block_size));
+        }
+
+        Ok(BlockLayout::new(base, block_size))
+    }
+
+    /// Size of the volume in 2048 byte sectors.
+    pub(crate) fn volume_space_sectors(&self) -> u32 {
+        let bytes = u64::from(self.volume_space_size()) * u64::from(self.logical_block_size());
+        bytes.div_ceil(LOGICAL_BLOCK_SIZE as u64) as u32
+    }
+
+    pub fn volume_space_size(&self) -> u32 {
+        self.volume_space_size.lsb()
+    }
+
+    pub fn type_l_path_table_block(&self) -> u32 {
+        self.loc_of_type_l_path_table
+    }
+
+    pub fn type_m_path_table_block(&self) -> u32 {
+        u32::from_be(self.loc_of_type_m_path_table)
+    }
+
+    /// Block of the optional L path table, zero if none is recorded.
+    pub fn opti_l_path_table_block(&self) -> u32 {
+        self.loc_of_opti_l_path_table
+    }
+
+    /// Block of the optional M path table, zero if none is recorded.
+    pub fn opti_m_path_table_block(&self) -> u32 {
+        u32::from_be(self.loc_of_opti_m_path_table)
     }
 
     pub async fn read<R: AsyncReadExt + Unpin>(reader: &mut R) -> Result<Self> {
@@ -187,6 +288,37 @@ impl IsoHeaderRaw {
     }
 }
 
+/// Looks for the primary volume descriptor of a session starting at or after
+/// `from_block`, giving up after [`SESSION_SCAN_LIMIT`] blocks or at the end of
+/// the image.
+pub(crate) async fn find_next_session<R: AsyncRead + AsyncSeekExt + Unpin>(
+    reader: &mut R,
+    base: u64,
+    from_block: u32,
+) -> Result<Option<u32>> {
+    let layout = BlockLayout::new(base, LOGICAL_BLOCK_SIZE as u16);
+
+    for block in from_block..from_block.saturating_add(SESSION_SCAN_LIMIT) {
+        reader
+            .seek(SeekFrom::Start(layout.offset(block + 16)))
+            .await?;
+
+        let mut signature = [0u8; 7];
+
+        match reader.read_exact(&mut signature).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        if signature == [0x01, b'C', b'D', b'0', b'0', b'1', 0x01] {
+            return Ok(Some(block));
+        }
+    }
+
+    Ok(None)
+}
+
 impl Default for IsoHeaderRaw {
     fn default() -> Self {
         Self {
@@ -253,10 +385,10 @@ pub struct IsoHeader {
     pub(crate) copyright_file_id: Option<String>,
     pub(crate) abstract_file_id: Option<String>,
     pub(crate) bibliographic_file_id: Option<String>,
-    pub(crate) volume_creation_date: Option<DateTime<Utc>>,
-    pub(crate) volume_modification_date: Option<DateTime<Utc>>,
-    pub(crate) volume_expiration_date: Option<DateTime<Utc>>,
-    pub(crate) volume_effective_date: Option<DateTime<Utc>>,
+    pub(crate) volume_creation_date: Option<DateTime<FixedOffset>>,
+    pub(crate) volume_modification_date: Option<DateTime<FixedOffset>>,
+    pub(crate) volume_expiration_date: Option<DateTime<FixedOffset>>,
+    pub(crate) volume_effective_date: Option<DateTime<FixedOffset>>,
 }
 
 impl IsoHeader {
@@ -293,23 +425,84 @@ impl IsoHeader {
     }
 
     pub fn set_bibliographic_file_id<P: Into<String>>(&mut self, bibliographic_file_id: P) {
-        self.bibliographic_file_id = Some(bibliographic_file_id);
+        self.bibliographic_file_id = Some(bibliographic_file_id.into());
     }
 
-    pub fn set_volume_creation_date(&mut self, volume_creation_date: DateTime<Utc>) {
-        self.volume_creation_date = Some(volume_creation_date);
+    /// Keeps the offset from GMT of `volume_creation_date`.
+    pub fn set_volume_creation_date<T: Into<DateTime<FixedOffset>>>(
+        &mut self,
+        volume_creation_date: T,
+    ) {
+        self.volume_creation_date = Some(volume_creation_date.into());
+    }
+
+    pub fn volume_creation_date(&self) -> Option<DateTime<FixedOffset>> {
+        self.volume_creation_date
     }
 
-    pub fn set_volume_modification_date(&mut self, volume_modification_date: DateTime<Utc>) {
-        self.volume_modification_date = Some(volume_modification_date);
+    /// Keeps the offset from GMT of `volume_modification_date`.
+    pub fn set_volume_modification_date<T: Into<DateTime<FixedOffset>>>(
+        &mut self,
+        volume_modification_date: T,
+    ) {
+        self.volume_modification_date = Some(volume_modification_date.into());
     }
 
-    pub fn set_volume_expiration_date(&mut self, volume_expiration_date: DateTime<Utc>) {
-        self.volume_expiration_date = Some(volume_expiration_date);
+    pub fn volume_modification_date(&self) -> Option<DateTime<FixedOffset>> {
+        self.volume_modification_date
+    }
+
+    /// Keeps the offset from GMT of `volume_expiration_date`.
+    pub fn set_volume_expiration_date<T: Into<DateTime<FixedOffset>>>(
+        &mut self,
+        volume_expiration_date: T,
+    ) {
+        self.volume_expiration_date = Some(volume_expiration_date.into());
     }
 
-    pub fn set_volume_effective_date(&mut self, volume_effective_date: DateTime<Utc>) {
-        self.volume_effective_date = Some(volume_effective_date);
+    pub fn volume_expiration_date(&self) -> Option<DateTime<FixedOffset>> {
+        self.volume_expiration_date
+    }
+
+    /// Keeps the offset from GMT of `volume_effective_date`.
+    pub fn set_volume_effective_date<T: Into<DateTime<FixedOffset>>>(
+        &mut self,
+        volume_effective_date: T,
+    ) {
+        self.volume_effective_date = Some(volume_effective_date.into());
+    }
+
+    pub fn volume_effective_date(&self) -> Option<DateTime<FixedOffset>> {
+        self.volume_effective_date
+    }
+
+    /// Size of the blocks files and directories are addressed in, 512, 1024
+    /// or 2048 for the writer. Descriptors keep their 2048-byte sectors.
+    pub fn set_logical_block_size(&mut self, logical_block_size: u16) {
+        self.logical_block_size = logical_block_size;
+    }
+
+    pub fn logical_block_size(&self) -> u16 {
+        self.logical_block_size
+    }
+
+    /// Number of volumes in the set, 1 when written if left at 0.
+    pub fn set_volume_set_size(&mut self, volume_set_size: u16) {
+        self.volume_set_size = volume_set_size;
+    }
+
+    pub fn volume_set_size(&self) -> u16 {
+        self.volume_set_size
+    }
+
+    /// Number of this volume in the set, 1 when written if left at 0. Directory
+    /// records of the writer refer to it unless overridden per file.
+    pub fn set_volume_sequence_number(&mut self, volume_sequence_number: u16) {
+        self.volume_sequence_number = volume_sequence_number;
+    }
+
+    pub fn volume_sequence_number(&self) -> u16 {
+        self.volume_sequence_number
     }
 
     pub(crate) fn into_raw(self, root_directory: RootDirectoryEntry) -> Result<IsoHeaderRaw> {
@@ -344,9 +537,16 @@ impl IsoHeader {
 
 impl From<&IsoHeaderRaw> for IsoHeader {
     fn from(raw: &IsoHeaderRaw) -> Self {
+        Self::decode(raw, Charset::default())
+    }
+}
+
+impl IsoHeader {
+    /// Header of a raw descriptor, strings decoded with `charset`.
+    pub(crate) fn decode(raw: &IsoHeaderRaw, charset: Charset) -> Self {
         Self {
-            system_id: utf8_trimmed!(&raw.system_id),
-            volumen_id: utf8_trimmed!(&raw.volumen_id),
+            system_id: trimmed!(charset, &raw.system_id),
+            volumen_id: trimmed!(charset, &raw.volumen_id),
             volume_space_size: raw.volume_space_size.lsb(),
             volume_set_size: raw.volume_set_size.lsb(),
             volume_sequence_number: raw.volume_sequence_number.lsb(),
@@ -356,13 +556,13 @@ impl From<&IsoHeaderRaw> for IsoHeader {
             loc_of_opti_l_path_table: raw.loc_of_opti_l_path_table,
             loc_of_type_m_path_table: raw.loc_of_type_m_path_table.to_be(),
             loc_of_opti_m_path_table: raw.loc_of_opti_m_path_table.to_be(),
-            volume_set_id: utf8_trimmed!(&raw.volume_set_id),
-            publisher_id: utf8_trimmed!(&raw.publisher_id),
-            data_preparer_id: utf8_trimmed!(&raw.data_preparer_id),
-            application_id: utf8_trimmed!(&raw.application_id),
-            copyright_file_id: utf8_trimmed!(&raw.copyright_file_id),
-            abstract_file_id: utf8_trimmed!(&raw.abstract_file_id),
-            bibliographic_file_id: utf8_trimmed!(&raw.bibliographic_file_id),
+            volume_set_id: trimmed!(charset, &raw.volume_set_id),
+            publisher_id: trimmed!(charset, &raw.publisher_id),
+            data_preparer_id: trimmed!(charset, &raw.data_preparer_id),
+            application_id: trimmed!(charset, &raw.application_id),
+            copyright_file_id: trimmed!(charset, &raw.copyright_file_id),
+            abstract_file_id: trimmed!(charset, &raw.abstract_file_id),
+            bibliographic_file_id: trimmed!(charset, &raw.bibliographic_file_id),
             volume_creation_date: raw.volume_creation_date.try_into().ok(),
             volume_modification_date: raw.volume_modification_date.try_into().ok(),
             volume_expiration_date: raw.volume_expiration_date.try_into().ok(),
@@ -398,10 +598,10 @@ impl Default for IsoHeader {
             copyright_file_id: None,
             abstract_file_id: None,
             bibliographic_file_id: None,
-            volume_creation_date: Some(Utc::now()),
-            volume_modification_date: Some(Utc::now()),
+            volume_creation_date: Some(Utc::now().into()),
+            volume_modification_date: Some(Utc::now().into()),
             volume_expiration_date: None,
-            volume_effective_date: Some(Utc::now()),
+            volume_effective_date: Some(Utc::now().into()),
         }
     }
 }
@@ -458,9 +658,100 @@ impl IsoDirectoryHeader {
         }
     }
 
+    /// Length in blocks of the extended attribute record before the data.
+    pub fn extended_attribute_length(&self) -> u8 {
+        self.extended_attribute_length
+    }
+
+    /// First block of the data, after the extended attribute record.
+    pub fn data_location(&self) -> u32 {
+        self.location(None) + u32::from(self.extended_attribute_length)
+    }
+
+    /// Volume of the volume set holding the extent.
+    pub fn volume_seq_number(&self) -> u16 {
+        self.volume_seq_number.lsb()
+    }
+
     pub fn set_location(&mut self, location: usize) {
         self.location_of_extent = LsbMsb::new_u32(location as u32);
     }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.flags & 0x02 != 0
+    }
+
+    /// Set on every record of a multi-extent file except the last one.
+    pub fn is_multi_extent(&self) -> bool {
+        self.flags & 0x80 != 0
+    }
+
+    /// Set on the associated file sharing the name of a file.
+    pub fn is_associated(&self) -> bool {
+        self.flags & 0x04 != 0
+    }
+
+    /// Existence bit, set on entries that should not be shown to the user.
+    pub fn is_hidden(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+
+    pub fn datetime(&self) -> Result<DateTime<Utc>> {
+        self.datetime.try_into()
+    }
+
+    /// Both-endian fields whose halves differ.
+    pub(crate) fn endian_mismatches(&self) -> Vec<&'static str> {
+        [
+            (
+                "location_of_extent",
+                self.location_of_extent.is_consistent(),
+            ),
+            ("data_length", self.data_length.is_consistent()),
+            ("volume_seq_number", self.volume_seq_number.is_consistent()),
+        ]
+        .into_iter()
+        .filter(|(_, consistent)| !consistent)
+        .map(|(field, _)| field)
+        .collect()
+    }
+
+    /// Recording date and time with the offset from GMT of the record.
+    pub fn datetime_with_offset(&self) -> Result<DateTime<FixedOffset>> {
+        self.datetime.try_into()
+    }
+}
+
+/// Summary of a directory record.
+#[derive(Debug, Clone)]
+pub struct FileMetadata {
+    /// Data length in bytes.
+    pub size: u64,
+    /// Rock Ridge modification time if recorded, otherwise the recording date
+    /// and time of the record. `None` if neither holds a valid date.
+    pub datetime: Option<DateTime<Utc>>,
+    pub is_dir: bool,
+    /// Rock Ridge symbolic link, see [`IsoDirectoryEntry::symlink_target`].
+    pub is_symlink: bool,
+    pub hidden: bool,
+    /// Logical block number of the extent.
+    pub location: u32,
+}
+
+/// Totals over the entries of an image, see [`IsoDirectoryEntries::stats`].
+#[derive(Debug, Clone, Default)]
+pub struct IsoStats {
+    pub files: usize,
+    /// Directories not counting the root.
+    pub directories: usize,
+    /// Sum of the data length of every file.
+    pub total_size: u64,
+    pub deepest_path: Option<PathBuf>,
+    pub largest_file: Option<(PathBuf, u64)>,
 }
 
 #[derive(Debug, Clone)]
@@ -468,28 +759,32 @@ pub struct IsoDirectoryEntry {
     entry: IsoEntry,
     record: IsoDirectoryHeader,
     is_odd: bool,
+    session: usize,
+    primary_name: Option<String>,
+    joliet_name: Option<String>,
+    rock_ridge: RockRidge,
+    system_use: Vec<u8>,
+    // block and length of the extents before the one of `record`, for a
+    // multi-extent file
+    parts: Vec<(u32, u32)>,
+    version: Option<u16>,
+    trans_name: Option<String>,
 }
 
 impl IsoDirectoryEntry {
     pub(crate) fn new(
         data_offset: usize,
         data_length: usize,
-        timestamp: &DateTime<Utc>,
+        timestamp: &DateTime<FixedOffset>,
         entry: IsoEntry,
     ) -> Self {
-        let name = entry.name();
-        let name_bytes = name.as_bytes();
-        let id_len = name_bytes.len();
-
-        let real_length = 33 + id_len as u8;
-        let length = (real_length + 1) & !1;
-
         let flags = if entry.is_file() { 0 } else { 2 };
+        let version = entry.is_file().then_some(1);
 
-        Self {
+        let mut entry = Self {
             entry,
             record: IsoDirectoryHeader {
-                length,
+                length: 0,
                 extended_attribute_length: 0,
                 location_of_extent: LsbMsb::new_u32(data_offset as u32),
                 data_length: LsbMsb::new_u32(data_length as u32),
@@ -497,10 +792,67 @@ impl IsoDirectoryEntry {
                 flags,
                 unit_size: 0,
                 interleave_gap_size: 0,
-                volume_seq_number: LsbMsb::new_u16(256),
-                file_identifier_length: id_len as u8,
+                volume_seq_number: LsbMsb::new_u16(1),
+                file_identifier_length: 0,
             },
-            is_odd: real_length != length,
+            is_odd: false,
+            session: 0,
+            primary_name: None,
+            joliet_name: None,
+            rock_ridge: RockRidge::default(),
+            system_use: Vec::new(),
+            parts: Vec::new(),
+            version,
+            trans_name: None,
+        };
+
+        entry.set_version(version);
+        entry
+    }
+
+    // version written after the identifier of a file, sizing the record again
+    pub(crate) fn set_version(&mut self, version: Option<u16>) {
+        self.version = version;
+        self.resize();
+    }
+
+    // system use area written after the identifier, sizing the record again
+    pub(crate) fn set_system_use(&mut self, system_use: Vec<u8>) {
+        self.system_use = system_use;
+        self.resize();
+    }
+
+    // record length for the identifier and the system use area, both starting
+    // at an even offset
+    fn resize(&mut self) {
+        let id_len = self.identifier().len();
+        let padded = (33 + id_len).next_multiple_of(2);
+
+        self.record.length = (padded + self.system_use.len()).next_multiple_of(2) as u8;
+        self.record.file_identifier_length = id_len as u8;
+        self.is_odd = padded != 33 + id_len;
+    }
+
+    // volume of the set holding the extent
+    pub(crate) fn set_volume_seq_number(&mut self, volume_seq_number: u16) {
+        self.record.volume_seq_number = LsbMsb::new_u16(volume_seq_number);
+    }
+
+    // existence bit of the record, keeping listings from showing the entry
+    pub(crate) fn set_hidden(&mut self, hidden: bool) {
+        if hidden {
+            self.record.flags |= 0x01;
+        } else {
+            self.record.flags &= !0x01;
+        }
+    }
+
+    // file identifier as written, with the `;N` suffix of a file if it has one
+    fn identifier(&self) -> String {
+        match (&self.entry, self.version) {
+            (IsoEntry::File(t), Some(version)) => format!("{t};{version}"),
+            (IsoEntry::File(t), None) => t.clone(),
+            (entry, _) => entry.name(),
         }
     }
 
@@ -509,7 +861,7 @@ impl IsoDirectoryEntry {
     }
 
     pub(crate) async fn write<W: AsyncWriteExt + Unpin>(&self, writer: &mut W) -> Result<usize> {
-        let name = self.entry.name();
+        let name = self.identifier();
         let name_bytes = name.as_bytes();
 
         let size = mem::size_of::<IsoDirectoryHeader>();
@@ -526,7 +878,13 @@ impl IsoDirectoryEntry {
             0
         };
 
-        Ok(byte_slice.len() + name_bytes.len() + odd_size)
+        writer.write_all(&self.system_use).await?;
+
+        let written = byte_slice.len() + name_bytes.len() + odd_size + self.system_use.len();
+        let padding = self.len() - written;
+        writer.write_all(&[0; 1][..padding]).await?;
+
+        Ok(self.len())
     }
 
     pub fn entry(&self) -> &IsoEntry {
@@ -540,89 +898,790 @@ impl IsoDirectoryEntry {
     pub fn record_mut(&mut self) -> &mut IsoDirectoryHeader {
         &mut self.record
     }
+
+    /// Index of the session the entry was read from, 0 for the first one.
+    pub fn session(&self) -> usize {
+        self.session
+    }
+
+    /// Existence bit of the record, see [`IsoReadOptions::set_hidden`](crate::IsoReadOptions::set_hidden).
+    pub fn is_hidden(&self) -> bool {
+        self.record.is_hidden()
+    }
+
+    /// ISO 9660 name of the entry, also known for Joliet entries matched in the
+    /// primary tree with [`IsoReadOptions::set_paired_names`](crate::IsoReadOptions::set_paired_names)
+    /// when the whole tree is read.
+    pub fn primary_name(&self) -> Option<&str> {
+        self.primary_name.as_deref()
+    }
+
+    /// Joliet long name of the entry, for primary entries when a Joliet tree
+    /// is present and [`IsoReadOptions::set_paired_names`](crate::IsoReadOptions::set_paired_names)
+    /// is set.
+    pub fn joliet_name(&self) -> Option<&str> {
+        self.joliet_name.as_deref()
+    }
+
+    /// POSIX name from the Rock Ridge `NM` entries.
+    pub fn rock_ridge_name(&self) -> Option<&str> {
+        self.rock_ridge.name.as_deref()
+    }
+
+    /// Permissions and ownership from the Rock Ridge `PX` entry.
+    pub fn posix_attributes(&self) -> Option<PosixAttributes> {
+        self.rock_ridge.posix
+    }
+
+    /// Timestamps from the Rock Ridge `TF` entry.
+    pub fn rock_ridge_times(&self) -> &RockRidgeTimes {
+        &self.rock_ridge.times
+    }
+
+    /// Target of a symbolic link recorded with Rock Ridge `SL` entries.
+    pub fn symlink_target(&self) -> Option<&Path> {
+        self.rock_ridge.symlink.as_deref()
+    }
+
+    /// True for files stored compressed with zisofs, they are inflated on read.
+    pub fn is_compressed(&self) -> bool {
+        self.rock_ridge.zisofs.is_some()
+    }
+
+    pub(crate) fn zisofs(&self) -> Option<Zisofs> {
+        self.rock_ridge.zisofs
+    }
+
+    /// Original name from the `TRANS.TBL` of the parent directory, see
+    /// [`IsoReadOptions::set_trans_tbl`](crate::IsoReadOptions::set_trans_tbl).
+    pub fn trans_name(&self) -> Option<&str> {
+        self.trans_name.as_deref()
+    }
+
+    /// File version from the `;N` suffix of the identifier.
+    pub fn version(&self) -> Option<u16> {
+        self.version
+    }
+
+    /// Block and length of every extent of the file, in order. Files of 4 GiB
+    /// and more are recorded as several extents.
+    pub fn extents(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let last = (self.record.data_location(), self.record.data_length());
+        self.parts.iter().copied().chain(std::iter::once(last))
+    }
+
+    /// Size in bytes as stored, summing every extent of a multi-extent file.
+    pub fn size(&self) -> u64 {
+        self.extents().map(|(_, length)| u64::from(length)).sum()
+    }
+
+    /// Type, creator and Finder flags from the Apple extensions.
+    pub fn apple_attributes(&self) -> Option<AppleAttributes> {
+        AppleAttributes::parse(self.extension_area())
+    }
+
+    /// Attributes from the CD-ROM XA record.
+    pub fn xa_attributes(&self) -> Option<XaAttributes> {
+        XaAttributes::parse(&self.system_use)
+    }
+
+    // system use area past the XA record, if any
+    fn extension_area(&self) -> &[u8] {
+        match self.xa_attributes() {
+            Some(_) => &self.system_use[XA_SIZE..],
+            None => &self.system_use,
+        }
+    }
+
+    /// System use area of the record, after the file identifier.
+    pub fn system_use(&self) -> &[u8] {
+        &self.system_use
+    }
+
+    pub fn metadata(&self) -> FileMetadata {
+        FileMetadata {
+            size: match self.rock_ridge.zisofs {
+                Some(zisofs) => u64::from(zisofs.size),
+                None => self.size(),
+            },
+            datetime: self
+                .rock_ridge
+                .times
+                .modified
+                .or_else(|| self.record.datetime().ok()),
+            is_dir: !self.entry.is_file(),
+            is_symlink: self.rock_ridge.symlink.is_some(),
+            hidden: self.record.is_hidden(),
+            location: self.record.data_location(),
+        }
+    }
 }
 
-#[derive(Debug, Default)]
-pub struct IsoDirectoryEntries(BTreeMap<PathBuf, IsoDirectoryEntry>);
+#[derive(Debug, Default, Clone)]
+pub struct IsoDirectoryEntries {
+    entries: BTreeMap<PathBuf, IsoDirectoryEntry>,
+    // associated files, by the path of the file they belong to
+    associated: BTreeMap<PathBuf, IsoDirectoryEntry>,
+    // files superseded by a higher version, by their path with the `;N` suffix
+    versions: BTreeMap<PathBuf, IsoDirectoryEntry>,
+    // directories found but not parsed yet, with the byte offset of their extent
+    // and its length if known, and the session they belong to
+    pending: BTreeMap<PathBuf, (u64, Option<u64>, usize)>,
+    limits: Limits,
+    // sessions whose tree is read from a Joliet descriptor
+    joliet_sessions: BTreeSet<usize>,
+    // sessions whose tree is read from an ISO 9660:1999 enhanced descriptor
+    enhanced_sessions: BTreeSet<usize>,
+    ignore_rock_ridge: bool,
+    exclude_hidden: bool,
+    trans_tbl: bool,
+    charset: Charset,
+    // SUSP skip length of the sessions using SUSP
+    susp_sessions: BTreeMap<usize, u8>,
+}
 
 impl IsoDirectoryEntries {
-    #[async_recursion(?Send)]
-    #[allow(clippy::multiple_bound_locations)]
+    pub(crate) fn with_limits(limits: Limits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    /// Decode identifiers of `session` as UCS-2.
+    pub(crate) fn set_joliet(&mut self, session: usize) {
+        self.joliet_sessions.insert(session);
+    }
+
+    pub(crate) fn is_joliet(&self, session: usize) -> bool {
+        self.joliet_sessions.contains(&session)
+    }
+
+    /// Identifiers of `session` are the long names of an enhanced descriptor.
+    pub(crate) fn set_enhanced(&mut self, session: usize) {
+        self.enhanced_sessions.insert(session);
+    }
+
+    pub(crate) fn set_rock_ridge(&mut self, rock_ridge: bool) {
+        self.ignore_rock_ridge = !rock_ridge;
+    }
+
+    pub(crate) fn set_hidden(&mut self, hidden: bool) {
+        self.exclude_hidden = !hidden;
+    }
+
+    pub(crate) fn set_trans_tbl(&mut self, trans_tbl: bool) {
+        self.trans_tbl = trans_tbl;
+    }
+
+    pub(crate) fn set_charset(&mut self, charset: Charset) {
+        self.charset = charset;
+    }
+
+    /// Reads the directory whose extent starts at `offset`. Without a `length`
+    /// the extent size is taken from its `.` record.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn read<R: AsyncRead + AsyncSeekExt + Unpin>(
         &mut self,
         reader: &mut R,
         base: &Path,
-        logical_block_size: u16,
-        mut offset: u32,
+        layout: BlockLayout,
+        offset: u64,
+        length: Option<u64>,
+        session: usize,
+        recursive: bool,
     ) -> Result<()> {
-        loop {
-            reader.seek(SeekFrom::Start(offset.into())).await?;
+        let mut walker = DirectoryWalker::new(layout, base.to_path_buf(), offset, length);
+        walker.set_recursive(recursive);
+        walker.set_max_depth(self.limits.max_depth);
+        walker.set_joliet(self.is_joliet(session));
+        walker.set_enhanced(self.enhanced_sessions.contains(&session));
+        walker.set_rock_ridge(!self.ignore_rock_ridge);
+        walker.set_hidden(!self.exclude_hidden);
+        walker.set_charset(self.charset);
+        walker.set_susp_skip(self.susp_sessions.get(&session).copied());
+
+        // name tables are only needed without Joliet or Rock Ridge names
+        let trans_tbl = self.trans_tbl && !self.is_joliet(session);
+        let mut tables = Vec::new();
+
+        while let Some((path, mut value)) = walker.next(reader).await? {
+            value.session = session;
+
+            if let Some(skip) = walker.susp_skip() {
+                self.susp_sessions.insert(session, skip);
+            }
+
+            if self.entries.len() >= self.limits.max_entries {
+                return Err(IsoFileError::EntryLimit(self.limits.max_entries));
+            }
+
+            match value.entry {
+                IsoEntry::CurrentDirectory => {
+                    // "/dir/." and "/dir" are the same path, keep the record from the parent
+                    _ = self.entries.entry(path).or_insert(value)
+                }
+                IsoEntry::ParentDirectory => _ = self.entries.insert(path, value),
+                IsoEntry::File(_) => {
+                    if trans_tbl && path.file_name().is_some_and(|t| t == "TRANS.TBL") {
+                        tables.push(path.clone());
+                    }
+
+                    let entries = if value.record.is_associated() {
+                        &mut self.associated
+                    } else {
+                        &mut self.entries
+                    };
+
+                    let previous = entries.get(&path).filter(|t| t.session == value.session);
+
+                    match previous {
+                        // the records of a multi-extent file follow each other
+                        // with the same name, all but the last flagged
+                        Some(previous) if previous.record.is_multi_extent() => {
+                            value.parts = previous.extents().collect()
+                        }
+                        // versions of a file share its path, which leads to the
+                        // highest, the others keep their suffix
+                        Some(previous) if previous.version != value.version => {
+                            let (lower, higher) = if previous.version > value.version {
+                                (value, previous.clone())
+                            } else {
+                                (previous.clone(), value)
+                            };
+
+                            if let (Some(name), Some(version)) = (path.file_name(), lower.version) {
+                                let id = format!("{};{version}", name.to_string_lossy());
+                                self.versions.insert(path.with_file_name(id), lower);
+                            }
+
+                            entries.insert(path, higher);
+                            continue;
+                        }
+                        _ => {}
+                    }
+
+                    entries.insert(path, value);
+                }
+                IsoEntry::Directory(_) => {
+                    let location = layout.offset(value.record.data_location());
+                    let length = u64::from(value.record.data_length());
+
+                    // a directory may have been read before its parent through the path table
+                    let loaded = self.entries.contains_key(&path) && !self.is_pending(&path);
+
+                    self.entries.insert(path.clone(), value);
+
+                    if !recursive && !loaded {
+                        self.pending.insert(path, (location, Some(length), session));
+                    }
+                }
+            }
+        }
+
+        for path in tables {
+            self.apply_trans_tbl(reader, layout, &path).await?;
+        }
+
+        Ok(())
+    }
+
+    // sets the original names listed in the TRANS.TBL file at `path` on its
+    // siblings that have no Rock Ridge name
+    async fn apply_trans_tbl<R: AsyncRead + AsyncSeekExt + Unpin>(
+        &mut self,
+        reader: &mut R,
+        layout: BlockLayout,
+        path: &Path,
+    ) -> Result<()> {
+        let Some(record) = self.entries.get(path).map(|t| t.record.clone()) else {
+            return Ok(());
+        };
+
+        let length = u64::from(record.data_length());
+
+        if length > self.limits.max_allocation {
+            return Err(IsoFileError::AllocationLimit(length));
+        }
+
+        reader
+            .seek(SeekFrom::Start(layout.offset(record.data_location())))
+            .await?;
+
+        let mut content = vec![0u8; length as usize];
+        reader.read_exact(&mut content).await?;
+
+        let parent = path.parent().unwrap_or(Path::new("/"));
+
+        for (id, name) in trans_tbl::parse(&content) {
+            let sibling = parent.join(split_version(&id).0);
+
+            if let Some(entry) = self.entries.get_mut(&sibling)
+                && entry.rock_ridge.name.is_none()
+            {
+                entry.trans_name = Some(name);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses a directory that was skipped by a non-recursive read.
+    ///
+    /// Returns `false` if the directory was already loaded or is unknown.
+    pub(crate) async fn load<R: AsyncRead + AsyncSeekExt + Unpin>(
+        &mut self,
+        reader: &mut R,
+        path: &Path,
+        layout: BlockLayout,
+    ) -> Result<bool> {
+        match self.pending.remove(path) {
+            Some((location, length, session)) => {
+                self.read(reader, path, layout, location, length, session, false)
+                    .await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // marks a directory found outside the tree, e.g. in the path table
+    pub(crate) fn add_pending(&mut self, path: PathBuf, location: u64, session: usize) {
+        if !self.entries.contains_key(&path) {
+            self.pending.insert(path, (location, None, session));
+        }
+    }
+
+    pub(crate) fn pending(&self) -> impl Iterator<Item = &Path> {
+        self.pending.keys().map(PathBuf::as_path)
+    }
+
+    /// True if `path` is a directory whose contents have not been read yet.
+    pub fn is_pending(&self, path: &Path) -> bool {
+        self.pending.contains_key(path)
+    }
+
+    /// Associated file recorded with the same name as the file at `path`.
+    pub fn associated(&self, path: &Path) -> Option<&IsoDirectoryEntry> {
+        self.associated.get(path)
+    }
+
+    /// Looks up an entry, a `;N` suffix finds version N of a file and none its
+    /// highest version.
+    pub fn get(&self, path: &Path) -> Option<&IsoDirectoryEntry> {
+        self.entries.get(path).or_else(|| {
+            let name = path.file_name()?.to_str()?;
+            let (stem, version) = split_version(name);
+            let entry = self.entries.get(&path.with_file_name(stem))?;
+
+            if version.is_some() && entry.version == version {
+                Some(entry)
+            } else {
+                self.versions.get(path)
+            }
+        })
+    }
+
+    /// Depth-first traversal over every entry, parents before their children.
+    ///
+    /// When `skip_pseudo` is set the `.` and `..` entries are left out.
+    pub fn walk(&self, skip_pseudo: bool) -> impl Iterator<Item = (PathBuf, &IsoDirectoryEntry)> {
+        self.entries
+            .iter()
+            .filter(move |(_, value)| {
+                !skip_pseudo || value.entry().is_directory() || value.entry().is_file()
+            })
+            .map(|(key, value)| (key.clone(), value))
+    }
 
-            let record = IsoDirectoryHeader::read(reader).await?;
+    /// Copies the names of the matching entries of `other`, a tree read from
+    /// another descriptor of the same volume. Files are matched by extent and
+    /// directories by the extents of the files below them, or by name when
+    /// they hold none.
+    pub(crate) fn pair_names(&mut self, other: &IsoDirectoryEntries) {
+        let mut stack = vec![(PathBuf::from("/"), PathBuf::from("/"))];
+
+        while let Some((ours, theirs)) = stack.pop() {
+            let mut candidates: Vec<_> = other
+                .children(&theirs)
+                .into_iter()
+                .map(|(path, value)| {
+                    let extents = other.file_extents(&path);
+                    (path, value, extents)
+                })
+                .collect();
+            let mut pairs = Vec::new();
+
+            for (path, value) in self.children(&ours) {
+                let found =
+                    match value.entry() {
+                        IsoEntry::File(_) => candidates.iter().position(|(_, t, _)| {
+                            t.entry().is_file()
+                                && t.record().location(None) == value.record().location(None)
+                        }),
+                        IsoEntry::Directory(name) => {
+                            let extents = self.file_extents(&path);
+                            let name = normalize_name(name);
+
+                            let mut matching = candidates.iter().enumerate().filter_map(
+                                |(index, (_, t, other))| {
+                                    let same = match t.entry() {
+                                        IsoEntry::Directory(_) if !extents.is_empty() => {
+                                            *other == extents
+                                        }
+                                        IsoEntry::Directory(t) => {
+                                            other.is_empty() && normalize_name(t) == name
+                                        }
+                                        _ => false,
+                                    };
+
+                                    same.then_some(index)
+                                },
+                            );
+
+                            // an ambiguous match is left unpaired
+                            matching.next().filter(|_| matching.next().is_none())
+                        }
+                        _ => None,
+                    };
+
+                if let Some(index) = found {
+                    let (other_path, other_value, _) = candidates.swap_remove(index);
+
+                    if value.entry().is_directory() {
+                        stack.push((path.clone(), other_path));
+                    }
+
+                    pairs.push((path, other_value));
+                }
+            }
+
+            for (path, other_value) in pairs {
+                if let Some(value) = self.entries.get_mut(&path) {
+                    value.primary_name = value
+                        .primary_name
+                        .take()
+                        .or(other_value.primary_name.clone());
+                    value.joliet_name =
+                        value.joliet_name.take().or(other_value.joliet_name.clone());
+                }
+            }
+        }
+    }
+
+    // sorted extents of the files with data below a directory
+    fn file_extents(&self, path: &Path) -> Vec<u32> {
+        let mut extents: Vec<u32> = self
+            .entries
+            .range::<Path, _>((Bound::Excluded(path), Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(path))
+            .filter(|(_, value)| value.entry().is_file() && value.record().data_length() > 0)
+            .map(|(_, value)| value.record().location(None))
+            .collect();
+
+        extents.sort_unstable();
+        extents
+    }
+
+    // direct children of a directory without "." and ".."
+    fn children(&self, path: &Path) -> Vec<(PathBuf, &IsoDirectoryEntry)> {
+        self.entries
+            .range::<Path, _>((Bound::Excluded(path), Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(path))
+            .filter(|(key, value)| {
+                key.parent() == Some(path)
+                    && (value.entry().is_file() || value.entry().is_directory())
+            })
+            .map(|(key, value)| (key.clone(), value))
+            .collect()
+    }
+
+    /// Counts of the entries read so far, directories skipped by lazy mode
+    /// are not included.
+    pub fn stats(&self) -> IsoStats {
+        let mut stats = IsoStats::default();
+        let mut depth = 0;
+
+        for (path, value) in self.walk(true) {
+            let size = value.size();
+
+            if value.entry().is_file() {
+                stats.files += 1;
+                stats.total_size += size;
+
+                if stats.largest_file.as_ref().is_none_or(|(_, t)| size > *t) {
+                    stats.largest_file = Some((path.clone(), size));
+                }
+            } else {
+                stats.directories += 1;
+            }
+
+            if path.components().count() > depth {
+                depth = path.components().count();
+                stats.deepest_path = Some(path);
+            }
+        }
+
+        stats
+    }
+
+    /// Direct children of a directory, without the `.` and `..` entries.
+    ///
+    /// Returns `None` if `path` is not a directory.
+    pub fn list_dir(&self, path: &Path) -> Option<Vec<(String, &IsoEntry, &IsoDirectoryHeader)>> {
+        match self.entries.get(path)?.entry() {
+            IsoEntry::CurrentDirectory | IsoEntry::Directory(_) => {}
+            _ => return None,
+        }
+
+        let children = self
+            .entries
+            .range::<Path, _>((Bound::Excluded(path), Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(path))
+            .filter(|(key, _)| key.parent() == Some(path))
+            .filter_map(|(_, value)| match value.entry() {
+                IsoEntry::Directory(name) | IsoEntry::File(name) => {
+                    Some((name.clone(), value.entry(), value.record()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        Some(children)
+    }
+}
+
+/// Directory traversal with an explicit stack, decoding one record per call.
+#[derive(Debug)]
+pub(crate) struct DirectoryWalker {
+    layout: BlockLayout,
+    recursive: bool,
+    joliet: bool,
+    enhanced: bool,
+    rock_ridge: bool,
+    hidden: bool,
+    charset: Charset,
+    // set once the root "." record shows SUSP is in use
+    susp_skip: Option<u8>,
+    max_depth: usize,
+    // directory path, byte offset of its next record and end of its extent
+    stack: Vec<(PathBuf, u64, Option<u64>)>,
+}
+
+impl DirectoryWalker {
+    pub(crate) fn new(
+        layout: BlockLayout,
+        path: PathBuf,
+        offset: u64,
+        length: Option<u64>,
+    ) -> Self {
+        Self {
+            layout,
+            recursive: true,
+            joliet: false,
+            enhanced: false,
+            rock_ridge: true,
+            hidden: true,
+            charset: Charset::default(),
+            susp_skip: None,
+            max_depth: Limits::default().max_depth,
+            stack: vec![(path, offset, length.map(|t| offset + t))],
+        }
+    }
+
+    /// Descend into subdirectories, on by default.
+    pub(crate) fn set_recursive(&mut self, recursive: bool) {
+        self.recursive = recursive;
+    }
+
+    pub(crate) fn set_joliet(&mut self, joliet: bool) {
+        self.joliet = joliet;
+    }
+
+    pub(crate) fn set_enhanced(&mut self, enhanced: bool) {
+        self.enhanced = enhanced;
+    }
+
+    /// Prefer Rock Ridge names when the tree uses SUSP, on by default.
+    pub(crate) fn set_rock_ridge(&mut self, rock_ridge: bool) {
+        self.rock_ridge = rock_ridge;
+    }
+
+    /// Yield entries with the existence bit set, on by default. The contents
+    /// of hidden directories are skipped with them.
+    pub(crate) fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+
+    /// Encoding of identifiers outside Joliet trees.
+    pub(crate) fn set_charset(&mut self, charset: Charset) {
+        self.charset = charset;
+    }
+
+    pub(crate) fn set_susp_skip(&mut self, susp_skip: Option<u8>) {
+        self.susp_skip = susp_skip;
+    }
+
+    pub(crate) fn susp_skip(&self) -> Option<u8> {
+        self.susp_skip
+    }
+
+    pub(crate) fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    pub(crate) async fn next<R: AsyncRead + AsyncSeekExt + Unpin>(
+        &mut self,
+        reader: &mut R,
+    ) -> Result<Option<(PathBuf, IsoDirectoryEntry)>> {
+        while let Some((base, offset, end)) = self.stack.last_mut() {
+            if end.is_some_and(|end| *offset >= end) {
+                self.stack.pop();
+                continue;
+            }
+
+            reader.seek(SeekFrom::Start(*offset)).await?;
+
+            let mut record = IsoDirectoryHeader::read(reader).await?;
 
             if record.is_empty() {
-                break;
+                // records never cross a sector, the rest of the sector is padding
+                let sector = LOGICAL_BLOCK_SIZE as u64;
+                let next =
+                    self.layout.base() + ((*offset - self.layout.base()) / sector + 1) * sector;
+
+                match end {
+                    Some(end) if next < *end => *offset = next,
+                    _ => _ = self.stack.pop(),
+                }
+
+                continue;
             }
 
             let mut file_id_buffer = vec![0u8; record.file_identifier_length()];
             reader.read_exact(&mut file_id_buffer).await?;
 
-            offset += record.length();
+            // padding byte and system use area
+            let rest = (record.length() as usize).saturating_sub(33 + file_id_buffer.len());
+            let mut system_use = vec![0u8; rest];
+            reader.read_exact(&mut system_use).await?;
 
-            let entry = IsoEntry::from(file_id_buffer);
+            if file_id_buffer.len() % 2 == 0 && !system_use.is_empty() {
+                system_use.remove(0);
+            }
 
+            let charset = (!self.joliet).then_some(self.charset);
+            let mut entry = IsoEntry::decode(&file_id_buffer, record.is_directory(), charset);
+            let version = match &entry {
+                IsoEntry::File(_) => split_version(&decode_id(&file_id_buffer, charset)).1,
+                _ => None,
+            };
             let is_odd = record.file_identifier_length() % 2 != 0;
 
-            match entry {
-                IsoEntry::CurrentDirectory => {
-                    _ = self.0.insert(
-                        base.join("."),
-                        IsoDirectoryEntry {
-                            entry,
-                            record,
-                            is_odd,
-                        },
-                    )
-                }
-                IsoEntry::ParentDirectory => {
-                    _ = self.0.insert(
-                        base.join(".."),
-                        IsoDirectoryEntry {
-                            entry,
-                            record,
-                            is_odd,
-                        },
-                    )
+            // SUSP is announced in the "." record of the root
+            let is_root = base.as_path() == Path::new("/");
+
+            if is_root && matches!(entry, IsoEntry::CurrentDirectory) && self.rock_ridge {
+                self.susp_skip = self.susp_skip.or(rock_ridge::susp_skip(&system_use));
+            }
+
+            let name = match &entry {
+                IsoEntry::Directory(t) | IsoEntry::File(t) => Some(t.clone()),
+                _ => None,
+            };
+
+            let rock_ridge = match self
+                .susp_skip
+                .and_then(|skip| system_use.get(usize::from(skip)..))
+            {
+                Some(area) => {
+                    RockRidge::parse(&susp::read_entries(reader, self.layout, area).await?)
                 }
-                IsoEntry::File(ref t) => {
-                    _ = self.0.insert(
-                        base.join(t),
-                        IsoDirectoryEntry {
-                            entry,
-                            record,
-                            is_odd,
-                        },
-                    )
+                None => RockRidge::default(),
+            };
+
+            if let Some(name) = &rock_ridge.name {
+                entry = match entry {
+                    IsoEntry::Directory(_) => IsoEntry::Directory(name.clone()),
+                    IsoEntry::File(_) => IsoEntry::File(name.clone()),
+                    t => t,
+                };
+            }
+
+            // the "." record gives the extent size when the parent record is unknown
+            if end.is_none() && matches!(entry, IsoEntry::CurrentDirectory) {
+                *end = Some(*offset + u64::from(record.data_length()));
+            }
+
+            *offset += u64::from(record.length());
+
+            // deep directories are moved away and listed where their CL entry is
+            if rock_ridge.relocated {
+                continue;
+            }
+
+            if !self.hidden && record.is_hidden() && (entry.is_file() || entry.is_directory()) {
+                continue;
+            }
+
+            if let Some(location) = rock_ridge.child_link {
+                // the "." record of the moved directory stands in for the placeholder
+                reader
+                    .seek(SeekFrom::Start(self.layout.offset(location)))
+                    .await?;
+                record = IsoDirectoryHeader::read(reader).await?;
+
+                if let IsoEntry::File(t) = entry {
+                    entry = IsoEntry::Directory(t);
                 }
-                IsoEntry::Directory(ref t) => {
-                    if entry.is_directory() {
-                        self.read(
-                            reader,
-                            &base.join(t),
-                            logical_block_size,
-                            record.location(Some(logical_block_size)),
-                        )
-                        .await?;
-                    }
+            }
+
+            let path = match &entry {
+                IsoEntry::CurrentDirectory => base.clone(),
+                IsoEntry::ParentDirectory => base.join(".."),
+                IsoEntry::File(t) => base.join(t),
+                IsoEntry::Directory(t) => base.join(t),
+            };
+
+            if self.recursive && entry.is_directory() {
+                // the root is at depth 0
+                if path.components().count() > self.max_depth + 1 {
+                    return Err(IsoFileError::DepthLimit(self.max_depth));
                 }
+
+                let location = self.layout.offset(record.data_location());
+                let end = location + u64::from(record.data_length());
+                self.stack.push((path.clone(), location, Some(end)));
             }
-        }
 
-        Ok(())
-    }
+            // names of an enhanced tree are neither, the primary one is paired later
+            let (primary_name, joliet_name) = if self.joliet {
+                (None, name)
+            } else if self.enhanced {
+                (None, None)
+            } else {
+                (name, None)
+            };
+
+            return Ok(Some((
+                path,
+                IsoDirectoryEntry {
+                    entry,
+                    record,
+                    is_odd,
+                    session: 0,
+                    primary_name,
+                    joliet_name,
+                    rock_ridge,
+                    system_use,
+                    parts: Vec::new(),
+                    version,
+                    trans_name: None,
+                },
+            )));
+        }
 
-    pub fn get(&self, path: &Path) -> Option<&IsoDirectoryEntry> {
-        self.0.get(path)
+        Ok(None)
     }
 }
 
@@ -655,33 +1714,84 @@ impl IsoEntry {
         }
     }
 
+    /// Identifier without the `;N` version of a file, which its record keeps.
     pub fn name(&self) -> String {
         match self {
             IsoEntry::CurrentDirectory => "\0".to_string(),
             IsoEntry::ParentDirectory => "\u{1}".to_string(),
             IsoEntry::Directory(t) => t.to_string(),
-            IsoEntry::File(t) => {
-                format!("{};1", t)
+            IsoEntry::File(t) => t.to_string(),
+        }
+    }
+}
+
+impl IsoEntry {
+    // entry of a file identifier, classified by the directory flag of its record
+    pub(crate) fn decode(id: &[u8], directory: bool, charset: Option<Charset>) -> Self {
+        let name = match id {
+            [0] => return Self::CurrentDirectory,
+            [1] => return Self::ParentDirectory,
+            _ => decode_id(id, charset),
+        };
+
+        if directory {
+            Self::Directory(name)
+        } else {
+            Self::File(split_version(&name).0.to_string())
+        }
+    }
+}
+
+/// Splits the `;N` version suffix off a file identifier.
+pub(crate) fn split_version(name: &str) -> (&str, Option<u16>) {
+    match name.rsplit_once(';') {
+        Some((stem, version))
+            if !version.is_empty() && version.bytes().all(|t| t.is_ascii_digit()) =>
+        {
+            match version.parse() {
+                Ok(version) => (stem, Some(version)),
+                Err(_) => (name, None),
             }
         }
+        _ => (name, None),
+    }
+}
+
+// comparable form of a directory name from either tree
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|t| t.to_ascii_uppercase())
+        .collect()
+}
+
+// identifier in `charset`, UCS-2 for Joliet trees without one
+fn decode_id(id: &[u8], charset: Option<Charset>) -> String {
+    match charset {
+        Some(charset) => charset.decode(id),
+        None => decode_ucs2(id),
     }
 }
 
+/// Big-endian UCS-2 as used by Joliet, invalid code units are replaced.
+pub(crate) fn decode_ucs2(bytes: &[u8]) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|t| u16::from_be_bytes([t[0], t[1]]));
+
+    char::decode_utf16(units)
+        .map(|t| t.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Entry of an identifier without its record, a file when it ends with a
+/// `;N` version and a directory otherwise.
 impl From<Vec<u8>> for IsoEntry {
     fn from(src: Vec<u8>) -> Self {
-        let str = String::from_utf8_lossy(&src);
-
-        match str.as_ref() {
-            "\0" => IsoEntry::CurrentDirectory,
-            "\u{1}" => IsoEntry::ParentDirectory,
-            _ => {
-                if str.contains(";1") {
-                    IsoEntry::File(str.strip_suffix(";1").unwrap_or_default().to_string())
-                } else {
-                    IsoEntry::Directory(str.to_string())
-                }
-            }
-        }
+        let name = String::from_utf8_lossy(&src);
+        let directory = split_version(&name).1.is_none();
+
+        Self::decode(&src, directory, Some(Charset::Utf8))
     }
 }
 
@@ -696,6 +1806,15 @@ pub struct IsoPathTableEntryHeader {
     directory_number_of_parent_directory: u16,
 }
 
+impl IsoPathTableEntryHeader {
+    // converts to big-endian order and back, both are the same operation
+    fn convert_big_endian(&mut self) {
+        self.location_of_extent = self.location_of_extent.to_be();
+        self.directory_number_of_parent_directory =
+            self.directory_number_of_parent_directory.to_be();
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct IsoPathTableEntry {
     header: IsoPathTableEntryHeader,
@@ -703,6 +1822,19 @@ pub struct IsoPathTableEntry {
 }
 
 impl IsoPathTableEntry {
+    pub fn location(&self) -> u32 {
+        self.header.location_of_extent
+    }
+
+    /// 1-based number of the parent directory in the table.
+    pub fn parent(&self) -> u16 {
+        self.header.directory_number_of_parent_directory
+    }
+
+    pub fn directory_id(&self) -> &str {
+        &self.directory_id
+    }
+
     pub fn new<S: Into<String>>(location: usize, parent_directory: usize, directory_id: S) -> Self {
         let directory_id = directory_id.into();
 
@@ -729,10 +1861,63 @@ pub enum IsoPathTable {
 impl IsoPathTable {
     pub async fn read_l_table<R: AsyncRead + AsyncSeekExt + Unpin>(
         reader: &mut R,
-        location: u32,
+        location: u64,
     ) -> Result<Self> {
+        Self::read_l_table_limited(
+            reader,
+            location,
+            Limits::default(),
+            Some(Charset::default()),
+        )
+        .await
+    }
+
+    pub(crate) async fn read_l_table_limited<R: AsyncRead + AsyncSeekExt + Unpin>(
+        reader: &mut R,
+        location: u64,
+        limits: Limits,
+        charset: Option<Charset>,
+    ) -> Result<Self> {
+        let entries = Self::read_entries(reader, location, limits, charset).await?;
+        Ok(Self::LTable(entries))
+    }
+
+    /// Reads the big-endian M table, its entries are decoded to native order.
+    pub async fn read_m_table<R: AsyncRead + AsyncSeekExt + Unpin>(
+        reader: &mut R,
+        location: u64,
+    ) -> Result<Self> {
+        Self::read_m_table_limited(
+            reader,
+            location,
+            Limits::default(),
+            Some(Charset::default()),
+        )
+        .await
+    }
+
+    pub(crate) async fn read_m_table_limited<R: AsyncRead + AsyncSeekExt + Unpin>(
+        reader: &mut R,
+        location: u64,
+        limits: Limits,
+        charset: Option<Charset>,
+    ) -> Result<Self> {
+        let mut entries = Self::read_entries(reader, location, limits, charset).await?;
+        entries
+            .iter_mut()
+            .for_each(|t| t.header.convert_big_endian());
+
+        Ok(Self::MTable(entries))
+    }
+
+    async fn read_entries<R: AsyncRead + AsyncSeekExt + Unpin>(
+        reader: &mut R,
+        location: u64,
+        limits: Limits,
+        charset: Option<Charset>,
+    ) -> Result<Vec<IsoPathTableEntry>> {
         // go to table location
-        reader.seek(SeekFrom::Start(location.into())).await?;
+        reader.seek(SeekFrom::Start(location)).await?;
 
         let mut entries = Vec::new();
 
@@ -746,6 +1931,10 @@ impl IsoPathTable {
                 break;
             }
 
+            if entries.len() >= limits.max_entries {
+                return Err(IsoFileError::EntryLimit(limits.max_entries));
+            }
+
             let mut directory_id = vec![0u8; header.length.into()];
             reader.read_exact(&mut directory_id).await?;
 
@@ -754,26 +1943,83 @@ impl IsoPathTable {
                 let _ = reader.seek(SeekFrom::Current(1)).await?;
             }
 
+            // the root identifier is a single zero byte in both trees
+            let directory_id = if header.length > 1 {
+                decode_id(&directory_id, charset)
+            } else {
+                String::from_utf8_lossy(&directory_id).to_string()
+            };
+
             entries.push(IsoPathTableEntry {
                 header,
-                directory_id: String::from_utf8_lossy(&directory_id).to_string(),
+                directory_id,
             });
         }
 
-        Ok(Self::LTable(entries))
+        Ok(entries)
     }
 
-    pub fn as_vec(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+    /// True if the table starts with the root and every parent precedes its
+    /// children, as required for a table to be usable.
+    pub fn is_consistent(&self) -> bool {
+        let Some(root) = self.entries().first() else {
+            return false;
+        };
 
-        let entries = match self {
+        root.parent() == 1
+            && self
+                .entries()
+                .iter()
+                .enumerate()
+                .skip(1)
+                .all(|(index, entry)| (1..=index).contains(&usize::from(entry.parent())))
+    }
+
+    pub fn entries(&self) -> &[IsoPathTableEntry] {
+        match self {
             Self::LTable(t) => t,
             Self::MTable(t) => t,
-        };
+        }
+    }
+
+    /// Full path and extent location of every directory in the table.
+    ///
+    /// Entries whose parent does not precede them are skipped.
+    pub fn paths(&self) -> Vec<(PathBuf, u32)> {
+        let mut paths: Vec<Option<(PathBuf, u32)>> = Vec::new();
+
+        for (index, entry) in self.entries().iter().enumerate() {
+            let (location, parent) = (entry.location(), entry.parent());
+
+            let path = if index == 0 {
+                Some(PathBuf::from("/"))
+            } else {
+                usize::from(parent)
+                    .checked_sub(1)
+                    .filter(|&parent| parent < index)
+                    .and_then(|parent| paths[parent].as_ref())
+                    .map(|(parent, _)| parent.join(&entry.directory_id))
+            };
+
+            paths.push(path.map(|path| (path, location)));
+        }
+
+        paths.into_iter().flatten().collect()
+    }
+
+    pub fn as_vec(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for entry in self.entries() {
+            // M tables are big-endian on disc
+            let mut header = entry.header.clone();
+
+            if let Self::MTable(_) = self {
+                header.convert_big_endian();
+            }
 
-        for entry in entries {
             let size = mem::size_of::<IsoPathTableEntryHeader>();
-            let ptr = &entry.header as *const IsoPathTableEntryHeader as *const u8;
+            let ptr = &header as *const IsoPathTableEntryHeader as *const u8;
             let byte_slice: &[u8] = unsafe { slice::from_raw_parts(ptr, size) };
 
             bytes.extend_from_slice(byte_slice);
@@ -788,11 +2034,11 @@ impl IsoPathTable {
         bytes
     }
 
-    pub fn new_l_table(source: &[Vec<(String, usize)>]) -> Self {
+    pub fn new_l_table(root_location: usize, source: &[Vec<(String, usize)>]) -> Self {
         let mut index = 1;
         let mut folder_map = Vec::new();
 
-        let mut path_table = vec![IsoPathTableEntry::new(23, 1, "\0".to_string())];
+        let mut path_table = vec![IsoPathTableEntry::new(root_location, 1, "\0".to_string())];
 
         // First level folders
         for folder in &source[0] {
@@ -805,7 +2051,6 @@ impl IsoPathTable {
         for (i, subfolders) in source.iter().skip(1).enumerate() {
             if let Some((_, parent_index)) = folder_map.get(i) {
                 for subfolder in subfolders {
-                    index += 1;
                     path_table.push(IsoPathTableEntry::new(
                         subfolder.1,
                         *parent_index,
@@ -818,17 +2063,40 @@ impl IsoPathTable {
         Self::LTable(path_table)
     }
 
+    /// L table of the tree rooted at `root_location`, `children` maps the
+    /// location of each directory to the names and locations of its
+    /// subdirectories. Records are ordered by level, then parent number, then
+    /// identifier as ECMA-119 6.9.1 requires.
+    pub(crate) fn from_directories(
+        root_location: usize,
+        children: &BTreeMap<usize, Vec<(String, usize)>>,
+    ) -> Self {
+        let mut path_table = vec![IsoPathTableEntry::new(root_location, 1, "\0")];
+        let mut level = vec![(1, root_location)];
+
+        while !level.is_empty() {
+            let mut next = Vec::new();
+
+            for (parent, location) in level {
+                let mut subdirs = children.get(&location).cloned().unwrap_or_default();
+                subdirs.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+
+                for (name, location) in subdirs {
+                    path_table.push(IsoPathTableEntry::new(location, parent, name));
+                    next.push((path_table.len(), location));
+                }
+            }
+
+            level = next;
+        }
+
+        Self::LTable(path_table)
+    }
+
+    /// Same entries written big-endian by [`as_vec`](Self::as_vec).
     pub fn convert_to_m_table(self) -> Self {
         match self {
-            Self::LTable(mut t) => {
-                t.iter_mut().for_each(|t| {
-                    t.header.location_of_extent = t.header.location_of_extent.to_be();
-                    t.header.directory_number_of_parent_directory =
-                        t.header.directory_number_of_parent_directory.to_be();
-                });
-
-                Self::MTable(t)
-            }
+            Self::LTable(t) => Self::MTable(t),
             Self::MTable(t) => Self::MTable(t),
         }
     }
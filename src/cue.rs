@@ -0,0 +1,143 @@
+use crate::sector::SectorMode;
+use crate::{IsoFileError, Result};
+
+/// Frames (sectors) per second of CD audio time.
+const FRAMES_PER_SECOND: u32 = 75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CueTrackMode {
+    Audio,
+    Mode1Cooked,
+    Mode1Raw,
+    Mode2Raw,
+    /// Any other track type, kept by name only.
+    Other,
+}
+
+impl CueTrackMode {
+    fn parse(value: &str) -> Self {
+        match value.to_ascii_uppercase().as_str() {
+            "AUDIO" => Self::Audio,
+            "MODE1/2048" => Self::Mode1Cooked,
+            "MODE1/2352" => Self::Mode1Raw,
+            "MODE2/2352" => Self::Mode2Raw,
+            _ => Self::Other,
+        }
+    }
+
+    /// Sector layout of a data track readable as ISO9660.
+    pub fn sector_mode(&self) -> Option<SectorMode> {
+        match self {
+            Self::Mode1Cooked => Some(SectorMode::Cooked),
+            Self::Mode1Raw => Some(SectorMode::Mode1),
+            Self::Mode2Raw => Some(SectorMode::Mode2Form1),
+            Self::Audio | Self::Other => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CueTrack {
+    pub number: u32,
+    pub mode: CueTrackMode,
+    /// Sector of `INDEX 01` relative to the start of the track's file.
+    pub start: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct CueFile {
+    pub name: String,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CueSheet {
+    pub files: Vec<CueFile>,
+}
+
+impl CueSheet {
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut sheet = Self::default();
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            let invalid = || IsoFileError::InvalidCueSheet(number + 1);
+
+            let Some((command, arguments)) = line.split_once(char::is_whitespace) else {
+                continue;
+            };
+
+            match command.to_ascii_uppercase().as_str() {
+                "FILE" => {
+                    // the name may be quoted and contain spaces, the type is last
+                    let (name, _) = arguments
+                        .rsplit_once(char::is_whitespace)
+                        .ok_or_else(invalid)?;
+
+                    sheet.files.push(CueFile {
+                        name: name.trim().trim_matches('"').to_string(),
+                        tracks: Vec::new(),
+                    });
+                }
+                "TRACK" => {
+                    let mut parts = arguments.split_whitespace();
+                    let number = parts
+                        .next()
+                        .and_then(|t| t.parse().ok())
+                        .ok_or_else(invalid)?;
+                    let mode = parts.next().map(CueTrackMode::parse).ok_or_else(invalid)?;
+
+                    let file = sheet.files.last_mut().ok_or_else(invalid)?;
+
+                    file.tracks.push(CueTrack {
+                        number,
+                        mode,
+                        start: 0,
+                    });
+                }
+                "INDEX" => {
+                    let mut parts = arguments.split_whitespace();
+                    let index: u32 = parts
+                        .next()
+                        .and_then(|t| t.parse().ok())
+                        .ok_or_else(invalid)?;
+                    let start = parts.next().and_then(parse_msf).ok_or_else(invalid)?;
+
+                    let track = sheet
+                        .files
+                        .last_mut()
+                        .and_then(|t| t.tracks.last_mut())
+                        .ok_or_else(invalid)?;
+
+                    if index == 1 {
+                        track.start = start;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(sheet)
+    }
+
+    /// First track that holds data, with the file it is stored in.
+    pub fn first_data_track(&self) -> Option<(&CueFile, &CueTrack)> {
+        self.files.iter().find_map(|file| {
+            file.tracks
+                .iter()
+                .find(|t| t.mode.sector_mode().is_some())
+                .map(|track| (file, track))
+        })
+    }
+}
+
+// "mm:ss:ff" to a sector count
+fn parse_msf(value: &str) -> Option<u32> {
+    let mut parts = value.split(':').map(|t| t.parse::<u32>().ok());
+
+    let minutes = parts.next()??;
+    let seconds = parts.next()??;
+    let frames = parts.next()??;
+
+    Some((minutes * 60 + seconds) * FRAMES_PER_SECOND + frames)
+}
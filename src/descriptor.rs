@@ -0,0 +1,221 @@
+use std::io::ErrorKind;
+use std::mem::transmute;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+use crate::core::{BlockLayout, IsoHeaderRaw, LOGICAL_BLOCK_SIZE};
+use crate::{IsoHeader, Result};
+
+// gives up on sets without a terminator
+const DESCRIPTOR_LIMIT: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeDescriptorType {
+    BootRecord,
+    Primary,
+    Supplementary,
+    Partition,
+    Terminator,
+    Reserved(u8),
+}
+
+impl From<u8> for VolumeDescriptorType {
+    fn from(type_code: u8) -> Self {
+        match type_code {
+            0 => Self::BootRecord,
+            1 => Self::Primary,
+            2 => Self::Supplementary,
+            3 => Self::Partition,
+            255 => Self::Terminator,
+            t => Self::Reserved(t),
+        }
+    }
+}
+
+/// Fields of a boot record descriptor, whatever boot system it is for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootRecord {
+    /// Boot system the record is meant for, e.g. `EL TORITO SPECIFICATION`.
+    pub system_id: String,
+    pub boot_id: String,
+    /// The 1977 bytes left to the boot system.
+    pub system_use: Vec<u8>,
+}
+
+/// Region of the volume declared by a volume partition descriptor. It can be
+/// opened with [`IsoReadOptions::set_start_offset`](crate::IsoReadOptions::set_start_offset)
+/// at [`offset`](Self::offset).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumePartition {
+    pub system_id: String,
+    pub partition_id: String,
+    /// First logical block of the partition.
+    pub location: u32,
+    /// Size in logical blocks.
+    pub size: u32,
+    /// The 1960 bytes left to the system.
+    pub system_use: Vec<u8>,
+    /// Byte offset of block 0 in the reader, the start offset of the image.
+    pub base: u64,
+}
+
+impl VolumePartition {
+    /// Byte offset of the partition from the start of the image.
+    pub fn offset(&self) -> u64 {
+        self.base + u64::from(self.location) * LOGICAL_BLOCK_SIZE as u64
+    }
+}
+
+/// One sector of the volume descriptor set.
+#[derive(Debug, Clone)]
+pub struct VolumeDescriptor {
+    block: u32,
+    raw: Vec<u8>,
+    // start offset of the image it was read from
+    base: u64,
+}
+
+impl VolumeDescriptor {
+    pub fn descriptor_type(&self) -> VolumeDescriptorType {
+        self.raw[0].into()
+    }
+
+    pub fn version(&self) -> u8 {
+        self.raw[6]
+    }
+
+    /// Block of the descriptor, counted from the start of the image.
+    pub fn block(&self) -> u32 {
+        self.block
+    }
+
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// Supplementary descriptor with one of the UCS-2 escape sequences.
+    pub fn is_joliet(&self) -> bool {
+        self.descriptor_type() == VolumeDescriptorType::Supplementary
+            && matches!(&self.raw[88..91], b"%/@" | b"%/C" | b"%/E")
+    }
+
+    /// ISO 9660:1999 enhanced descriptor, a supplementary descriptor of
+    /// version 2 with relaxed identifiers.
+    pub fn is_enhanced(&self) -> bool {
+        self.descriptor_type() == VolumeDescriptorType::Supplementary
+            && self.version() == 2
+            && self.raw[881] == 2
+    }
+
+    /// Identifiers and data of a boot record descriptor.
+    pub fn boot_record(&self) -> Option<BootRecord> {
+        if self.descriptor_type() != VolumeDescriptorType::BootRecord {
+            return None;
+        }
+
+        Some(BootRecord {
+            system_id: text(&self.raw[7..39]),
+            boot_id: text(&self.raw[39..71]),
+            system_use: self.raw[71..].to_vec(),
+        })
+    }
+
+    /// Location and size of a volume partition descriptor.
+    pub fn volume_partition(&self) -> Option<VolumePartition> {
+        if self.descriptor_type() != VolumeDescriptorType::Partition {
+            return None;
+        }
+
+        let lsb = |at: usize| u32::from_le_bytes(self.raw[at..at + 4].try_into().unwrap());
+
+        Some(VolumePartition {
+            system_id: text(&self.raw[8..40]),
+            partition_id: text(&self.raw[40..72]),
+            location: lsb(72),
+            size: lsb(80),
+            system_use: self.raw[88..].to_vec(),
+            base: self.base,
+        })
+    }
+
+    /// Block of the boot catalog, for an El Torito boot record.
+    pub fn boot_catalog(&self) -> Option<u32> {
+        if self.descriptor_type() != VolumeDescriptorType::BootRecord
+            || !self.raw[7..].starts_with(b"EL TORITO SPECIFICATION")
+        {
+            return None;
+        }
+
+        Some(u32::from_le_bytes(self.raw[71..75].try_into().unwrap()))
+    }
+
+    /// Decoded fields of a primary or supplementary descriptor.
+    pub fn header(&self) -> Option<IsoHeader> {
+        match self.descriptor_type() {
+            VolumeDescriptorType::Primary | VolumeDescriptorType::Supplementary => {
+                Some((&self.raw_header()).into())
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn raw_header(&self) -> IsoHeaderRaw {
+        let buffer: [u8; LOGICAL_BLOCK_SIZE] = self.raw[..].try_into().unwrap();
+        unsafe { transmute(buffer) }
+    }
+}
+
+// identifier padded with spaces or zeros
+fn text(raw: &[u8]) -> String {
+    String::from_utf8_lossy(raw)
+        .trim_end_matches(['\0', ' '])
+        .to_string()
+}
+
+/// Reads the descriptor set of the session starting at `session_start` up to
+/// and including the terminator.
+pub(crate) async fn read_descriptors<R: AsyncRead + AsyncSeekExt + Unpin>(
+    reader: &mut R,
+    base: u64,
+    session_start: u32,
+) -> Result<Vec<VolumeDescriptor>> {
+    let layout = BlockLayout::new(base, LOGICAL_BLOCK_SIZE as u16);
+    let mut descriptors = Vec::new();
+
+    for block in session_start + 16..session_start + 16 + DESCRIPTOR_LIMIT {
+        let Some(raw) = read_sector(reader, layout.offset(block)).await? else {
+            break;
+        };
+
+        if &raw[1..6] != b"CD001" {
+            break;
+        }
+
+        let descriptor = VolumeDescriptor { block, raw, base };
+        let terminator = descriptor.descriptor_type() == VolumeDescriptorType::Terminator;
+
+        descriptors.push(descriptor);
+
+        if terminator {
+            break;
+        }
+    }
+
+    Ok(descriptors)
+}
+
+/// Reads a 2048 byte sector, `None` past the end of the image.
+pub(crate) async fn read_sector<R: AsyncRead + AsyncSeekExt + Unpin>(
+    reader: &mut R,
+    offset: u64,
+) -> Result<Option<Vec<u8>>> {
+    reader.seek(SeekFrom::Start(offset)).await?;
+
+    let mut sector = vec![0u8; LOGICAL_BLOCK_SIZE];
+
+    match reader.read_exact(&mut sector).await {
+        Ok(_) => Ok(Some(sector)),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,280 @@
+//! El Torito bootable CD-ROM format.
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+use crate::core::{BlockLayout, LOGICAL_BLOCK_SIZE};
+use crate::descriptor::read_sector;
+use crate::{IsoFileError, Result};
+
+// size of every boot catalog entry
+const ENTRY_SIZE: usize = 32;
+
+// sectors read for catalogs with many sections
+const CATALOG_SECTORS: u32 = 8;
+
+// header indicators of the sections, the last one is marked final
+const SECTION_HEADER: u8 = 0x90;
+const FINAL_SECTION_HEADER: u8 = 0x91;
+
+// indicator of the extension entries that may follow a section entry
+const SECTION_EXTENSION: u8 = 0x44;
+
+// emulated sectors counted by the entries
+const VIRTUAL_SECTOR_SIZE: u64 = 512;
+
+// partition table and signature of the master boot record of a hard disk image
+const PARTITION_TABLE: std::ops::Range<usize> = 446..510;
+const MBR_SIGNATURE: [u8; 2] = [0x55, 0xaa];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootPlatform {
+    X86,
+    PowerPc,
+    Mac,
+    Efi,
+    Other(u8),
+}
+
+impl From<u8> for BootPlatform {
+    fn from(platform_id: u8) -> Self {
+        match platform_id {
+            0x00 => Self::X86,
+            0x01 => Self::PowerPc,
+            0x02 => Self::Mac,
+            0xef => Self::Efi,
+            t => Self::Other(t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootMediaType {
+    NoEmulation,
+    Floppy1200,
+    Floppy1440,
+    Floppy2880,
+    HardDisk,
+    Other(u8),
+}
+
+impl From<u8> for BootMediaType {
+    fn from(media_type: u8) -> Self {
+        match media_type & 0x0f {
+            0 => Self::NoEmulation,
+            1 => Self::Floppy1200,
+            2 => Self::Floppy1440,
+            3 => Self::Floppy2880,
+            4 => Self::HardDisk,
+            t => Self::Other(t),
+        }
+    }
+}
+
+/// Initial/default or section entry of a boot catalog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootEntry {
+    pub bootable: bool,
+    pub media_type: BootMediaType,
+    /// Real mode segment the image is loaded at, 0 for the traditional 0x7c0.
+    pub load_segment: u16,
+    /// Partition type of the emulated hard disk.
+    pub system_type: u8,
+    /// Emulated 512 byte sectors loaded by the BIOS.
+    pub sector_count: u16,
+    /// Block of the boot image.
+    pub load_rba: u32,
+}
+
+impl BootEntry {
+    fn parse(raw: &[u8]) -> Self {
+        let u16_at = |at: usize| u16::from_le_bytes([raw[at], raw[at + 1]]);
+
+        Self {
+            bootable: raw[0] == 0x88,
+            media_type: raw[1].into(),
+            load_segment: u16_at(2),
+            system_type: raw[4],
+            sector_count: u16_at(6),
+            load_rba: u32::from_le_bytes(raw[8..12].try_into().unwrap()),
+        }
+    }
+
+    /// Size of the boot image, the full disk for floppy emulation and the
+    /// recorded sector count otherwise. For hard disk emulation that count
+    /// usually covers the master boot record only, reading the entry takes
+    /// the image up to the end of its last partition.
+    pub fn image_size(&self) -> u64 {
+        match self.media_type {
+            BootMediaType::Floppy1200 => 1_228_800,
+            BootMediaType::Floppy1440 => 1_474_560,
+            BootMediaType::Floppy2880 => 2_949_120,
+            _ => u64::from(self.sector_count) * VIRTUAL_SECTOR_SIZE,
+        }
+    }
+}
+
+/// Section of a boot catalog, e.g. the EFI entries of a hybrid image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootSection {
+    pub platform: BootPlatform,
+    pub id: String,
+    pub entries: Vec<BootEntry>,
+}
+
+/// Boot catalog pointed to by the El Torito boot record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootCatalog {
+    /// Platform of the validation entry.
+    pub platform: BootPlatform,
+    /// Manufacturer or developer of the CD-ROM.
+    pub id: String,
+    pub default_entry: BootEntry,
+    pub sections: Vec<BootSection>,
+}
+
+impl BootCatalog {
+    pub(crate) fn parse(raw: &[u8]) -> Result<Self> {
+        let validation = raw
+            .get(..ENTRY_SIZE)
+            .ok_or(IsoFileError::InvalidBootCatalog)?;
+
+        // the words of the validation entry sum up to zero
+        let checksum = validation.chunks_exact(2).fold(0u16, |sum, t| {
+            sum.wrapping_add(u16::from_le_bytes([t[0], t[1]]))
+        });
+
+        if validation[0] != 0x01 || validation[30..32] != [0x55, 0xaa] || checksum != 0 {
+            return Err(IsoFileError::InvalidBootCatalog);
+        }
+
+        let default_entry = raw
+            .get(ENTRY_SIZE..ENTRY_SIZE * 2)
+            .ok_or(IsoFileError::InvalidBootCatalog)?;
+
+        Ok(Self {
+            platform: validation[1].into(),
+            id: id_string(&validation[4..28]),
+            default_entry: BootEntry::parse(default_entry),
+            sections: parse_sections(&raw[ENTRY_SIZE * 2..]),
+        })
+    }
+
+    /// The default entry followed by the entries of every section.
+    pub fn entries(&self) -> impl Iterator<Item = &BootEntry> {
+        std::iter::once(&self.default_entry)
+            .chain(self.sections.iter().flat_map(|t| t.entries.iter()))
+    }
+
+    pub(crate) async fn read<R: AsyncRead + AsyncSeekExt + Unpin>(
+        reader: &mut R,
+        base: u64,
+        block: u32,
+    ) -> Result<Self> {
+        let layout = BlockLayout::new(base, LOGICAL_BLOCK_SIZE as u16);
+
+        let mut raw = Vec::new();
+
+        for block in block..block + CATALOG_SECTORS {
+            match read_sector(reader, layout.offset(block)).await? {
+                Some(sector) => raw.extend(sector),
+                None => break,
+            }
+        }
+
+        Self::parse(&raw)
+    }
+}
+
+fn id_string(raw: &[u8]) -> String {
+    String::from_utf8_lossy(raw)
+        .trim_end_matches(['\0', ' '])
+        .to_string()
+}
+
+// section headers and their entries, up to the final header or the end of `raw`
+fn parse_sections(raw: &[u8]) -> Vec<BootSection> {
+    let mut sections = Vec::new();
+    let mut entries = raw.chunks_exact(ENTRY_SIZE).peekable();
+
+    while let Some(header) = entries.next() {
+        if header[0] != SECTION_HEADER && header[0] != FINAL_SECTION_HEADER {
+            break;
+        }
+
+        let count = u16::from_le_bytes([header[2], header[3]]);
+        let mut section = BootSection {
+            platform: header[1].into(),
+            id: id_string(&header[4..32]),
+            entries: Vec::new(),
+        };
+
+        for _ in 0..count {
+            let Some(entry) = entries.next() else {
+                break;
+            };
+
+            section.entries.push(BootEntry::parse(entry));
+
+            while entries.next_if(|t| t[0] == SECTION_EXTENSION).is_some() {}
+        }
+
+        sections.push(section);
+
+        if header[0] == FINAL_SECTION_HEADER {
+            break;
+        }
+    }
+
+    sections
+}
+
+/// Size of the image loaded by a boot entry, from the partition table of the
+/// master boot record for an emulated hard disk.
+pub(crate) async fn boot_image_size<R: AsyncRead + AsyncSeekExt + Unpin>(
+    reader: &mut R,
+    base: u64,
+    entry: &BootEntry,
+) -> Result<u64> {
+    if entry.media_type != BootMediaType::HardDisk {
+        return Ok(entry.image_size());
+    }
+
+    let layout = BlockLayout::new(base, LOGICAL_BLOCK_SIZE as u16);
+    reader
+        .seek(SeekFrom::Start(layout.offset(entry.load_rba)))
+        .await?;
+
+    let mut mbr = [0u8; VIRTUAL_SECTOR_SIZE as usize];
+    reader.read_exact(&mut mbr).await?;
+
+    if mbr[PARTITION_TABLE.end..] != MBR_SIGNATURE {
+        return Ok(entry.image_size());
+    }
+
+    let u32_at = |raw: &[u8], at: usize| u32::from_le_bytes(raw[at..at + 4].try_into().unwrap());
+    let end = mbr[PARTITION_TABLE]
+        .chunks_exact(16)
+        .map(|t| u64::from(u32_at(t, 8)) + u64::from(u32_at(t, 12)))
+        .max()
+        .unwrap_or(0);
+
+    Ok((end * VIRTUAL_SECTOR_SIZE).max(entry.image_size()))
+}
+
+/// Reads the `size` bytes of the image loaded by a boot entry.
+pub(crate) async fn read_boot_image<R: AsyncRead + AsyncSeekExt + Unpin>(
+    reader: &mut R,
+    base: u64,
+    entry: &BootEntry,
+    size: u64,
+) -> Result<Vec<u8>> {
+    let layout = BlockLayout::new(base, LOGICAL_BLOCK_SIZE as u16);
+    reader
+        .seek(SeekFrom::Start(layout.offset(entry.load_rba)))
+        .await?;
+
+    let mut buffer = vec![0u8; size as usize];
+    reader.read_exact(&mut buffer).await?;
+
+    Ok(buffer)
+}
@@ -1,3 +1,6 @@
+use std::fmt;
+use std::path::PathBuf;
+
 use thiserror::Error;
 
 /// The result type for all methods that can return an error.
@@ -9,12 +12,116 @@ pub enum IsoFileError {
     InvalidDatetime,
     #[error("Invalid timezone")]
     InvalidTimezone,
+    #[error("Invalid logical block size {0}.")]
+    InvalidLogicalBlockSize(u16),
     #[error("File not found.")]
     FileNotFound,
     #[error("Entry is current directory.")]
     EntryCurrentDirectory,
     #[error("Entry is parent directory.")]
     EntryParentDirectory,
+    #[error("Entry is directory.")]
+    EntryDirectory,
+    #[error("Entry is not a directory.")]
+    NotDirectory,
+    #[error("Invalid cue sheet at line {0}.")]
+    InvalidCueSheet(usize),
+    #[error("No data track found.")]
+    NoDataTrack,
+    #[error("Truncated file, {} of {expected} bytes recovered.", data.len())]
+    Truncated { data: Vec<u8>, expected: u64 },
+    #[error("Allocation of {0} bytes exceeds the limit.")]
+    AllocationLimit(u64),
+    #[error("Entry limit of {0} exceeded.")]
+    EntryLimit(usize),
+    #[error("Depth limit of {0} exceeded.")]
+    DepthLimit(usize),
+    #[error("Buffer too small, {0} bytes needed.")]
+    BufferTooSmall(u64),
+    #[error("No El Torito boot record found.")]
+    NoBootRecord,
+    #[error("Invalid El Torito boot catalog.")]
+    InvalidBootCatalog,
+    #[error("Invalid or unsupported UDF structures.")]
+    InvalidUdf,
+    #[error("File is not stored in one piece.")]
+    Fragmented,
+    #[error("File is stored compressed.")]
+    Compressed,
+    #[error("Invalid zisofs compressed file.")]
+    InvalidZisofs,
+    #[error("Source of {} ended after {actual} of {length} bytes.", path.display())]
+    ShortSource {
+        path: PathBuf,
+        length: usize,
+        actual: usize,
+    },
+    #[error("{} cannot be placed at block {location}.", path.display())]
+    LocationConflict { path: PathBuf, location: u32 },
+    #[error("Path names no entry.")]
+    EmptyPath,
+    #[error("Invalid name {0:?}.")]
+    InvalidName(String),
+    #[error("{} is taken by an appended entry.", .0.display())]
+    DuplicatePath(PathBuf),
+    #[error("{} is the target of a hard link.", .0.display())]
+    LinkTarget(PathBuf),
+    #[error("Writing the image was cancelled.")]
+    Cancelled,
+    #[error("Streamed files must be finished with close_seekable.")]
+    SeekRequired,
     #[error("Std. IO: {0}.")]
     StdIo(#[from] std::io::Error),
 }
+
+/// Failure of [`close`](crate::IsoFileWriter::close), giving back the writer.
+/// Nothing is written when the image is rejected up front, e.g. with
+/// [`IsoFileError::SeekRequired`], so it can still be finished otherwise.
+pub struct CloseError<T> {
+    writer: T,
+    error: IsoFileError,
+}
+
+impl<T> CloseError<T> {
+    pub(crate) fn new(writer: T, error: IsoFileError) -> Self {
+        Self { writer, error }
+    }
+
+    pub fn error(&self) -> &IsoFileError {
+        &self.error
+    }
+
+    pub fn into_error(self) -> IsoFileError {
+        self.error
+    }
+
+    pub fn into_writer(self) -> T {
+        self.writer
+    }
+}
+
+impl<T> fmt::Debug for CloseError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CloseError")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for CloseError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<T> std::error::Error for CloseError<T> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl<T> From<CloseError<T>> for IsoFileError {
+    fn from(value: CloseError<T>) -> Self {
+        value.error
+    }
+}
@@ -0,0 +1,61 @@
+//! Include and exclude patterns for [`IsoFileWriter::append_dir_all`](crate::IsoFileWriter::append_dir_all).
+
+/// Selects the files added from a local directory.
+///
+/// Patterns use `*` for any run of characters and `?` for one character,
+/// neither crossing a `/`. A pattern without `/` is matched against the name
+/// only, otherwise against the whole path relative to the directory.
+/// Excluded directories are skipped with their contents. With no include
+/// pattern every file is included.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl PathFilter {
+    pub fn include<S: Into<String>>(&mut self, pattern: S) -> &mut Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    pub fn exclude<S: Into<String>>(&mut self, pattern: S) -> &mut Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// `relative` uses `/` as separator.
+    pub(crate) fn is_excluded(&self, relative: &str) -> bool {
+        self.exclude.iter().any(|t| matches_path(t, relative))
+    }
+
+    pub(crate) fn is_included(&self, relative: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|t| matches_path(t, relative)))
+            && !self.is_excluded(relative)
+    }
+}
+
+fn matches_path(pattern: &str, relative: &str) -> bool {
+    if pattern.contains('/') {
+        matches(
+            pattern.trim_start_matches('/').as_bytes(),
+            relative.as_bytes(),
+        )
+    } else {
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        matches(pattern.as_bytes(), name.as_bytes())
+    }
+}
+
+fn matches(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            matches(&pattern[1..], text)
+                || (text.first().is_some_and(|&t| t != b'/') && matches(pattern, &text[1..]))
+        }
+        (Some(b'?'), Some(&t)) if t != b'/' => matches(&pattern[1..], &text[1..]),
+        (Some(p), Some(t)) if p == t => matches(&pattern[1..], &text[1..]),
+        _ => false,
+    }
+}
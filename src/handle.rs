@@ -0,0 +1,248 @@
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, ReadBuf};
+
+/// Where the content of a file is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FileData {
+    /// Byte offset and length of each extent, in file order.
+    Extents(Vec<(u64, u64)>),
+    /// Content recorded in the file descriptor itself.
+    Embedded(Vec<u8>),
+}
+
+impl FileData {
+    /// Joins adjacent extents so contiguous files are read in one piece.
+    pub fn from_extents(extents: impl IntoIterator<Item = (u64, u64)>) -> Self {
+        let mut joined: Vec<(u64, u64)> = Vec::new();
+
+        for (offset, length) in extents {
+            match joined.last_mut() {
+                Some((start, previous)) if *start + *previous == offset => *previous += length,
+                _ => joined.push((offset, length)),
+            }
+        }
+
+        Self::Extents(joined)
+    }
+
+    pub fn len(&self) -> u64 {
+        match self {
+            Self::Extents(extents) => extents.iter().map(|t| t.1).sum(),
+            Self::Embedded(content) => content.len() as u64,
+        }
+    }
+
+    /// Byte offset and length of the file when it is stored in one piece.
+    pub fn contiguous(&self) -> Option<(u64, u64)> {
+        match self {
+            Self::Extents(extents) => match extents[..] {
+                [] => Some((0, 0)),
+                [extent] => Some(extent),
+                _ => None,
+            },
+            Self::Embedded(_) => None,
+        }
+    }
+}
+
+/// Read-only view over the extents of a file inside the image.
+///
+/// Positions are relative to the start of the file and reads stop at its end.
+/// An image ending inside the file fails the read, unless the reader is in
+/// recovery mode where reads stop there, before [`len`](Self::len).
+#[derive(Debug)]
+pub struct IsoFileHandle<'r, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    reader: &'r mut R,
+    data: FileData,
+    length: u64,
+    position: u64,
+    // absolute position of the inner reader, if known
+    inner_position: Option<u64>,
+    // absolute position of an inner seek that has not completed yet
+    pending_seek: Option<u64>,
+    // ends reads where the image does instead of failing
+    recovery: bool,
+}
+
+impl<'r, R> IsoFileHandle<'r, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    pub(crate) fn new(reader: &'r mut R, data: FileData) -> Self {
+        Self {
+            reader,
+            length: data.len(),
+            data,
+            position: 0,
+            inner_position: None,
+            pending_seek: None,
+            recovery: false,
+        }
+    }
+
+    pub(crate) fn set_recovery(&mut self, recovery: bool) {
+        self.recovery = recovery;
+    }
+
+    pub(crate) fn into_data(self) -> FileData {
+        self.data
+    }
+
+    /// Reads until `buffer` is full or the readable part of the file ends and
+    /// returns the bytes read.
+    pub(crate) async fn fill(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+
+        while filled < buffer.len() {
+            match self.read(&mut buffer[filled..]).await? {
+                0 => break,
+                read => filled += read,
+            }
+        }
+
+        Ok(filled)
+    }
+
+    /// Size of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Current position relative to the start of the file.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    // absolute position of the current byte and the bytes left in its extent
+    fn locate(&self) -> (u64, u64) {
+        let FileData::Extents(extents) = &self.data else {
+            return (0, 0);
+        };
+
+        let mut skipped = 0;
+
+        for &(start, length) in extents {
+            if self.position < skipped + length {
+                return (
+                    start + self.position - skipped,
+                    skipped + length - self.position,
+                );
+            }
+
+            skipped += length;
+        }
+
+        (0, 0)
+    }
+
+    fn poll_sync_inner(&mut self, cx: &mut Context<'_>, target: u64) -> Poll<io::Result<()>> {
+        loop {
+            if self.pending_seek.is_some() {
+                let position = ready!(Pin::new(&mut *self.reader).poll_complete(cx))?;
+                self.pending_seek = None;
+                self.inner_position = Some(position);
+            }
+
+            if self.inner_position == Some(target) {
+                return Poll::Ready(Ok(()));
+            }
+
+            Pin::new(&mut *self.reader).start_seek(SeekFrom::Start(target))?;
+            self.pending_seek = Some(target);
+        }
+    }
+}
+
+impl<R> AsyncRead for IsoFileHandle<'_, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        let remaining = this.length.saturating_sub(this.position);
+
+        if remaining == 0 || buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        if let FileData::Embedded(content) = &this.data {
+            let start = this.position as usize;
+            let end = start + remaining.min(buf.remaining() as u64) as usize;
+            buf.put_slice(&content[start..end]);
+            this.position = end as u64;
+
+            return Poll::Ready(Ok(()));
+        }
+
+        let (target, available) = this.locate();
+        ready!(this.poll_sync_inner(cx, target))?;
+
+        let max = available.min(buf.remaining() as u64) as usize;
+        let mut limited = buf.take(max);
+
+        ready!(Pin::new(&mut *this.reader).poll_read(cx, &mut limited))?;
+
+        let read = limited.filled().len();
+
+        if read == 0 && !this.recovery {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "image ends inside the file",
+            )));
+        }
+
+        // SAFETY: `limited` was filled by the inner reader over `buf`'s unfilled part
+        unsafe { buf.assume_init(read) };
+        buf.advance(read);
+
+        this.position += read as u64;
+        this.inner_position = Some(target + read as u64);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R> AsyncSeek for IsoFileHandle<'_, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+
+        let target = match position {
+            SeekFrom::Start(t) => Some(t),
+            SeekFrom::End(t) => this.length.checked_add_signed(t),
+            SeekFrom::Current(t) => this.position.checked_add_signed(t),
+        };
+
+        match target {
+            Some(t) => {
+                this.position = t;
+                Ok(())
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
@@ -0,0 +1,74 @@
+use std::io;
+use std::ops::Range;
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use reqwest::header::{CONTENT_LENGTH, RANGE};
+use reqwest::{Client, IntoUrl, StatusCode, Url};
+
+use crate::remote::{RangeReader, RangeSource};
+use crate::{IsoFileReader, Result};
+
+/// Image served over HTTP by a server that honors range requests.
+#[derive(Debug, Clone)]
+pub struct HttpRangeSource {
+    client: Client,
+    url: Url,
+}
+
+impl HttpRangeSource {
+    pub fn new<U: IntoUrl>(client: Client, url: U) -> Result<Self> {
+        Ok(Self {
+            client,
+            url: url.into_url().map_err(io::Error::other)?,
+        })
+    }
+}
+
+impl RangeSource for HttpRangeSource {
+    fn size(&self) -> BoxFuture<'static, io::Result<u64>> {
+        let request = self.client.head(self.url.clone());
+
+        Box::pin(async move {
+            let response = request
+                .send()
+                .await
+                .and_then(|t| t.error_for_status())
+                .map_err(io::Error::other)?;
+
+            response
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|t| t.to_str().ok())
+                .and_then(|t| t.parse().ok())
+                .ok_or_else(|| io::Error::other("missing content length"))
+        })
+    }
+
+    fn fetch(&self, range: Range<u64>) -> BoxFuture<'static, io::Result<Bytes>> {
+        let request = self
+            .client
+            .get(self.url.clone())
+            .header(RANGE, format!("bytes={}-{}", range.start, range.end - 1));
+
+        Box::pin(async move {
+            let response = request.send().await.map_err(io::Error::other)?;
+
+            match response.status() {
+                StatusCode::PARTIAL_CONTENT => response.bytes().await.map_err(io::Error::other),
+                StatusCode::RANGE_NOT_SATISFIABLE => Ok(Bytes::new()),
+                StatusCode::OK => Err(io::Error::other("server ignored the range request")),
+                status => Err(io::Error::other(format!("unexpected status {status}"))),
+            }
+        })
+    }
+}
+
+impl IsoFileReader<RangeReader<HttpRangeSource>> {
+    /// Reads a remote image, fetching only the ranges that are needed.
+    pub async fn open_url<U: IntoUrl>(url: U) -> Result<Self> {
+        let source = HttpRangeSource::new(Client::new(), url)?;
+
+        Self::read(RangeReader::new(source)).await
+    }
+}
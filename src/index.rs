@@ -0,0 +1,276 @@
+use std::path::Path;
+
+use tokio::io::{AsyncRead, AsyncSeekExt, SeekFrom};
+
+use crate::charset::Charset;
+use crate::core::{self, BlockLayout, IsoHeaderRaw, find_next_session};
+use crate::descriptor::{VolumeDescriptor, read_descriptors};
+use crate::options::Limits;
+use crate::udf::UdfEntries;
+use crate::{IsoDirectoryEntries, IsoFileError, IsoHeader, IsoPathTable, IsoReadOptions, Result};
+
+/// Parsed metadata of an image, independent of the IO it was read from.
+///
+/// Wrap it in an `Arc` and pass it to [`IsoFileReader::with_index`](crate::IsoFileReader::with_index)
+/// to open several readers without parsing the tree again.
+#[derive(Debug, Clone)]
+pub struct IsoIndex {
+    pub(crate) header: IsoHeaderRaw,
+    pub(crate) layout: BlockLayout,
+    pub(crate) path_table: IsoPathTable,
+    pub(crate) optional_path_tables: Vec<IsoPathTable>,
+    // blocks of the optional tables left out as unreadable or disagreeing
+    pub(crate) rejected_path_tables: Vec<u32>,
+    pub(crate) entries: IsoDirectoryEntries,
+    pub(crate) sessions: Vec<u32>,
+    pub(crate) udf: Option<UdfEntries>,
+    // the UDF tree was asked for but uses structures not supported
+    pub(crate) udf_unsupported: bool,
+    pub(crate) limits: Limits,
+    pub(crate) charset: Charset,
+}
+
+impl IsoIndex {
+    pub async fn read<R: AsyncRead + AsyncSeekExt + Unpin>(
+        reader: &mut R,
+        options: &IsoReadOptions,
+    ) -> Result<Self> {
+        let mut entries = IsoDirectoryEntries::with_limits(options.limits);
+        entries.set_rock_ridge(options.rock_ridge);
+        entries.set_hidden(options.hidden);
+        entries.set_trans_tbl(options.trans_tbl);
+        entries.set_charset(options.charset);
+        let mut sessions = Vec::new();
+        let mut next_session = Some(options.session_start);
+        let mut last = None;
+
+        while let Some(session_start) = next_session {
+            // reserved for boot sector, descriptors start at block 16 of the session
+            let session_offset = u64::from(session_start) * core::LOGICAL_BLOCK_SIZE as u64;
+
+            reader
+                .seek(SeekFrom::Start(
+                    options.start_offset + session_offset + 0x8000,
+                ))
+                .await?;
+
+            // read ISO Header
+            let header = IsoHeaderRaw::read(reader).await?;
+
+            let descriptors = read_descriptors(reader, options.start_offset, session_start).await?;
+            let find = |f: fn(&VolumeDescriptor) -> bool| {
+                descriptors.iter().find(|t| f(t)).map(|t| t.raw_header())
+            };
+            let joliet = find(VolumeDescriptor::is_joliet);
+            let enhanced = find(VolumeDescriptor::is_enhanced);
+
+            // the tree of the Joliet or enhanced descriptor replaces the primary one
+            let (tree, other) = match (joliet, enhanced) {
+                (Some(joliet), _) if options.joliet => {
+                    entries.set_joliet(sessions.len());
+                    (joliet, Some((header, false)))
+                }
+                (_, Some(enhanced)) if options.enhanced => {
+                    entries.set_enhanced(sessions.len());
+                    (enhanced, Some((header, false)))
+                }
+                (Some(joliet), _) => (header, Some((joliet, true))),
+                _ => (header, None),
+            };
+
+            let layout = tree.layout(options.start_offset)?;
+            let recursive = !options.lazy && !options.path_table_lookup;
+
+            // read directory entries, later sessions replace earlier entries
+            entries
+                .read(
+                    reader,
+                    Path::new("/"),
+                    layout,
+                    layout.offset(tree.root_entry_block()),
+                    Some(tree.root_entry_length().into()),
+                    sessions.len(),
+                    recursive,
+                )
+                .await?;
+
+            // names from the other tree, only when the whole tree is read
+            if let Some((other, joliet)) = other.filter(|_| recursive && options.paired_names) {
+                let mut names = IsoDirectoryEntries::with_limits(options.limits);
+                names.set_charset(options.charset);
+                let layout = other.layout(options.start_offset)?;
+
+                if joliet {
+                    names.set_joliet(sessions.len());
+                }
+
+                names
+                    .read(
+                        reader,
+                        Path::new("/"),
+                        layout,
+                        layout.offset(other.root_entry_block()),
+                        Some(other.root_entry_length().into()),
+                        sessions.len(),
+                        true,
+                    )
+                    .await?;
+
+                entries.pair_names(&names);
+            }
+
+            sessions.push(session_start);
+
+            next_session = if options.scan_sessions {
+                let from_block = header.volume_space_sectors().max(session_start + 1);
+                find_next_session(reader, options.start_offset, from_block).await?
+            } else {
+                None
+            };
+
+            last = Some((header, tree, layout));
+        }
+
+        let (header, tree, layout) = last.expect("at least one session is read");
+        let charset = (!entries.is_joliet(sessions.len() - 1)).then_some(options.charset);
+
+        let path_table = read_path_table(reader, &tree, layout, options.limits, charset).await?;
+        let (optional_path_tables, rejected_path_tables) =
+            read_optional_path_tables(reader, &tree, layout, options.limits, charset, &path_table)
+                .await;
+
+        if options.path_table_lookup {
+            let session = sessions.len() - 1;
+
+            for (path, location) in path_table.paths() {
+                entries.add_pending(path, layout.offset(location), session);
+            }
+        }
+
+        let udf = if options.udf {
+            UdfEntries::read(reader, options.start_offset, options.limits, options.hidden).await
+        } else {
+            Ok(None)
+        };
+
+        // the ISO 9660 tree is still usable without it
+        let udf_unsupported = matches!(udf, Err(IsoFileError::InvalidUdf));
+        let udf = if udf_unsupported { None } else { udf? };
+
+        Ok(Self {
+            header,
+            layout,
+            path_table,
+            optional_path_tables,
+            rejected_path_tables,
+            entries,
+            sessions,
+            udf,
+            udf_unsupported,
+            limits: options.limits,
+            charset: options.charset,
+        })
+    }
+
+    pub fn header(&self) -> IsoHeader {
+        IsoHeader::decode(&self.header, self.charset)
+    }
+
+    /// Start block of every session that was read, see [`IsoDirectoryEntry::session`](crate::IsoDirectoryEntry::session).
+    pub fn sessions(&self) -> &[u32] {
+        &self.sessions
+    }
+
+    pub fn entries(&self) -> &IsoDirectoryEntries {
+        &self.entries
+    }
+
+    pub fn path_table(&self) -> &IsoPathTable {
+        &self.path_table
+    }
+
+    /// Optional L and M path tables that describe the same directories as
+    /// [`path_table`](Self::path_table). Tables that disagree are left out and
+    /// reported by [`verify`](crate::IsoFileReader::verify).
+    pub fn optional_path_tables(&self) -> &[IsoPathTable] {
+        &self.optional_path_tables
+    }
+
+    /// UDF tree of a bridge disc read with [`IsoReadOptions::set_udf`], `None`
+    /// as well if it uses unsupported structures, which
+    /// [`verify`](crate::IsoFileReader::verify) reports.
+    pub fn udf_entries(&self) -> Option<&UdfEntries> {
+        self.udf.as_ref()
+    }
+}
+
+// L path table of `tree`, or its M table when the L table is missing or damaged
+async fn read_path_table<R: AsyncRead + AsyncSeekExt + Unpin>(
+    reader: &mut R,
+    tree: &IsoHeaderRaw,
+    layout: BlockLayout,
+    limits: Limits,
+    charset: Option<Charset>,
+) -> Result<IsoPathTable> {
+    let l_block = tree.type_l_path_table_block();
+    let m_block = tree.type_m_path_table_block();
+
+    let l_table = if l_block != 0 {
+        IsoPathTable::read_l_table_limited(reader, layout.offset(l_block), limits, charset)
+            .await
+            .ok()
+            .filter(IsoPathTable::is_consistent)
+    } else {
+        None
+    };
+
+    match l_table {
+        Some(table) => Ok(table),
+        None if m_block != 0 => {
+            IsoPathTable::read_m_table_limited(reader, layout.offset(m_block), limits, charset)
+                .await
+        }
+        None => {
+            IsoPathTable::read_l_table_limited(reader, layout.offset(l_block), limits, charset)
+                .await
+        }
+    }
+}
+
+// optional tables of `tree` that agree with the table in use, and the blocks
+// of those that do not
+async fn read_optional_path_tables<R: AsyncRead + AsyncSeekExt + Unpin>(
+    reader: &mut R,
+    tree: &IsoHeaderRaw,
+    layout: BlockLayout,
+    limits: Limits,
+    charset: Option<Charset>,
+    path_table: &IsoPathTable,
+) -> (Vec<IsoPathTable>, Vec<u32>) {
+    let expected = path_table.paths();
+    let mut tables = Vec::new();
+    let mut rejected = Vec::new();
+
+    let l_block = tree.opti_l_path_table_block();
+    let m_block = tree.opti_m_path_table_block();
+
+    for (block, m_table) in [(l_block, false), (m_block, true)] {
+        if block == 0 {
+            continue;
+        }
+
+        let offset = layout.offset(block);
+        let table = if m_table {
+            IsoPathTable::read_m_table_limited(reader, offset, limits, charset).await
+        } else {
+            IsoPathTable::read_l_table_limited(reader, offset, limits, charset).await
+        };
+
+        match table {
+            Ok(table) if table.is_consistent() && table.paths() == expected => tables.push(table),
+            _ => rejected.push(block),
+        }
+    }
+
+    (tables, rejected)
+}
@@ -1,17 +1,94 @@
-use std::collections::{HashMap, VecDeque};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::Cursor;
+use std::mem;
 use std::path::{Path, PathBuf};
-
-use chrono::{DateTime, Utc};
-use core::{IsoDirectoryEntries, IsoDirectoryEntry, RootDirectoryEntry};
-use core::{IsoEntry, IsoHeader, IsoHeaderRaw, IsoPathTable};
-use tokio::io::{self, AsyncRead, AsyncWrite, SeekFrom};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+use bytes::{Bytes, BytesMut};
+use checksum::{APPLICATION_USE, Hasher};
+use chrono::{DateTime, FixedOffset, Utc};
+use core::{DirectoryWalker, IsoHeaderRaw, RootDirectoryEntry};
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt, stream};
+use handle::FileData;
+use naming::{NameMap, Naming};
+use progress::{Cancel, Progress};
+use tokio::fs::{self, File};
+use tokio::io::{self, AsyncRead, AsyncSeek, AsyncWrite, SeekFrom};
 use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
-
+use tokio::sync::watch;
+
+mod apple;
+mod capabilities;
+mod charset;
+mod checksum;
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "xz"))]
+mod compression;
 mod core;
+mod cue;
+mod descriptor;
+mod el_torito;
 pub mod error;
+mod filter;
+mod handle;
+#[cfg(feature = "http")]
+mod http;
+mod index;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod naming;
+#[cfg(feature = "object_store")]
+mod object_store;
+mod options;
+mod partition;
+mod progress;
+mod remote;
+mod rock_ridge;
+mod sector;
+mod susp;
+#[cfg(feature = "sync")]
+pub mod sync;
+mod trans_tbl;
 mod types;
-
-pub use error::{IsoFileError, Result};
+mod udf;
+mod verify;
+mod xa;
+mod xar;
+mod zisofs;
+
+pub use apple::AppleAttributes;
+pub use capabilities::IsoCapabilities;
+pub use charset::Charset;
+pub use checksum::ChecksumAlgorithm;
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "xz"))]
+pub use compression::{Compression, DEFAULT_CACHE_SIZE, DecompressReader};
+pub use core::{FileMetadata, IsoHeader, IsoPathTable, IsoPathTableEntry, IsoStats};
+pub use core::{IsoDirectoryEntries, IsoDirectoryEntry, IsoDirectoryHeader, IsoEntry};
+pub use cue::{CueFile, CueSheet, CueTrack, CueTrackMode};
+pub use descriptor::{BootRecord, VolumeDescriptor, VolumeDescriptorType, VolumePartition};
+pub use el_torito::{BootCatalog, BootEntry, BootMediaType, BootPlatform, BootSection};
+pub use error::{CloseError, IsoFileError, Result};
+pub use filter::PathFilter;
+pub use handle::IsoFileHandle;
+#[cfg(feature = "http")]
+pub use http::HttpRangeSource;
+pub use index::IsoIndex;
+pub use naming::{DeepDirectories, InterchangeLevel};
+#[cfg(feature = "object_store")]
+pub use object_store::ObjectStoreSource;
+pub use options::IsoReadOptions;
+pub use partition::{ApmPartition, GptPartition, MbrPartition, PartitionTables};
+pub use progress::WriteProgress;
+pub use remote::{DEFAULT_FETCH_SIZE, RangeReader, RangeSource};
+pub use rock_ridge::{PosixAttributes, RockRidgeTimes};
+pub use sector::{RAW_SECTOR_SIZE, RawSectorReader, SectorMode};
+pub use udf::{UdfEntries, UdfEntry};
+pub use verify::{VerifyReport, Violation};
+pub use xa::XaAttributes;
+pub use xar::ExtendedAttributes;
 
 #[cfg(test)]
 mod test;
@@ -23,496 +100,2557 @@ pub struct IsoFileReader<R>
 where
     R: AsyncRead + AsyncSeekExt + Unpin,
 {
-    header: IsoHeaderRaw,
-    path_table: IsoPathTable,
-    entries: IsoDirectoryEntries,
+    index: Arc<IsoIndex>,
     reader: R,
+    recovery: bool,
 }
 
 impl<R> IsoFileReader<R>
 where
     R: AsyncRead + AsyncSeekExt + Unpin,
 {
-    pub async fn read(mut reader: R) -> Result<Self> {
-        // reserved for boot sector
-        reader.seek(SeekFrom::Start(0x8000)).await?;
-
-        // read ISO Header
-        let header = IsoHeaderRaw::read(&mut reader).await?;
+    pub async fn read(reader: R) -> Result<Self> {
+        Self::read_with_options(reader, IsoReadOptions::default()).await
+    }
 
-        // read path table
-        let type_l_location = header.loc_of_type_l_path_table();
-        let path_table = IsoPathTable::read_l_table(&mut reader, type_l_location).await?;
+    /// Reads an image whose first byte is at `start_offset` in `reader`, e.g. an
+    /// ISO9660 filesystem stored inside a partition.
+    pub async fn read_at(reader: R, start_offset: u64) -> Result<Self> {
+        let mut options = IsoReadOptions::default();
+        options.set_start_offset(start_offset);
 
-        // read directory entries
-        let base_path = Path::new("/");
-        let mut entries = IsoDirectoryEntries::default();
+        Self::read_with_options(reader, options).await
+    }
 
-        entries
-            .read(
-                &mut reader,
-                base_path,
-                header.logical_block_size(),
-                header.root_entry_location(),
-            )
-            .await?;
+    /// Reads the session whose first block is `session_start`, like `mkisofs -C`.
+    ///
+    /// Extent locations stay absolute, so files from earlier sessions referenced
+    /// by the last one are still reachable.
+    pub async fn read_session(reader: R, session_start: u32) -> Result<Self> {
+        let mut options = IsoReadOptions::default();
+        options.set_session_start(session_start);
 
-        Ok(Self {
-            header,
-            path_table,
-            entries,
-            reader,
-        })
+        Self::read_with_options(reader, options).await
     }
 
-    pub async fn read_file<P: Into<PathBuf> + Ord>(&mut self, path: P) -> Result<Vec<u8>> {
-        match self.entries.get(&path.into()) {
-            Some(value) => match value.entry() {
-                IsoEntry::CurrentDirectory => Err(IsoFileError::EntryCurrentDirectory),
-                IsoEntry::ParentDirectory => Err(IsoFileError::EntryParentDirectory),
-                IsoEntry::Directory(_) => unreachable!(),
-                IsoEntry::File(_) => {
-                    let logical_block_size = self.header.logical_block_size();
-
-                    self.reader
-                        .seek(SeekFrom::Start(
-                            value.record().location(Some(logical_block_size)).into(),
-                        ))
-                        .await?;
+    pub async fn read_with_options(mut reader: R, options: IsoReadOptions) -> Result<Self> {
+        let index = IsoIndex::read(&mut reader, &options).await?;
 
-                    let mut buffer = vec![0u8; value.record().data_length() as usize];
-                    self.reader.read_exact(&mut buffer).await?;
+        Ok(Self::with_index(reader, Arc::new(index)))
+    }
 
-                    Ok(buffer)
-                }
-            },
-            None => Err(IsoFileError::FileNotFound),
+    /// Opens a reader over an index parsed earlier from the same image.
+    pub fn with_index(reader: R, index: Arc<IsoIndex>) -> Self {
+        Self {
+            index,
+            reader,
+            recovery: false,
         }
     }
 
-    pub fn header(&self) -> IsoHeader {
-        self.header.as_ref().into()
+    /// Return the readable part of truncated files inside
+    /// [`IsoFileError::Truncated`] instead of failing with an IO error. File
+    /// handles end their reads where the image does.
+    pub fn set_recovery(&mut self, recovery: bool) {
+        self.recovery = recovery;
     }
 
-    pub fn entries(&self) -> &IsoDirectoryEntries {
-        &self.entries
-    }
+    /// Reads a directory and its ancestors if they were skipped by lazy mode.
+    ///
+    /// A shared index is cloned before it is modified.
+    pub async fn load_dir<P: Into<PathBuf> + Ord>(&mut self, path: P) -> Result<()> {
+        let path = path.into();
 
-    pub fn path_table(&self) -> &IsoPathTable {
-        &self.path_table
+        for ancestor in path.ancestors().collect::<Vec<_>>().into_iter().rev() {
+            if !self.index.entries.is_pending(ancestor) {
+                continue;
+            }
+
+            let index = Arc::make_mut(&mut self.index);
+            index
+                .entries
+                .load(&mut self.reader, ancestor, index.layout)
+                .await?;
+        }
+
+        Ok(())
     }
-}
 
-/* WRITE */
+    /// Reads every directory skipped by lazy mode.
+    pub async fn load_all(&mut self) -> Result<()> {
+        loop {
+            let next = self.index.entries.pending().next().map(Path::to_path_buf);
 
-#[derive(Debug, Clone)]
-struct FileEntry<'r> {
-    path: PathBuf,
-    content: &'r [u8],
-    timestamp: DateTime<Utc>,
-}
+            let Some(path) = next else {
+                break;
+            };
 
-#[derive(Debug, Clone, Copy)]
-struct SectorProps {
-    group_no: usize,
-    depth: usize,
-}
+            let index = Arc::make_mut(&mut self.index);
+            index
+                .entries
+                .load(&mut self.reader, &path, index.layout)
+                .await?;
+        }
 
-fn build_dirs<'r>(
-    file_entries: Vec<FileEntry<'r>>,
-    files_sectors: &mut Vec<&'r [u8]>,
-    group_no: usize,
-    depth: usize,
-) -> (Vec<(Vec<IsoDirectoryEntry>, SectorProps)>, Vec<String>) {
-    let mut dirs_sector = Vec::new();
-    let mut dirs_sectors = Vec::new();
-    let mut folders: Vec<String> = Vec::new();
+        Ok(())
+    }
 
-    let mut dirs_sector_size = 0;
+    // makes sure the entry for `path` has been read
+    async fn resolve(&mut self, path: &Path) -> Result<()> {
+        let Some(parent) = path.parent() else {
+            return Ok(());
+        };
 
-    let cur_dir = IsoDirectoryEntry::new(0, 0, &Utc::now(), IsoEntry::CurrentDirectory);
-    dirs_sector_size += cur_dir.len();
-    dirs_sector.push(cur_dir);
+        // known through the path table, its ancestors are not needed
+        if self.index.entries.is_pending(parent) {
+            let index = Arc::make_mut(&mut self.index);
+            index
+                .entries
+                .load(&mut self.reader, parent, index.layout)
+                .await?;
 
-    let par_dir = IsoDirectoryEntry::new(0, 0, &Utc::now(), IsoEntry::ParentDirectory);
-    dirs_sector_size += par_dir.len();
-    dirs_sector.push(par_dir);
+            return Ok(());
+        }
 
-    // files
-    for entry in file_entries
-        .iter()
-        .filter(|t| t.path.components().count() == 2)
-    {
-        let file_name = entry
-            .path
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .to_string();
-
-        let file_dir = IsoDirectoryEntry::new(
-            files_sectors.len(),
-            entry.content.len(),
-            &entry.timestamp,
-            IsoEntry::File(file_name),
-        );
+        self.load_dir(parent).await
+    }
 
-        dirs_sector_size += file_dir.len();
+    /// Decodes directory entries lazily as the stream is polled, without
+    /// building the whole tree first. Subdirectories follow their parent entry.
+    pub fn stream_entries(reader: R) -> impl Stream<Item = Result<(PathBuf, IsoDirectoryEntry)>> {
+        stream::try_unfold(
+            (reader, None::<DirectoryWalker>),
+            |(mut reader, walker)| async move {
+                let mut walker = match walker {
+                    Some(t) => t,
+                    None => {
+                        reader.seek(SeekFrom::Start(0x8000)).await?;
+                        let header = IsoHeaderRaw::read(&mut reader).await?;
+
+                        let layout = header.layout(0)?;
+
+                        DirectoryWalker::new(
+                            layout,
+                            PathBuf::from("/"),
+                            layout.offset(header.root_entry_block()),
+                            Some(header.root_entry_length().into()),
+                        )
+                    }
+                };
 
-        if dirs_sector_size > core::LOGICAL_BLOCK_SIZE {
-            dirs_sectors.push((dirs_sector, SectorProps { group_no, depth }));
-            dirs_sector_size = file_dir.len();
-            dirs_sector = vec![file_dir];
-        } else {
-            dirs_sector.push(file_dir);
-        }
+                let next = walker.next(&mut reader).await?;
 
-        for chunk in entry.content.chunks(core::LOGICAL_BLOCK_SIZE) {
-            files_sectors.push(chunk);
-        }
+                Ok(next.map(|item| (item, (reader, Some(walker)))))
+            },
+        )
     }
 
-    // folders
-    for entry in file_entries
-        .iter()
-        .filter(|t| t.path.components().count() > 2)
-    {
-        let folder_name = entry
-            .path
-            .components()
-            .nth(1)
-            .unwrap()
-            .as_os_str()
-            .to_string_lossy()
-            .to_string();
-
-        if !folders.iter().any(|t| t == &folder_name) {
-            folders.push(folder_name.clone());
-
-            let dir_dir =
-                IsoDirectoryEntry::new(0, 0, &Utc::now(), IsoEntry::Directory(folder_name));
-
-            dirs_sector_size += dir_dir.len();
-
-            if dirs_sector_size > core::LOGICAL_BLOCK_SIZE {
-                dirs_sectors.push((dirs_sector, SectorProps { group_no, depth }));
-                dirs_sector_size = dir_dir.len();
-                dirs_sector = vec![dir_dir];
-            } else {
-                dirs_sector.push(dir_dir);
-            }
-        }
+    /// Reads the whole file into memory, failing with
+    /// [`IsoFileError::AllocationLimit`] past the allocation limit.
+    pub async fn read_file<P: Into<PathBuf> + Ord>(&mut self, path: P) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.read_file_into(path, &mut buffer).await?;
+
+        Ok(buffer)
     }
 
-    dirs_sectors.push((dirs_sector, SectorProps { group_no, depth }));
+    /// Reads the associated file recorded with the same name as the file at `path`.
+    pub async fn read_associated_file<P: Into<PathBuf> + Ord>(
+        &mut self,
+        path: P,
+    ) -> Result<Vec<u8>> {
+        let path = path.into();
+        self.resolve(&path).await?;
+
+        let entry = self
+            .index
+            .entries
+            .associated(&path)
+            .ok_or(IsoFileError::FileNotFound)?;
+
+        let data = FileData::from_extents(
+            entry
+                .extents()
+                .map(|(block, length)| (self.index.layout.offset(block), u64::from(length))),
+        );
+        let length = data.len();
+        self.check_allocation(length)?;
 
-    (dirs_sectors, folders)
-}
+        let mut buffer = vec![0u8; length as usize];
+        IsoFileHandle::new(&mut self.reader, data)
+            .read_exact(&mut buffer)
+            .await?;
 
-fn build_sectors<'r>(
-    dirs_sectors: &mut Vec<(Vec<IsoDirectoryEntry>, SectorProps)>,
-    files_sectors: &mut Vec<&'r [u8]>,
-    group_no: &mut usize,
-    files: &Vec<FileEntry<'r>>,
-    depth: usize,
-    base_path_opt: Option<&Path>,
-) {
-    let base_path = base_path_opt.unwrap_or(Path::new("/"));
+        Ok(buffer)
+    }
 
-    let filtered_entries = files
-        .iter()
-        .filter_map(|t| {
-            if t.path.starts_with(base_path) {
-                let stripped = if base_path_opt.is_some() {
-                    PathBuf::from("/").join(t.path.strip_prefix(base_path).unwrap())
-                } else {
-                    t.path.clone()
-                };
+    /// Like [`read_file`](Self::read_file), replacing the content of `buffer` so
+    /// its allocation can be reused between calls.
+    pub async fn read_file_into<P: Into<PathBuf> + Ord>(
+        &mut self,
+        path: P,
+        buffer: &mut Vec<u8>,
+    ) -> Result<()> {
+        let path = path.into();
+        self.resolve(&path).await?;
 
-                Some(FileEntry {
-                    path: stripped.to_owned(),
-                    content: t.content,
-                    timestamp: t.timestamp,
-                })
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<FileEntry<'_>>>();
+        let data = self.decoded_file_data(&path).await?;
+        let length = data.len();
+        self.check_allocation(length)?;
 
-    let (mut new_dirs_sectors, folders) =
-        build_dirs(filtered_entries, files_sectors, *group_no, depth);
+        let mut handle = IsoFileHandle::new(&mut self.reader, data);
+        handle.set_recovery(self.recovery);
 
-    dirs_sectors.append(&mut new_dirs_sectors);
+        buffer.clear();
+        buffer.resize(length as usize, 0);
 
-    *group_no += 1;
+        let filled = handle.fill(buffer).await?;
 
-    for folder in folders {
-        build_sectors(
-            dirs_sectors,
-            files_sectors,
-            group_no,
-            files,
-            depth + 1,
-            Some(&base_path.join(folder)),
-        );
-    }
-}
+        if filled < buffer.len() {
+            buffer.truncate(filled);
 
-#[derive(Clone, Copy)]
-struct GroupValues {
-    index: usize,
-    count: usize,
-    depth: usize,
-}
+            return Err(IsoFileError::Truncated {
+                data: mem::take(buffer),
+                expected: length,
+            });
+        }
 
-impl GroupValues {
-    fn new(index: usize, depth: usize) -> Self {
-        Self {
-            index,
-            depth,
-            count: 0,
+        Ok(())
+    }
+
+    /// Copies a file to the start of `buffer` and returns its length.
+    ///
+    /// Fails with [`IsoFileError::BufferTooSmall`] if the file does not fit.
+    pub async fn read_file_to_slice<P: Into<PathBuf> + Ord>(
+        &mut self,
+        path: P,
+        buffer: &mut [u8],
+    ) -> Result<usize> {
+        let path = path.into();
+        self.resolve(&path).await?;
+
+        let data = self.decoded_file_data(&path).await?;
+        let length = data.len();
+
+        let target = buffer
+            .get_mut(..length as usize)
+            .ok_or(IsoFileError::BufferTooSmall(length))?;
+
+        let mut handle = IsoFileHandle::new(&mut self.reader, data);
+        handle.set_recovery(self.recovery);
+
+        let filled = handle.fill(target).await?;
+
+        if filled < target.len() {
+            return Err(IsoFileError::Truncated {
+                data: target[..filled].to_vec(),
+                expected: length,
+            });
         }
+
+        Ok(length as usize)
     }
-}
 
-struct Groups(Vec<GroupValues>);
+    /// Untouched bytes of a directory extent, including the system use area of
+    /// every record.
+    pub async fn read_dir_raw<P: Into<PathBuf> + Ord>(&mut self, path: P) -> Result<Vec<u8>> {
+        let path = path.into();
+        self.resolve(&path).await?;
 
-impl Groups {
-    fn new(items: &[(Vec<IsoDirectoryEntry>, SectorProps)]) -> Self {
-        let mut group_info: HashMap<usize, GroupValues> = HashMap::new();
+        let record = match self.index.entries.get(&path) {
+            Some(value) if value.entry().is_file() => return Err(IsoFileError::NotDirectory),
+            Some(value) => value.record(),
+            None => return Err(IsoFileError::FileNotFound),
+        };
 
-        for (index, &(_, props)) in items.iter().enumerate() {
-            group_info
-                .entry(props.group_no)
-                .or_insert(GroupValues::new(index, props.depth))
-                .count += 1;
-        }
+        let location = self.index.layout.offset(record.data_location());
+        let length = record.data_length() as usize;
+        self.check_allocation(length as u64)?;
 
-        let mut value: Vec<GroupValues> = group_info.into_values().collect();
-        value.sort_by_key(|t| t.index);
+        self.reader.seek(SeekFrom::Start(location)).await?;
 
-        Self(value)
-    }
+        let mut buffer = vec![0u8; length];
+        self.reader.read_exact(&mut buffer).await?;
 
-    fn get(&self, index: usize) -> &GroupValues {
-        self.0.get(index).expect("invalid block number")
+        Ok(buffer)
     }
-}
 
-struct ParentDirectoryStack<'r> {
-    stack: VecDeque<GroupValues>,
-    groups: &'r Groups,
-}
+    /// Opens a file as a seekable handle restricted to the file's extents.
+    pub async fn open_file<P: Into<PathBuf> + Ord>(
+        &mut self,
+        path: P,
+    ) -> Result<IsoFileHandle<'_, R>> {
+        let path = path.into();
+        self.resolve(&path).await?;
 
-impl<'r> ParentDirectoryStack<'r> {
-    fn new(groups: &'r Groups) -> Self {
-        Self {
-            stack: VecDeque::new(),
-            groups,
-        }
+        let data = self.decoded_file_data(&path).await?;
+
+        let mut handle = IsoFileHandle::new(&mut self.reader, data);
+        handle.set_recovery(self.recovery);
+
+        Ok(handle)
     }
 
-    fn set(&mut self, props: &SectorProps) {
-        if self.stack.len() != props.depth + 1 {
-            if self.stack.len() < props.depth + 1 {
-                self.stack.push_front(*self.groups.get(props.group_no));
-            } else {
-                self.stack.pop_front();
-            }
-        }
+    /// Streams a file in chunks of one logical block.
+    ///
+    /// In recovery mode the readable part of a truncated file is streamed and
+    /// then [`IsoFileError::Truncated`] ends the stream, with no data since it
+    /// was already given.
+    pub async fn file_stream<P: Into<PathBuf> + Ord>(
+        &mut self,
+        path: P,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + '_> {
+        let chunk_size = self.index.layout.logical_block_size() as usize;
+        let path = path.into();
+        self.resolve(&path).await?;
+
+        let data = self.decoded_file_data(&path).await?;
+
+        Ok(chunk_stream(
+            &mut self.reader,
+            data,
+            chunk_size,
+            self.recovery,
+        ))
     }
 
-    fn get(&self) -> &GroupValues {
-        if self.stack.len() == 1 {
-            self.stack.front().unwrap()
-        } else {
-            self.stack.get(1).expect("empty depth stack")
-        }
+    /// Like [`file_stream`](Self::file_stream), but the stream owns the reader
+    /// so it can outlive this value, e.g. as a response body.
+    pub async fn into_file_stream<P: Into<PathBuf> + Ord>(
+        mut self,
+        path: P,
+    ) -> Result<impl Stream<Item = Result<Bytes>> + use<R, P>> {
+        let chunk_size = self.index.layout.logical_block_size() as usize;
+        let path = path.into();
+        self.resolve(&path).await?;
+
+        let data = self.decoded_file_data(&path).await?;
+
+        Ok(chunk_stream(self.reader, data, chunk_size, self.recovery))
     }
-}
 
-fn set_locations(
-    start_location: usize,
-    dirs_sectors: &mut [(Vec<IsoDirectoryEntry>, SectorProps)],
-) -> Vec<Vec<(String, usize)>> {
-    let dirs_sectors_count = dirs_sectors.len();
+    /// Digest of a file computed while streaming its extent.
+    pub async fn checksum_file<P: Into<PathBuf> + Ord>(
+        &mut self,
+        path: P,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<Vec<u8>> {
+        let chunk_size = self.index.layout.logical_block_size() as usize * 16;
+        let mut handle = self.open_file(path).await?;
 
-    let groups = Groups::new(dirs_sectors);
-    let mut parent_stack = ParentDirectoryStack::new(&groups);
-    let mut count_stack = [0usize; 128];
+        let mut hasher = Hasher::new(algorithm);
+        let mut buffer = vec![0u8; chunk_size];
 
-    let mut path_groups: Vec<Vec<(String, usize)>> = Vec::new();
+        loop {
+            let read = handle.read(&mut buffer).await?;
 
-    // iterate over a group of sectors
-    for (sector, props) in dirs_sectors.iter_mut() {
-        let mut path_group = Vec::new();
+            if read == 0 {
+                break;
+            }
 
-        parent_stack.set(props);
+            hasher.update(&buffer[..read]);
+        }
 
-        for dirs in sector {
-            match dirs.entry() {
-                IsoEntry::CurrentDirectory => {
-                    let group = groups.get(props.group_no);
+        if handle.position() != handle.len() {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
 
-                    dirs.record_mut().set_location(start_location + group.index);
-                    dirs.record_mut()
-                        .set_data_length(group.count * core::LOGICAL_BLOCK_SIZE);
-                }
-                IsoEntry::ParentDirectory => {
-                    let group = parent_stack.get();
+        Ok(hasher.finalize())
+    }
 
-                    dirs.record_mut().set_location(start_location + group.index);
-                    dirs.record_mut()
-                        .set_data_length(group.count * core::LOGICAL_BLOCK_SIZE);
-                }
-                IsoEntry::Directory(name) => {
-                    let mut group;
+    /// Checks the MD5 implanted by `implantisomd5`, as `checkisomd5` does.
+    ///
+    /// Returns `None` if the image has no implanted digest.
+    pub async fn verify_implanted_md5(&mut self) -> Result<Option<bool>> {
+        let base = self.index.layout.base();
+        let descriptor = 16 * core::LOGICAL_BLOCK_SIZE as u64;
 
-                    loop {
-                        count_stack[props.group_no] += 1;
-                        group = groups.get(props.group_no + count_stack[props.group_no]);
+        let Some(sector) = descriptor::read_sector(&mut self.reader, base + descriptor).await?
+        else {
+            return Ok(None);
+        };
 
-                        if group.depth == props.depth + 1 {
-                            break;
-                        }
-                    }
+        let Some((expected, skip)) = checksum::implanted_md5(&sector[APPLICATION_USE]) else {
+            return Ok(None);
+        };
 
-                    let location = start_location + group.index;
+        let volume_size = u64::from(u32::from_le_bytes(sector[80..84].try_into().unwrap()));
+        let length = volume_size.saturating_sub(skip) * core::LOGICAL_BLOCK_SIZE as u64;
+        let blank =
+            descriptor + APPLICATION_USE.start as u64..descriptor + APPLICATION_USE.end as u64;
 
-                    path_group.push((name.clone(), location));
+        let mut hasher = Hasher::new(ChecksumAlgorithm::Md5);
+        let mut buffer = vec![0u8; core::LOGICAL_BLOCK_SIZE * 16];
+        let mut position = 0;
 
-                    dirs.record_mut().set_location(location);
-                    dirs.record_mut()
-                        .set_data_length(group.count * core::LOGICAL_BLOCK_SIZE);
-                }
-                IsoEntry::File(_) => {
-                    let location =  dirs.record().location(None) as usize;
+        self.reader.seek(SeekFrom::Start(base)).await?;
 
-                    dirs.record_mut().set_location(
-                        start_location + dirs_sectors_count + location,
-                    );
-                }
+        while position < length {
+            let read = buffer.len().min((length - position) as usize);
+            let chunk = &mut buffer[..read];
+            self.reader.read_exact(chunk).await?;
+
+            let end = position + read as u64;
+            if blank.start < end && position < blank.end {
+                let start = blank.start.max(position) - position;
+                let stop = blank.end.min(end) - position;
+                chunk[start as usize..stop as usize].fill(b' ');
             }
+
+            hasher.update(chunk);
+            position = end;
         }
 
-        path_groups.push(path_group);
+        let digest: String = hasher
+            .finalize()
+            .iter()
+            .map(|t| format!("{t:02x}"))
+            .collect();
+
+        Ok(Some(digest == expected))
     }
 
-    path_groups
-}
+    /// Like the following checks, loads the directories on the way in lazy or
+    /// path table lookup mode and reports `false` if that fails.
+    pub async fn exists<P: Into<PathBuf> + Ord>(&mut self, path: P) -> bool {
+        let path = path.into();
 
-#[derive(Clone, Debug)]
-pub struct IsoFileWriter<'r, W>
-where
-    W: AsyncWrite + Unpin,
-{
-    header: IsoHeader,
-    files: Vec<FileEntry<'r>>,
-    writer: W,
-}
+        if self.resolve(&path).await.is_err() {
+            return false;
+        }
 
-impl<'r, W> IsoFileWriter<'r, W>
-where
-    W: AsyncWrite + Unpin,
-{
-    pub async fn new(writer: W, header: IsoHeader) -> io::Result<Self> {
-        Ok(Self {
-            writer,
-            header,
-            files: Vec::new(),
-        })
+        self.udf_entry(&path).is_some() || self.index.entries.get(&path).is_some()
     }
 
-    pub fn append_file(&mut self, path: &str, content: &'r [u8], timestamp: DateTime<Utc>) {
-        let a_characters = path
-            .to_uppercase()
-            .chars()
-            .filter(|&c| {
-                matches!(c,
-            'A'..='Z' | '0'..='9' | '_' |
-            '!' | '"' | '%' | '&' | '\'' | '(' | ')' | '*' | '+' | ',' | '-' | '.' | '/' |
-            ':' | ';' | '<' | '=' | '>' | '?')
-            })
-            .collect::<String>();
+    pub async fn is_file<P: Into<PathBuf> + Ord>(&mut self, path: P) -> bool {
+        let path = path.into();
 
-        let mut new_path = PathBuf::new();
+        if self.resolve(&path).await.is_err() {
+            return false;
+        }
 
-        for component in PathBuf::from(a_characters).components() {
-            new_path.push(
-                component
-                    .as_os_str()
-                    .to_string_lossy()
-                    .chars()
-                    .take(222)
-                    .collect::<String>(),
-            );
+        if let Some(entry) = self.udf_entry(&path) {
+            return !entry.is_dir();
         }
 
-        self.files.push(FileEntry {
-            path: new_path,
-            content,
-            timestamp,
-        });
+        self.index
+            .entries
+            .get(&path)
+            .is_some_and(|t| t.entry().is_file())
     }
 
-    pub async fn close(&mut self) -> Result<()> {
-        let mut dirs_sectors: Vec<(Vec<IsoDirectoryEntry>, SectorProps)> = Vec::new();
-        let mut files_sectors: Vec<&'r [u8]> = Vec::new();
+    /// True for directories, including the root.
+    pub async fn is_dir<P: Into<PathBuf> + Ord>(&mut self, path: P) -> bool {
+        let path = path.into();
 
-        let mut group_no = 0;
+        if self.resolve(&path).await.is_err() {
+            return false;
+        }
 
-        build_sectors(
-            &mut dirs_sectors,
-            &mut files_sectors,
+        if let Some(entry) = self.udf_entry(&path) {
+            return entry.is_dir();
+        }
+
+        self.index.entries.get(&path).is_some_and(|t| {
+            matches!(
+                t.entry(),
+                IsoEntry::Directory(_) | IsoEntry::CurrentDirectory
+            )
+        })
+    }
+
+    pub async fn metadata<P: Into<PathBuf> + Ord>(&mut self, path: P) -> Result<FileMetadata> {
+        let path = path.into();
+        self.resolve(&path).await?;
+
+        if let Some(entry) = self.udf_entry(&path) {
+            return Ok(entry.metadata());
+        }
+
+        self.index
+            .entries
+            .get(&path)
+            .map(|t| t.metadata())
+            .ok_or(IsoFileError::FileNotFound)
+    }
+
+    fn check_allocation(&self, length: u64) -> Result<()> {
+        if length > self.index.limits.max_allocation {
+            return Err(IsoFileError::AllocationLimit(length));
+        }
+
+        Ok(())
+    }
+
+    // UDF entry of a path, preferred over the ISO 9660 tree
+    fn udf_entry(&self, path: &Path) -> Option<&UdfEntry> {
+        self.index.udf.as_ref()?.get(path)
+    }
+
+    // extents or embedded content of a file entry
+    fn file_data(&self, path: &Path) -> Result<Cow<'_, FileData>> {
+        if let Some(entry) = self.udf_entry(path) {
+            if entry.is_dir() {
+                return Err(IsoFileError::EntryDirectory);
+            }
+
+            return Ok(Cow::Borrowed(&entry.data));
+        }
+
+        match self.index.entries.get(path) {
+            Some(value) => match value.entry() {
+                IsoEntry::CurrentDirectory => Err(IsoFileError::EntryCurrentDirectory),
+                IsoEntry::ParentDirectory => Err(IsoFileError::EntryParentDirectory),
+                IsoEntry::Directory(_) => Err(IsoFileError::EntryDirectory),
+                IsoEntry::File(_) => {
+                    Ok(Cow::Owned(FileData::from_extents(value.extents().map(
+                        |(block, length)| (self.index.layout.offset(block), u64::from(length)),
+                    ))))
+                }
+            },
+            None => Err(IsoFileError::FileNotFound),
+        }
+    }
+
+    // file data with zisofs content inflated in memory
+    async fn decoded_file_data(&mut self, path: &Path) -> Result<FileData> {
+        let data = self.file_data(path)?.into_owned();
+
+        let zisofs = match self.index.entries.get(path).and_then(|t| t.zisofs()) {
+            Some(zisofs) if self.udf_entry(path).is_none() => zisofs,
+            _ => return Ok(data),
+        };
+
+        self.check_allocation(data.len().max(u64::from(zisofs.size)))?;
+
+        let mut stored = vec![0u8; data.len() as usize];
+        IsoFileHandle::new(&mut self.reader, data)
+            .read_exact(&mut stored)
+            .await?;
+
+        Ok(FileData::Embedded(zisofs.decompress(&stored)?))
+    }
+
+    pub fn header(&self) -> IsoHeader {
+        self.index.header()
+    }
+
+    /// Start block of every session that was read, see [`IsoDirectoryEntry::session`].
+    pub fn sessions(&self) -> &[u32] {
+        self.index.sessions()
+    }
+
+    pub fn entries(&self) -> &IsoDirectoryEntries {
+        self.index.entries()
+    }
+
+    pub fn path_table(&self) -> &IsoPathTable {
+        self.index.path_table()
+    }
+
+    pub fn optional_path_tables(&self) -> &[IsoPathTable] {
+        self.index.optional_path_tables()
+    }
+
+    pub fn udf_entries(&self) -> Option<&UdfEntries> {
+        self.index.udf_entries()
+    }
+
+    /// Every path the lookups find in path order, the UDF tree of a bridge
+    /// disc merged with the loaded ISO 9660 tree and preferred like there.
+    pub fn walk(&self) -> impl Iterator<Item = (PathBuf, FileMetadata)> + use<R> {
+        let mut paths: BTreeMap<PathBuf, FileMetadata> = self
+            .index
+            .entries
+            .get(Path::new("/"))
+            .map(|t| (PathBuf::from("/"), t))
+            .into_iter()
+            .chain(self.index.entries.walk(true))
+            .map(|(path, value)| (path, value.metadata()))
+            .collect();
+
+        if let Some(udf) = &self.index.udf {
+            for (path, entry) in udf.walk() {
+                paths.insert(path.to_path_buf(), entry.metadata());
+            }
+        }
+
+        paths.into_iter()
+    }
+
+    /// Every volume descriptor of the last session read, up to and including
+    /// the set terminator.
+    pub async fn volume_descriptors(&mut self) -> Result<Vec<VolumeDescriptor>> {
+        let session_start = *self
+            .index
+            .sessions
+            .last()
+            .expect("at least one session is read");
+
+        descriptor::read_descriptors(&mut self.reader, self.index.layout.base(), session_start)
+            .await
+    }
+
+    /// Boot record descriptor of the last session, for any boot system.
+    pub async fn boot_record(&mut self) -> Result<Option<BootRecord>> {
+        Ok(self
+            .volume_descriptors()
+            .await?
+            .iter()
+            .find_map(|t| t.boot_record()))
+    }
+
+    /// Volume partitions declared in the last session.
+    pub async fn volume_partitions(&mut self) -> Result<Vec<VolumePartition>> {
+        Ok(self
+            .volume_descriptors()
+            .await?
+            .iter()
+            .filter_map(|t| t.volume_partition())
+            .collect())
+    }
+
+    /// El Torito boot catalog of the last session, `None` if it is not bootable.
+    pub async fn boot_catalog(&mut self) -> Result<Option<BootCatalog>> {
+        let block = self
+            .volume_descriptors()
+            .await?
+            .iter()
+            .find_map(|t| t.boot_catalog());
+
+        match block {
+            Some(block) => {
+                let base = self.index.layout.base();
+                Ok(Some(
+                    BootCatalog::read(&mut self.reader, base, block).await?,
+                ))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the image of the default boot catalog entry.
+    pub async fn read_boot_image(&mut self) -> Result<Vec<u8>> {
+        let catalog = self
+            .boot_catalog()
+            .await?
+            .ok_or(IsoFileError::NoBootRecord)?;
+
+        self.read_boot_entry(&catalog.default_entry).await
+    }
+
+    /// Reads the image of any boot catalog entry, e.g. one of a section. The
+    /// image of an emulated hard disk ends with its last partition.
+    pub async fn read_boot_entry(&mut self, entry: &BootEntry) -> Result<Vec<u8>> {
+        let base = self.index.layout.base();
+        let size = el_torito::boot_image_size(&mut self.reader, base, entry).await?;
+        self.check_allocation(size)?;
+
+        el_torito::read_boot_image(&mut self.reader, base, entry, size).await
+    }
+
+    /// Extended attribute record of a file or directory, `None` if it has none.
+    pub async fn extended_attributes<P: Into<PathBuf> + Ord>(
+        &mut self,
+        path: P,
+    ) -> Result<Option<ExtendedAttributes>> {
+        let path = path.into();
+        self.resolve(&path).await?;
+
+        let record = match self.index.entries.get(&path) {
+            Some(value) if value.record().extended_attribute_length() > 0 => value.record(),
+            Some(_) => return Ok(None),
+            None => return Err(IsoFileError::FileNotFound),
+        };
+
+        let location = self.index.layout.offset(record.location(None));
+        self.reader.seek(SeekFrom::Start(location)).await?;
+
+        let mut buffer = vec![0u8; xar::XAR_SIZE];
+        self.reader.read_exact(&mut buffer).await?;
+
+        Ok(ExtendedAttributes::parse(&buffer))
+    }
+
+    /// MBR, GPT and Apple partition maps in the system area of the image.
+    pub async fn partition_tables(&mut self) -> Result<PartitionTables> {
+        PartitionTables::read(&mut self.reader, self.index.layout.base()).await
+    }
+
+    /// Probes the volume descriptors and the root directory for extensions.
+    pub async fn capabilities(&mut self) -> Result<IsoCapabilities> {
+        let session_start = *self
+            .index
+            .sessions
+            .last()
+            .expect("at least one session is read");
+        let root_location = self
+            .index
+            .layout
+            .offset(self.index.header.root_entry_block());
+
+        IsoCapabilities::probe(
+            &mut self.reader,
+            self.index.layout,
+            session_start,
+            root_location,
+        )
+        .await
+    }
+
+    pub fn stats(&self) -> IsoStats {
+        self.index.entries.stats()
+    }
+
+    /// Loads the whole tree and checks it against the descriptor, the path
+    /// table and the size of the image.
+    pub async fn verify(&mut self) -> Result<VerifyReport> {
+        self.load_all().await?;
+        let image_len = self.reader.seek(SeekFrom::End(0)).await?;
+
+        Ok(VerifyReport::check(&self.index, image_len))
+    }
+
+    /// The parsed index, to share with readers over other handles to the image.
+    pub fn index(&self) -> &Arc<IsoIndex> {
+        &self.index
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Moving the position of the inner reader does not affect later reads.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R> IsoFileReader<RawSectorReader<R>>
+where
+    R: AsyncRead + AsyncSeekExt + Unpin,
+{
+    /// Reads an image stored as raw CD sectors, e.g. a `.bin` rip.
+    pub async fn read_raw(reader: R, mode: SectorMode) -> Result<Self> {
+        Self::read(RawSectorReader::new(reader, mode)).await
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "xz"))]
+impl<R> IsoFileReader<DecompressReader<R>>
+where
+    R: AsyncRead + AsyncSeekExt + Unpin,
+{
+    /// Reads a compressed image such as an `.iso.zst`.
+    pub async fn read_compressed(reader: R, compression: Compression) -> Result<Self> {
+        Self::read(DecompressReader::new(reader, compression)).await
+    }
+}
+
+impl<T> IsoFileReader<Cursor<T>>
+where
+    T: AsRef<[u8]> + Unpin,
+{
+    /// Content of a file borrowed straight from the in-memory image.
+    pub fn file_bytes<P: Into<PathBuf> + Ord>(&self, path: P) -> Result<&[u8]> {
+        let path = path.into();
+
+        if self.udf_entry(&path).is_none()
+            && self
+                .index
+                .entries
+                .get(&path)
+                .is_some_and(|t| t.is_compressed())
+        {
+            return Err(IsoFileError::Compressed);
+        }
+
+        let (location, length) = match self.file_data(&path)? {
+            Cow::Borrowed(FileData::Embedded(content)) => return Ok(content),
+            data => data.contiguous().ok_or(IsoFileError::Fragmented)?,
+        };
+
+        self.reader
+            .get_ref()
+            .as_ref()
+            .get(location as usize..(location + length) as usize)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof).into())
+    }
+}
+
+impl IsoFileReader<File> {
+    /// Opens the image file at `path` and reads it.
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read(File::open(path).await?).await
+    }
+}
+
+impl IsoFileReader<RawSectorReader<File>> {
+    /// Opens the first data track of a CUE sheet and its BIN file.
+    pub async fn open_cue<P: AsRef<Path>>(cue_path: P) -> Result<Self> {
+        let cue_path = cue_path.as_ref();
+        let sheet = CueSheet::parse(&fs::read_to_string(cue_path).await?)?;
+
+        let (file, track) = sheet.first_data_track().ok_or(IsoFileError::NoDataTrack)?;
+        let mode = track.mode.sector_mode().ok_or(IsoFileError::NoDataTrack)?;
+
+        let bin_path = cue_path.parent().unwrap_or(Path::new("")).join(&file.name);
+
+        let reader = RawSectorReader::new(File::open(bin_path).await?, mode);
+
+        let mut options = IsoReadOptions::default();
+        options.set_start_offset(u64::from(track.start) * core::LOGICAL_BLOCK_SIZE as u64);
+
+        Self::read_with_options(reader, options).await
+    }
+}
+
+/* WRITE */
+
+enum FileContent<'r> {
+    Bytes(Cow<'r, [u8]>),
+    Reader {
+        reader: Box<dyn AsyncRead + Send + Unpin + 'r>,
+        length: usize,
+    },
+    Path {
+        source: PathBuf,
+        length: usize,
+    },
+    Stream {
+        stream: BoxStream<'r, Bytes>,
+        length: usize,
+    },
+    // already written by `stream_file`, `location` counts from the data start
+    Written {
+        location: usize,
+        length: usize,
+    },
+    Directory,
+    // left behind by a directory relocated to RR_MOVED
+    Relocated,
+    // hard link sharing the extent of the file stored at `target`
+    Link {
+        target: PathBuf,
+        length: usize,
+    },
+}
+
+impl FileContent<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Self::Bytes(t) => t.len(),
+            Self::Reader { length, .. } => *length,
+            Self::Path { length, .. } => *length,
+            Self::Stream { length, .. } => *length,
+            Self::Written { length, .. } => *length,
+            Self::Link { length, .. } => *length,
+            Self::Directory | Self::Relocated => 0,
+        }
+    }
+}
+
+impl std::fmt::Debug for FileContent<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bytes(t) => f.debug_tuple("Bytes").field(&t.len()).finish(),
+            Self::Reader { length, .. } => f.debug_tuple("Reader").field(length).finish(),
+            Self::Path { source, length } => {
+                f.debug_tuple("Path").field(source).field(length).finish()
+            }
+            Self::Stream { length, .. } => f.debug_tuple("Stream").field(length).finish(),
+            Self::Written { location, length } => f
+                .debug_tuple("Written")
+                .field(location)
+                .field(length)
+                .finish(),
+            Self::Directory => f.write_str("Directory"),
+            Self::Relocated => f.write_str("Relocated"),
+            Self::Link { target, length } => {
+                f.debug_tuple("Link").field(target).field(length).finish()
+            }
+        }
+    }
+}
+
+// streams `data` in chunks of `chunk_size`, ending with an empty
+// `Truncated` error where a recovered file stops early
+fn chunk_stream<T>(
+    reader: T,
+    data: FileData,
+    chunk_size: usize,
+    recovery: bool,
+) -> impl Stream<Item = Result<Bytes>> + use<T>
+where
+    T: AsyncRead + AsyncSeek + Unpin,
+{
+    stream::try_unfold(
+        (reader, data, 0u64),
+        move |(mut reader, data, position)| async move {
+            let mut handle = IsoFileHandle::new(&mut reader, data);
+            handle.set_recovery(recovery);
+
+            let remaining = handle.len() - position;
+
+            if remaining == 0 {
+                return Ok(None);
+            }
+
+            handle.seek(SeekFrom::Start(position)).await?;
+
+            let mut buffer = BytesMut::zeroed(chunk_size.min(remaining as usize));
+            let read = handle.fill(&mut buffer).await?;
+
+            if read == 0 {
+                return Err(IsoFileError::Truncated {
+                    data: Vec::new(),
+                    expected: handle.len(),
+                });
+            }
+
+            buffer.truncate(read);
+            let data = handle.into_data();
+
+            Ok(Some((
+                buffer.freeze(),
+                (reader, data, position + read as u64),
+            )))
+        },
+    )
+}
+
+// zeroed sector every padding of the writer is copied from, so no zero buffer
+// is allocated while writing
+static ZEROES: [u8; core::LOGICAL_BLOCK_SIZE] = [0; core::LOGICAL_BLOCK_SIZE];
+
+// writes `length` zero bytes, one sector at a time
+async fn write_zeroes<W: AsyncWrite + Unpin>(writer: &mut W, mut length: usize) -> io::Result<()> {
+    while length > 0 {
+        let chunk = length.min(ZEROES.len());
+        writer.write_all(&ZEROES[..chunk]).await?;
+        length -= chunk;
+    }
+
+    Ok(())
+}
+
+// copies up to `length` bytes one sector at a time through `sector`, adding
+// them to `progress` and stopping once `cancel` is set
+async fn copy_sectors<R, W>(
+    source: &mut R,
+    writer: &mut W,
+    length: usize,
+    sector: &mut [u8],
+    progress: &Progress,
+    cancel: &Cancel,
+) -> Result<usize>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + Unpin,
+{
+    let mut copied = 0;
+
+    while copied < length {
+        cancel.check()?;
+
+        let wanted = (length - copied).min(sector.len());
+        let read = source.read(&mut sector[..wanted]).await?;
+
+        if read == 0 {
+            break;
+        }
+
+        writer.write_all(&sector[..read]).await?;
+        progress.advance(read);
+        copied += read;
+    }
+
+    Ok(copied)
+}
+
+#[derive(Debug)]
+struct FileEntry<'r> {
+    path: PathBuf,
+    content: FileContent<'r>,
+    timestamp: DateTime<FixedOffset>,
+    version: Option<u16>,
+    hidden: bool,
+    volume_seq_number: Option<u16>,
+    weight: i32,
+    // block the data is pinned at
+    location: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SectorProps {
+    group_no: usize,
+    depth: usize,
+}
+
+// orders identifiers by name, then extension, both padded with spaces, then
+// by descending version
+fn collate_ids(a: &str, b: &str) -> Ordering {
+    fn split(id: &str) -> (&[u8], &[u8], u32) {
+        let (id, version) = id.split_once(';').unwrap_or((id, ""));
+        let (name, extension) = id.split_once('.').unwrap_or((id, ""));
+
+        (
+            name.as_bytes(),
+            extension.as_bytes(),
+            version.parse().unwrap_or(0),
+        )
+    }
+
+    fn padded(a: &[u8], b: &[u8]) -> Ordering {
+        let len = a.len().max(b.len());
+        let a = a.iter().copied().chain(std::iter::repeat(b' ')).take(len);
+        let b = b.iter().copied().chain(std::iter::repeat(b' ')).take(len);
+        a.cmp(b)
+    }
+
+    let (a_name, a_extension, a_version) = split(a);
+    let (b_name, b_extension, b_version) = split(b);
+
+    padded(a_name, b_name)
+        .then_with(|| padded(a_extension, b_extension))
+        .then_with(|| b_version.cmp(&a_version))
+}
+
+// values shared by every directory of a layout: `timestamp` dates the
+// directories not given one by create_dir, records refer to volume `volume`
+// unless a file overrides it, file data takes blocks of `block_size` bytes and
+// `moved` maps the paths relocated directories leave behind to their new ones
+struct DirContext<'a> {
+    timestamp: DateTime<Utc>,
+    volume: u16,
+    block_size: usize,
+    // blocks every file extent is a multiple of
+    alignment: usize,
+    moved: &'a BTreeMap<PathBuf, PathBuf>,
+    // all-zero files stored in the extent of another one, by file index
+    shared: &'a BTreeMap<usize, usize>,
+}
+
+// `file_entries` pairs the index of each file with its path relative to
+// `dir_path` and `files_order` receives the indexes in the order data is
+// written
+fn build_dirs(
+    file_entries: Vec<(PathBuf, usize, &FileEntry<'_>)>,
+    files_order: &mut Vec<usize>,
+    files_blocks: &mut usize,
+    group_no: usize,
+    depth: usize,
+    dir_path: &Path,
+    context: &DirContext<'_>,
+) -> (Vec<(Vec<IsoDirectoryEntry>, SectorProps)>, Vec<String>) {
+    let mut dirs_sector = Vec::new();
+    let mut dirs_sectors = Vec::new();
+    let mut folders: Vec<String> = Vec::new();
+
+    let mut dirs_sector_size = 0;
+    let volume = context.volume;
+    let fixed_timestamp = context.timestamp.fixed_offset();
+    let is_moved = |path: &Path| context.moved.values().any(|t| t == path);
+
+    let mut cur_dir = IsoDirectoryEntry::new(0, 0, &fixed_timestamp, IsoEntry::CurrentDirectory);
+    cur_dir.set_volume_seq_number(volume);
+
+    if depth == 0 && !context.moved.is_empty() {
+        cur_dir.set_system_use(rock_ridge::sp_entry());
+    }
+
+    dirs_sector_size += cur_dir.len();
+    dirs_sector.push(cur_dir);
+
+    let mut par_dir = IsoDirectoryEntry::new(0, 0, &fixed_timestamp, IsoEntry::ParentDirectory);
+    par_dir.set_volume_seq_number(volume);
+
+    // located once the original parent is
+    if is_moved(dir_path) {
+        par_dir.set_system_use(rock_ridge::pl_entry(0));
+    }
+
+    dirs_sector_size += par_dir.len();
+    dirs_sector.push(par_dir);
+
+    // files and first level folders, explicit directories come as a path to
+    // themselves and folders carry no file
+    let mut children: Vec<(String, Option<(usize, &FileEntry<'_>)>)> = Vec::new();
+
+    for &(ref path, index, entry) in &file_entries {
+        let count = path.components().count();
+        let is_dir = matches!(entry.content, FileContent::Directory);
+
+        if !is_dir && count == 2 {
+            let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+            children.push((file_name, Some((index, entry))));
+        } else if count >= 2 {
+            let folder_name = path
+                .components()
+                .nth(1)
+                .unwrap()
+                .as_os_str()
+                .to_string_lossy()
+                .to_string();
+
+            if !folders.iter().any(|t| t == &folder_name) {
+                folders.push(folder_name.clone());
+                children.push((folder_name, None));
+            }
+        }
+    }
+
+    // records are sorted by identifier as ECMA-119 9.3 requires
+    children.sort_by(|a, b| collate_ids(&a.0, &b.0));
+    folders = children
+        .iter()
+        .filter(|t| t.1.is_none())
+        .map(|t| t.0.clone())
+        .collect();
+
+    for (name, file) in children {
+        let record = match file {
+            Some((index, entry)) => {
+                let location = match entry.content {
+                    FileContent::Written { location, .. } => location,
+                    // located once every file is
+                    FileContent::Relocated | FileContent::Link { .. } => 0,
+                    _ if context.shared.contains_key(&index) => 0,
+                    _ => {
+                        let location = *files_blocks;
+                        files_order.push(index);
+                        *files_blocks += entry
+                            .content
+                            .len()
+                            .div_ceil(context.block_size)
+                            .next_multiple_of(context.alignment);
+                        location
+                    }
+                };
+
+                let mut record = IsoDirectoryEntry::new(
+                    location,
+                    entry.content.len(),
+                    &entry.timestamp,
+                    IsoEntry::File(name),
+                );
+                record.set_version(entry.version);
+                record.set_hidden(entry.hidden);
+                record.set_volume_seq_number(entry.volume_seq_number.unwrap_or(volume));
+
+                // located once the moved directory is
+                if matches!(entry.content, FileContent::Relocated) {
+                    record.set_system_use(rock_ridge::cl_entry(0));
+                }
+
+                record
+            }
+            None => {
+                // entry given to create_dir, if any
+                let explicit = file_entries.iter().find(|t| {
+                    matches!(t.2.content, FileContent::Directory)
+                        && t.0.components().count() == 2
+                        && t.0.ends_with(&name)
+                });
+                let dir_timestamp = explicit.map_or(fixed_timestamp, |t| t.2.timestamp);
+
+                let moved = is_moved(&dir_path.join(&name));
+
+                let mut record =
+                    IsoDirectoryEntry::new(0, 0, &dir_timestamp, IsoEntry::Directory(name));
+                record.set_hidden(explicit.is_some_and(|t| t.2.hidden));
+                record.set_volume_seq_number(volume);
+
+                if moved {
+                    record.set_system_use(rock_ridge::re_entry());
+                }
+
+                record
+            }
+        };
+
+        dirs_sector_size += record.len();
+
+        if dirs_sector_size > core::LOGICAL_BLOCK_SIZE {
+            dirs_sectors.push((dirs_sector, SectorProps { group_no, depth }));
+            dirs_sector_size = record.len();
+            dirs_sector = vec![record];
+        } else {
+            dirs_sector.push(record);
+        }
+    }
+
+    dirs_sectors.push((dirs_sector, SectorProps { group_no, depth }));
+
+    (dirs_sectors, folders)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_sectors(
+    dirs_sectors: &mut Vec<(Vec<IsoDirectoryEntry>, SectorProps)>,
+    files_order: &mut Vec<usize>,
+    files_blocks: &mut usize,
+    group_no: &mut usize,
+    files: &[(PathBuf, usize, &FileEntry<'_>)],
+    depth: usize,
+    base_path_opt: Option<&Path>,
+    context: &DirContext<'_>,
+) {
+    let base_path = base_path_opt.unwrap_or(Path::new("/"));
+
+    let filtered_entries = files
+        .iter()
+        .filter_map(|&(ref path, index, t)| {
+            if path.starts_with(base_path) {
+                let stripped = if base_path_opt.is_some() {
+                    PathBuf::from("/").join(path.strip_prefix(base_path).unwrap())
+                } else {
+                    path.clone()
+                };
+
+                Some((stripped, index, t))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let (mut new_dirs_sectors, folders) = build_dirs(
+        filtered_entries,
+        files_order,
+        files_blocks,
+        *group_no,
+        depth,
+        base_path,
+        context,
+    );
+
+    dirs_sectors.append(&mut new_dirs_sectors);
+
+    *group_no += 1;
+
+    for folder in folders {
+        build_sectors(
+            dirs_sectors,
+            files_order,
+            files_blocks,
+            group_no,
+            files,
+            depth + 1,
+            Some(&base_path.join(folder)),
+            context,
+        );
+    }
+}
+
+#[derive(Clone, Copy)]
+struct GroupValues {
+    index: usize,
+    count: usize,
+    depth: usize,
+}
+
+impl GroupValues {
+    fn new(index: usize, depth: usize) -> Self {
+        Self {
+            index,
+            depth,
+            count: 0,
+        }
+    }
+}
+
+struct Groups(Vec<GroupValues>);
+
+impl Groups {
+    fn new(items: &[(Vec<IsoDirectoryEntry>, SectorProps)]) -> Self {
+        let mut group_info: HashMap<usize, GroupValues> = HashMap::new();
+
+        for (index, &(_, props)) in items.iter().enumerate() {
+            group_info
+                .entry(props.group_no)
+                .or_insert(GroupValues::new(index, props.depth))
+                .count += 1;
+        }
+
+        let mut value: Vec<GroupValues> = group_info.into_values().collect();
+        value.sort_by_key(|t| t.index);
+
+        Self(value)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn get(&self, index: usize) -> &GroupValues {
+        self.0.get(index).expect("invalid block number")
+    }
+}
+
+struct ParentDirectoryStack<'r> {
+    stack: VecDeque<GroupValues>,
+    groups: &'r Groups,
+}
+
+impl<'r> ParentDirectoryStack<'r> {
+    fn new(groups: &'r Groups) -> Self {
+        Self {
+            stack: VecDeque::new(),
+            groups,
+        }
+    }
+
+    fn set(&mut self, props: &SectorProps) {
+        if self.stack.len() != props.depth + 1 {
+            if self.stack.len() < props.depth + 1 {
+                self.stack.push_front(*self.groups.get(props.group_no));
+            } else {
+                self.stack.pop_front();
+            }
+        }
+    }
+
+    fn get(&self) -> &GroupValues {
+        if self.stack.len() == 1 {
+            self.stack.front().unwrap()
+        } else {
+            self.stack.get(1).expect("empty depth stack")
+        }
+    }
+}
+
+// bytes of the path table of the directories in `dirs_sectors` and the root
+fn path_table_size(dirs_sectors: &[(Vec<IsoDirectoryEntry>, SectorProps)]) -> usize {
+    dirs_sectors
+        .iter()
+        .flat_map(|t| &t.0)
+        .filter_map(|t| match t.entry() {
+            IsoEntry::Directory(name) => Some(name.len()),
+            _ => None,
+        })
+        .fold(10, |size, len| size + 8 + len.next_multiple_of(2))
+}
+
+fn set_locations(
+    start_location: usize,
+    files_start: usize,
+    dirs_sectors: &mut [(Vec<IsoDirectoryEntry>, SectorProps)],
+    block_size: usize,
+) -> BTreeMap<usize, Vec<(String, usize)>> {
+    let groups = Groups::new(dirs_sectors);
+    let mut parent_stack = ParentDirectoryStack::new(&groups);
+    // subdirectories of every group located so far
+    let mut count_stack = vec![0usize; groups.len()];
+
+    // directories keep 2048-byte sectors, which records may not cross
+    let sector_blocks = core::LOGICAL_BLOCK_SIZE / block_size;
+
+    // subdirectories by location of their parent
+    let mut path_groups: BTreeMap<usize, Vec<(String, usize)>> = BTreeMap::new();
+
+    // iterate over a group of sectors
+    for (sector, props) in dirs_sectors.iter_mut() {
+        let mut path_group = Vec::new();
+
+        parent_stack.set(props);
+
+        for dirs in sector {
+            match dirs.entry() {
+                IsoEntry::CurrentDirectory => {
+                    let group = groups.get(props.group_no);
+
+                    dirs.record_mut()
+                        .set_location(start_location + group.index * sector_blocks);
+                    dirs.record_mut()
+                        .set_data_length(group.count * core::LOGICAL_BLOCK_SIZE);
+                }
+                IsoEntry::ParentDirectory => {
+                    let group = parent_stack.get();
+
+                    dirs.record_mut()
+                        .set_location(start_location + group.index * sector_blocks);
+                    dirs.record_mut()
+                        .set_data_length(group.count * core::LOGICAL_BLOCK_SIZE);
+                }
+                IsoEntry::Directory(name) => {
+                    let mut group;
+
+                    loop {
+                        count_stack[props.group_no] += 1;
+                        group = groups.get(props.group_no + count_stack[props.group_no]);
+
+                        if group.depth == props.depth + 1 {
+                            break;
+                        }
+                    }
+
+                    let location = start_location + group.index * sector_blocks;
+
+                    path_group.push((name.clone(), location));
+
+                    dirs.record_mut().set_location(location);
+                    dirs.record_mut()
+                        .set_data_length(group.count * core::LOGICAL_BLOCK_SIZE);
+                }
+                IsoEntry::File(_) => {
+                    let location = dirs.record().location(None) as usize;
+
+                    dirs.record_mut().set_location(files_start + location);
+                }
+            }
+        }
+
+        let location = start_location + groups.get(props.group_no).index * sector_blocks;
+        path_groups.entry(location).or_default().extend(path_group);
+    }
+
+    path_groups
+}
+
+// places file data from `start`, the data of heavier files first, files of the
+// same weight keeping the order of their directories, and pinned files at their
+// block with the others around them. Locations count from block `files_start`
+// and the records given them one after the other by build_dirs are updated.
+// Returns the files with their location, in this order, the end of the data
+// and a pinned file that does not fit, if any
+fn place_files(
+    files: &[FileEntry<'_>],
+    mut files_order: Vec<usize>,
+    dirs_sectors: &mut [(Vec<IsoDirectoryEntry>, SectorProps)],
+    start: usize,
+    files_start: usize,
+    context: &DirContext<'_>,
+) -> (Vec<(usize, usize)>, usize, Option<usize>) {
+    let blocks = |index: usize| {
+        files[index]
+            .content
+            .len()
+            .div_ceil(context.block_size)
+            .next_multiple_of(context.alignment)
+    };
+
+    let mut old_locations = Vec::new();
+    let mut location = start;
+
+    for &index in files_order.iter() {
+        old_locations.push((index, location));
+        location += blocks(index);
+    }
+
+    let unsorted = files_order
+        .iter()
+        .all(|t| files[*t].weight == 0 && files[*t].location.is_none());
+
+    if unsorted {
+        return (old_locations, location, None);
+    }
+
+    files_order.sort_by_key(|t| std::cmp::Reverse(files[*t].weight));
+
+    let overlaps = |a: usize, a_index: usize, b: usize, b_index: usize| {
+        a < b + blocks(b_index) && b < a + blocks(a_index)
+    };
+
+    // pinned files that fit, the lower blocks first
+    let mut pins: Vec<(u32, usize)> = files_order
+        .iter()
+        .filter_map(|t| Some((files[*t].location?, *t)))
+        .collect();
+    pins.sort();
+
+    let mut pinned: Vec<(usize, usize)> = Vec::new();
+    let mut conflict = None;
+
+    for (block, index) in pins {
+        let fitting = (block as usize)
+            .checked_sub(files_start)
+            .filter(|t| *t >= start)
+            .filter(|t| !pinned.iter().any(|p| overlaps(*t, index, p.0, p.1)));
+
+        match fitting {
+            Some(location) => pinned.push((location, index)),
+            None => {
+                conflict.get_or_insert(index);
+            }
+        }
+    }
+
+    let mut placed: Vec<(usize, usize)> = pinned.iter().map(|t| (t.1, t.0)).collect();
+    let mut location = start;
+
+    for &index in files_order.iter() {
+        if pinned.iter().any(|t| t.1 == index) {
+            continue;
+        }
+
+        // past the pinned files in the way
+        while let Some(&(other, other_index)) =
+            pinned.iter().find(|t| overlaps(location, index, t.0, t.1))
+        {
+            location = (other + blocks(other_index)).next_multiple_of(context.alignment);
+        }
+
+        placed.push((index, location));
+        location += blocks(index);
+    }
+
+    let end = placed
+        .iter()
+        .map(|t| t.1 + blocks(t.0))
+        .max()
+        .unwrap_or(start);
+    placed.sort_by_key(|t| t.1);
+
+    let new_locations: HashMap<usize, usize> = placed.iter().copied().collect();
+
+    // empty files share the location of the file after them and move with it
+    let moved: HashMap<usize, usize> = old_locations
+        .into_iter()
+        .filter(|t| blocks(t.0) > 0)
+        .map(|(index, old)| (old, new_locations[&index]))
+        .collect();
+
+    for record in dirs_sectors.iter_mut().flat_map(|t| &mut t.0) {
+        if let IsoEntry::File(_) = record.entry() {
+            let old = record.record().location(None) as usize;
+
+            if let Some(new) = moved.get(&old) {
+                record.record_mut().set_location(*new);
+            }
+        }
+    }
+
+    (placed, end, conflict)
+}
+
+// points the CL entries of the placeholders relocated directories leave
+// behind and the PL entries of their ".." records at the extents given by
+// set_locations, and the records of hard links, which `links` maps to the path
+// of their file, at the extent of that file
+fn link_extents(
+    dirs_start: usize,
+    dirs_sectors: &mut [(Vec<IsoDirectoryEntry>, SectorProps)],
+    path_groups: &BTreeMap<usize, Vec<(String, usize)>>,
+    moved: &BTreeMap<PathBuf, PathBuf>,
+    links: &BTreeMap<PathBuf, PathBuf>,
+) {
+    if moved.is_empty() && links.is_empty() {
+        return;
+    }
+
+    // directory paths by location, from the root down
+    let mut paths = BTreeMap::from([(dirs_start, PathBuf::from("/"))]);
+    let mut pending = VecDeque::from([dirs_start]);
+
+    while let Some(location) = pending.pop_front() {
+        let parent = paths[&location].clone();
+
+        for (name, child) in path_groups.get(&location).into_iter().flatten() {
+            paths.insert(*child, parent.join(name));
+            pending.push_back(*child);
+        }
+    }
+
+    let locations: HashMap<&Path, usize> = paths
+        .iter()
+        .map(|(location, path)| (path.as_path(), *location))
+        .collect();
+
+    // extents of the files hard links point to
+    let mut extents = HashMap::new();
+    let mut dir_path = Path::new("/");
+
+    for record in dirs_sectors.iter().flat_map(|t| &t.0) {
+        match record.entry() {
+            IsoEntry::CurrentDirectory => {
+                dir_path = &paths[&(record.record().location(None) as usize)];
+            }
+            IsoEntry::File(name) => {
+                let path = dir_path.join(name);
+
+                if links.values().any(|t| *t == path) {
+                    extents.insert(path, record.record().location(None) as usize);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (sector, _) in dirs_sectors.iter_mut() {
+        for record in sector {
+            let link = match record.entry() {
+                IsoEntry::CurrentDirectory => {
+                    dir_path = &paths[&(record.record().location(None) as usize)];
+                    None
+                }
+                IsoEntry::ParentDirectory => moved
+                    .iter()
+                    .find(|t| t.1 == dir_path)
+                    .and_then(|t| t.0.parent())
+                    .map(|t| rock_ridge::pl_entry(locations[t] as u32)),
+                IsoEntry::File(name) => {
+                    let path = dir_path.join(name);
+
+                    if let Some(location) = links.get(&path).and_then(|t| extents.get(t)) {
+                        record.record_mut().set_location(*location);
+                    }
+
+                    moved.get(&path).map(|t| {
+                        let location = locations[t.as_path()];
+                        record.record_mut().set_location(location);
+                        rock_ridge::cl_entry(location as u32)
+                    })
+                }
+                IsoEntry::Directory(_) => None,
+            };
+
+            if let Some(system_use) = link {
+                record.set_system_use(system_use);
+            }
+        }
+    }
+}
+
+struct Layout {
+    dirs_sectors: Vec<(Vec<IsoDirectoryEntry>, SectorProps)>,
+    // files in the order their data is written, with its location counted
+    // from the data start
+    files_order: Vec<(usize, usize)>,
+    dirs_start: usize,
+    l_table_start: usize,
+    m_table_start: usize,
+    table_blocks: usize,
+    // blocks of the whole volume
+    blocks: usize,
+    // zeroed blocks ending the volume
+    padding_blocks: usize,
+    // zeroed blocks aligning the file data after what precedes it
+    gap_blocks: usize,
+    path_groups: BTreeMap<usize, Vec<(String, usize)>>,
+    timestamp: DateTime<Utc>,
+    // file whose pinned location is taken
+    pin_conflict: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct IsoFileWriter<'r, W>
+where
+    W: AsyncWrite + Unpin,
+{
+    header: IsoHeader,
+    files: Vec<FileEntry<'r>>,
+    writer: W,
+    streamed_blocks: usize,
+    reserved_sectors: usize,
+    epoch: Option<DateTime<Utc>>,
+    naming: Naming,
+    names: NameMap,
+    deep_dirs: Option<DeepDirectories>,
+    padding_sectors: usize,
+    padding_multiple: usize,
+    extent_alignment: usize,
+    overwrite: bool,
+    progress: Progress,
+    cancel: Cancel,
+}
+
+impl<'r, W> IsoFileWriter<'r, W>
+where
+    W: AsyncWrite + Unpin,
+{
+    pub async fn new(writer: W, header: IsoHeader) -> io::Result<Self> {
+        Ok(Self {
+            writer,
+            header,
+            files: Vec::new(),
+            streamed_blocks: 0,
+            reserved_sectors: 0,
+            epoch: None,
+            naming: Naming::default(),
+            names: NameMap::default(),
+            deep_dirs: None,
+            padding_sectors: 0,
+            padding_multiple: 0,
+            extent_alignment: 0,
+            overwrite: false,
+            progress: Progress::default(),
+            cancel: Cancel::default(),
+        })
+    }
+
+    /// Registers a file of `content`. `timestamp` is recorded with its offset
+    /// from GMT, so a `DateTime<FixedOffset>` keeps its local time.
+    ///
+    /// Returns the path stored for it, whose identifiers follow the naming
+    /// options. Fails with [`IsoFileError::EmptyPath`] without a name,
+    /// [`IsoFileError::InvalidName`] for `.`, `..` and names with control
+    /// characters, [`IsoFileError::DepthLimit`] if deep directories are
+    /// rejected and [`IsoFileError::DuplicatePath`] if an appended entry has
+    /// the path, or a file one of its directories. The other ways of adding
+    /// entries check their path the same.
+    ///
+    /// Contents that are all zeros are stored once: files of this kind taking
+    /// as many blocks share one extent, unless pinned to a location.
+    pub fn append_file<T: Into<DateTime<FixedOffset>>>(
+        &mut self,
+        path: &str,
+        content: &'r [u8],
+        timestamp: T,
+    ) -> Result<PathBuf> {
+        let content = FileContent::Bytes(Cow::Borrowed(content));
+        self.push_file(path, content, timestamp.into())
+    }
+
+    /// Like [`append_file`](Self::append_file) but takes ownership of the
+    /// content, so a writer fed only this way is `'static` and can be moved
+    /// into a spawned task.
+    pub fn append_file_owned<C: Into<Vec<u8>>, T: Into<DateTime<FixedOffset>>>(
+        &mut self,
+        path: &str,
+        content: C,
+        timestamp: T,
+    ) -> Result<PathBuf> {
+        let content = FileContent::Bytes(Cow::Owned(content.into()));
+        self.push_file(path, content, timestamp.into())
+    }
+
+    /// Registers a file of `length` bytes whose content is read from `reader`
+    /// while [`close`](Self::close) writes the image.
+    pub fn append_file_from_reader<R, T>(
+        &mut self,
+        path: &str,
+        reader: R,
+        length: usize,
+        timestamp: T,
+    ) -> Result<PathBuf>
+    where
+        R: AsyncRead + Send + Unpin + 'r,
+        T: Into<DateTime<FixedOffset>>,
+    {
+        let content = FileContent::Reader {
+            reader: Box::new(reader),
+            length,
+        };
+
+        self.push_file(path, content, timestamp.into())
+    }
+
+    /// Registers a file of `length` bytes whose content is the chunks of
+    /// `stream`, consumed while [`close`](Self::close) writes the image.
+    pub fn append_file_from_stream<S, T>(
+        &mut self,
+        path: &str,
+        stream: S,
+        length: usize,
+        timestamp: T,
+    ) -> Result<PathBuf>
+    where
+        S: Stream<Item = Bytes> + Send + 'r,
+        T: Into<DateTime<FixedOffset>>,
+    {
+        let content = FileContent::Stream {
+            stream: stream.boxed(),
+            length,
+        };
+
+        self.push_file(path, content, timestamp.into())
+    }
+
+    /// Leaves `sectors` more zeroed 2048-byte sectors after the volume
+    /// descriptors, for data the caller places there later. Must be set before any file is
+    /// streamed.
+    pub fn set_reserved_sectors(&mut self, sectors: usize) {
+        self.reserved_sectors = sectors;
+    }
+
+    /// Ends the image with at least `sectors` zeroed 2048-byte sectors, more
+    /// if needed for the volume to be a multiple of `multiple` sectors, for the
+    /// drives that read past the last extent. mkisofs pads by 150 sectors and
+    /// to a multiple of 16.
+    pub fn set_padding(&mut self, sectors: usize, multiple: usize) {
+        self.padding_sectors = sectors;
+        self.padding_multiple = multiple;
+    }
+
+    /// Starts the extent of every file on a multiple of `alignment` bytes, such
+    /// as 4 KiB or 64 KiB, for faster reads from flash media and loop devices.
+    /// File data is padded with zeroes up to the next boundary. Must be set
+    /// before any file is streamed.
+    pub fn set_extent_alignment(&mut self, alignment: usize) {
+        self.extent_alignment = alignment;
+    }
+
+    /// Makes entries appended afterwards replace the entries in their way
+    /// instead of failing with [`IsoFileError::DuplicatePath`], as when later
+    /// layers of the input win: the entry of the same path, files where
+    /// directories are needed and, for a file, the entries below it. Hard
+    /// links to a replaced file read the new one, removing a linked file
+    /// fails with [`IsoFileError::LinkTarget`].
+    pub fn set_overwrite(&mut self, overwrite: bool) {
+        self.overwrite = overwrite;
+    }
+
+    /// Makes the output depend only on the registered entries: `epoch` dates
+    /// the volume and the directories created implicitly, and entries are kept
+    /// sorted by path rather than in insertion order.
+    pub fn set_reproducible(&mut self, epoch: DateTime<Utc>) {
+        self.epoch = Some(epoch);
+        self.files.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    /// Restricts identifiers of the entries appended afterwards to `level`:
+    /// characters other than d-characters become `_` and names are truncated
+    /// to the lengths it allows. [`close`](Self::close) then also rejects
+    /// directories deeper than 8 levels, unless
+    /// [`set_deep_directories`](Self::set_deep_directories) chooses otherwise.
+    pub fn set_interchange_level(&mut self, level: InterchangeLevel) {
+        self.naming.level = Some(level);
+    }
+
+    /// Chooses what [`close`](Self::close) does with directories deeper than
+    /// the 8 levels ECMA-119 allows. Without a choice they are rejected once an
+    /// interchange level is set and kept otherwise.
+    pub fn set_deep_directories(&mut self, deep_dirs: DeepDirectories) {
+        self.deep_dirs = Some(deep_dirs);
+    }
+
+    /// Spells accented Latin letters of the names appended afterwards in ASCII
+    /// and replaces spaces and other characters with `_`, so `café.txt` is
+    /// stored as `CAFE.TXT` rather than losing characters. The stored names are
+    /// listed by [`name_mapping`](Self::name_mapping).
+    pub fn set_transliterate(&mut self, transliterate: bool) {
+        self.naming.transliterate = transliterate;
+    }
+
+    /// Keeps lower case letters in the names appended afterwards instead of
+    /// converting them to upper case.
+    pub fn set_allow_lowercase(&mut self, allow: bool) {
+        self.naming.lowercase = allow;
+    }
+
+    /// Keeps every printable ASCII character but `/` and `;` in the names
+    /// appended afterwards, also with an interchange level.
+    pub fn set_allow_full_ascii(&mut self, allow: bool) {
+        self.naming.full_ascii = allow;
+    }
+
+    /// Stores the names appended afterwards as given, only cut to 222 bytes.
+    /// Neither ECMA-119 character sets nor the interchange level lengths are
+    /// enforced, for readers that accept any identifier.
+    pub fn set_untranslated_names(&mut self, untranslated: bool) {
+        self.naming.untranslated = untranslated;
+    }
+
+    /// Writes the identifiers of the files appended afterwards without the `;1`
+    /// version suffix, which some firmware loaders fail to parse. The reader
+    /// accepts files recorded either way.
+    pub fn set_omit_version(&mut self, omit: bool) {
+        self.naming.omit_version = omit;
+    }
+
+    /// Path stored for every file and directory appended so far, by the path
+    /// it was given with. Names shortened to the same identifier as another
+    /// entry of their directory get a `~N` suffix, as in `LONGNA~1.TXT`.
+    pub fn name_mapping(&self) -> &BTreeMap<String, PathBuf> {
+        self.names.paths()
+    }
+
+    /// Size in bytes of the image [`close`](Self::close) would write with the
+    /// entries registered so far.
+    pub fn estimated_size(&self) -> u64 {
+        let layout = self.layout(self.streamed_blocks > 0);
+
+        layout.blocks as u64 * self.block_size() as u64
+    }
+
+    /// Gives back the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Registers a local file, its size and modification time are read now and
+    /// its content while [`close`](Self::close) writes the image.
+    pub async fn append_file_from_path<P: AsRef<Path>>(
+        &mut self,
+        path: &str,
+        source: P,
+    ) -> Result<PathBuf> {
+        let source = source.as_ref().to_path_buf();
+        let metadata = fs::metadata(&source).await?;
+
+        let content = FileContent::Path {
+            source,
+            length: metadata.len() as usize,
+        };
+
+        let modified = DateTime::<Utc>::from(metadata.modified()?);
+        self.push_file(path, content, modified.into())
+    }
+
+    /// Sets the existence bit on the record of an appended file or directory
+    /// created with [`create_dir`](Self::create_dir), so listings skip it.
+    pub fn set_hidden(&mut self, path: &str, hidden: bool) -> Result<()> {
+        let entry = self.appended_mut(path)?;
+        entry.hidden = hidden;
+
+        Ok(())
+    }
+
+    // last entry appended at `path`, given as passed to the writer
+    fn appended_mut(&mut self, path: &str) -> Result<&mut FileEntry<'r>> {
+        let stored = self.names.get(path).ok_or(IsoFileError::FileNotFound)?;
+
+        self.files
+            .iter_mut()
+            .rev()
+            .find(|t| &t.path == stored)
+            .ok_or(IsoFileError::FileNotFound)
+    }
+
+    /// Records an appended file as stored on volume `volume_seq_number` of the
+    /// set instead of the one of the header.
+    pub fn set_volume_sequence_number(&mut self, path: &str, volume_seq_number: u16) -> Result<()> {
+        let entry = self.appended_mut(path)?;
+        entry.volume_seq_number = Some(volume_seq_number);
+
+        Ok(())
+    }
+
+    /// Places the data of an appended file before that of the files of lower
+    /// `weight`, like the weights of mkisofs `-sort`, for boot loaders and
+    /// kernels that must sit at low blocks. Files weigh 0 by default and files
+    /// of the same weight follow the order of their directories.
+    pub fn set_sort_weight(&mut self, path: &str, weight: i32) -> Result<()> {
+        let entry = self.appended_mut(path)?;
+        entry.weight = weight;
+
+        Ok(())
+    }
+
+    /// Progress of [`close`](Self::close) and
+    /// [`close_seekable`](Self::close_seekable), updated as the volume
+    /// structures and the data of every file are written.
+    pub fn subscribe_progress(&mut self) -> watch::Receiver<WriteProgress> {
+        self.progress.subscribe()
+    }
+
+    /// Makes [`close`](Self::close), [`close_seekable`](Self::close_seekable)
+    /// and [`stream_file`](Self::stream_file) stop with
+    /// [`IsoFileError::Cancelled`] at the next sector once `flag` is set. The
+    /// writer, given back in the [`CloseError`], keeps failing this way, also
+    /// if the flag is cleared. Its sink, from [`into_inner`](Self::into_inner),
+    /// holds the sectors written so far as reported by
+    /// [`subscribe_progress`](Self::subscribe_progress), an image cut short
+    /// to be truncated or discarded.
+    pub fn set_cancel_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancel.set_flag(flag);
+    }
+
+    /// Pins the data of an appended file at logical block `location`, for boot
+    /// loaders that expect a payload there. The other files are laid out
+    /// around it, zeroes filling the gaps, and [`close`](Self::close) fails with
+    /// [`IsoFileError::LocationConflict`] if the block is taken by the volume
+    /// structures, streamed data or another pinned file.
+    pub fn set_location(&mut self, path: &str, location: u32) -> Result<()> {
+        let streamed_start = self.streamed_start();
+        let entry = self.appended_mut(path)?;
+
+        let conflict = match entry.content {
+            FileContent::Directory => return Err(IsoFileError::EntryDirectory),
+            FileContent::Written {
+                location: written, ..
+            } => streamed_start + written != location as usize,
+            _ => false,
+        };
+
+        if conflict {
+            return Err(IsoFileError::LocationConflict {
+                path: entry.path.clone(),
+                location,
+            });
+        }
+
+        entry.location = Some(location);
+
+        Ok(())
+    }
+
+    /// Adds `link` as a hard link to the file appended at `original`: both
+    /// records share its extent, so the data is stored once, and its timestamp.
+    /// Returns the path stored for the link.
+    pub fn append_hard_link(&mut self, original: &str, link: &str) -> Result<PathBuf> {
+        let entry = self.appended_mut(original)?;
+
+        let (target, length) = match &entry.content {
+            FileContent::Directory => return Err(IsoFileError::EntryDirectory),
+            FileContent::Link { target, length } => (target.clone(), *length),
+            content => (entry.path.clone(), content.len()),
+        };
+
+        let timestamp = entry.timestamp;
+        let volume_seq_number = entry.volume_seq_number;
+
+        let stored = self.push_file(link, FileContent::Link { target, length }, timestamp)?;
+        self.appended_mut(link)?.volume_seq_number = volume_seq_number;
+
+        Ok(stored)
+    }
+
+    /// Adds a directory, which is otherwise only created for the files in it,
+    /// and returns the path stored for it.
+    pub fn create_dir<T: Into<DateTime<FixedOffset>>>(
+        &mut self,
+        path: &str,
+        timestamp: T,
+    ) -> Result<PathBuf> {
+        self.push_file(path, FileContent::Directory, timestamp.into())
+    }
+
+    /// Registers every file below `local_dir` selected by `filter`, keeping
+    /// relative paths and modification times, under `iso_path` of the image.
+    /// Directories the filter does not exclude are created with their
+    /// modification time, even when empty. Symbolic links are skipped, so a
+    /// link to a parent directory cannot loop.
+    pub async fn append_dir_all<P: AsRef<Path>>(
+        &mut self,
+        iso_path: &str,
+        local_dir: P,
+        filter: &PathFilter,
+    ) -> Result<()> {
+        let mut pending = vec![(local_dir.as_ref().to_path_buf(), String::new())];
+
+        while let Some((dir, relative)) = pending.pop() {
+            let mut children = Vec::new();
+            let mut read_dir = fs::read_dir(&dir).await?;
+
+            while let Some(entry) = read_dir.next_entry().await? {
+                children.push(entry.path());
+            }
+
+            children.sort_unstable();
+            let mut subdirs = Vec::new();
+
+            for child in children {
+                let name = child.file_name().unwrap_or_default().to_string_lossy();
+                let child_relative = format!("{relative}/{name}");
+                let matched = child_relative.trim_start_matches('/');
+                let target = format!("{}{}", iso_path.trim_end_matches('/'), child_relative);
+                let metadata = fs::symlink_metadata(&child).await?;
+
+                if metadata.is_symlink() {
+                    continue;
+                } else if metadata.is_dir() {
+                    if !filter.is_excluded(matched) {
+                        let modified = DateTime::<Utc>::from(metadata.modified()?);
+                        self.create_dir(&target, modified)?;
+                        subdirs.push((child, child_relative));
+                    }
+                } else if filter.is_included(matched) {
+                    self.append_file_from_path(&target, &child).await?;
+                }
+            }
+
+            // reversed so the stack pops them in order
+            pending.extend(subdirs.into_iter().rev());
+        }
+
+        Ok(())
+    }
+
+    // rejects paths that cannot be stored as given
+    fn check_path(&self, path: &str, is_dir: bool) -> Result<()> {
+        let names: Vec<&str> = path.split('/').filter(|t| !t.is_empty()).collect();
+
+        if names.is_empty() {
+            return Err(IsoFileError::EmptyPath);
+        }
+
+        let invalid = names
+            .iter()
+            .find(|t| matches!(**t, "." | "..") || t.chars().any(char::is_control));
+
+        if let Some(name) = invalid {
+            return Err(IsoFileError::InvalidName(name.to_string()));
+        }
+
+        // the root is level 1, the parent of a file holds its level
+        let levels = if is_dir { names.len() + 1 } else { names.len() };
+
+        if self.deep_directories() == DeepDirectories::Reject && levels > naming::MAX_DEPTH {
+            return Err(IsoFileError::DepthLimit(naming::MAX_DEPTH));
+        }
+
+        match self.collisions(path, is_dir) {
+            Some((stored, _)) if !self.overwrite => Err(IsoFileError::DuplicatePath(stored)),
+            _ => Ok(()),
+        }
+    }
+
+    // drops the entries overwritten by `content` at `path`. Hard links follow
+    // a file replaced by a file of the same path, other targets cannot go
+    fn replace_entries(
+        &mut self,
+        path: &str,
+        content: &FileContent,
+        mut indexes: Vec<usize>,
+    ) -> Result<()> {
+        let replaced = match content {
+            FileContent::Directory | FileContent::Link { .. } => None,
+            content => self.names.get(path).cloned().map(|t| (t, content.len())),
+        };
+
+        let removed = |target: &PathBuf| indexes.iter().any(|&t| self.files[t].path == *target);
+
+        if let FileContent::Link { target, .. } = content
+            && removed(target)
+        {
+            return Err(IsoFileError::LinkTarget(target.clone()));
+        }
+
+        for (index, entry) in self.files.iter().enumerate() {
+            if let FileContent::Link { target, .. } = &entry.content {
+                let kept = replaced.as_ref().is_some_and(|t| t.0 == *target);
+
+                if !indexes.contains(&index) && removed(target) && !kept {
+                    return Err(IsoFileError::LinkTarget(target.clone()));
+                }
+            }
+        }
+
+        indexes.sort_unstable();
+
+        for index in indexes.into_iter().rev() {
+            self.files.remove(index);
+        }
+
+        if let Some((path, length)) = replaced {
+            for entry in &mut self.files {
+                if let FileContent::Link { target, length: t } = &mut entry.content
+                    && *target == path
+                {
+                    *t = length;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // appended entries in the way of `path`: the entry of the same path, files
+    // in place of its directories and, for a file, the entries below it. Comes
+    // with the first stored path they collide at
+    fn collisions(&self, path: &str, is_dir: bool) -> Option<(PathBuf, Vec<usize>)> {
+        let names: Vec<&str> = path.split('/').filter(|t| !t.is_empty()).collect();
+        let mut original = String::new();
+        let mut found: Option<(PathBuf, Vec<usize>)> = None;
+
+        for (index, name) in names.iter().enumerate() {
+            original.push('/');
+            original.push_str(name);
+
+            let Some(stored) = self.names.get(&original) else {
+                break;
+            };
+
+            let last = index + 1 == names.len();
+
+            let indexes = self.files.iter().enumerate().filter(|(_, t)| {
+                let taken =
+                    t.path == *stored && (last || !matches!(t.content, FileContent::Directory));
+                let below = last && !is_dir && t.path.starts_with(stored) && t.path != *stored;
+
+                taken || below
+            });
+
+            for (index, _) in indexes {
+                found
+                    .get_or_insert_with(|| (stored.clone(), Vec::new()))
+                    .1
+                    .push(index);
+            }
+        }
+
+        found
+    }
+
+    fn push_file(
+        &mut self,
+        path: &str,
+        content: FileContent<'r>,
+        timestamp: DateTime<FixedOffset>,
+    ) -> Result<PathBuf> {
+        let is_dir = matches!(content, FileContent::Directory);
+        self.check_path(path, is_dir)?;
+
+        if let Some((_, indexes)) = self.collisions(path, is_dir) {
+            self.replace_entries(path, &content, indexes)?;
+        }
+
+        let new_path = self.names.resolve(path, is_dir, self.naming);
+        let version = (!is_dir && !self.naming.omit_version).then_some(1);
+
+        // sorted position when reproducible, after entries of the same path
+        let index = match self.epoch {
+            Some(_) => self.files.partition_point(|t| t.path <= new_path),
+            None => self.files.len(),
+        };
+
+        self.files.insert(
+            index,
+            FileEntry {
+                path: new_path.clone(),
+                content,
+                timestamp,
+                version,
+                hidden: false,
+                volume_seq_number: None,
+                weight: 0,
+                location: None,
+            },
+        );
+
+        Ok(new_path)
+    }
+
+    /// Writes the image and gives back the underlying writer, or the writer
+    /// itself with the error.
+    pub async fn close(mut self) -> std::result::Result<W, CloseError<Self>> {
+        match self.write_image().await {
+            Ok(()) => Ok(self.writer),
+            Err(error) => Err(CloseError::new(self, error)),
+        }
+    }
+
+    async fn write_image(&mut self) -> Result<()> {
+        if self.streamed_blocks > 0 {
+            return Err(IsoFileError::SeekRequired);
+        }
+
+        self.cancel.check()?;
+        self.check_block_size()?;
+        self.check_depth()?;
+
+        let layout = self.layout(false);
+        self.check_pins(&layout)?;
+        self.progress.start(0, layout.blocks * self.block_size());
+
+        self.write_head(&layout).await?;
+        self.write_path_tables(&layout).await?;
+        self.write_dirs(&layout.dirs_sectors).await?;
+        self.write_zeroed(layout.gap_blocks).await?;
+        self.write_files(&layout.files_order).await?;
+        self.write_zeroed(layout.padding_blocks).await?;
+        self.writer.flush().await?;
+
+        Ok(())
+    }
+
+    // deep directories are rejected by default once an interchange level is
+    // chosen
+    fn deep_directories(&self) -> DeepDirectories {
+        match (self.deep_dirs, self.naming.level) {
+            (Some(t), _) => t,
+            (None, Some(_)) => DeepDirectories::Reject,
+            (None, None) => DeepDirectories::Keep,
+        }
+    }
+
+    fn check_depth(&self) -> Result<()> {
+        if self.deep_directories() != DeepDirectories::Reject {
+            return Ok(());
+        }
+
+        // the root directory is level 1, the parent of a file holds its level
+        let too_deep = self.files.iter().any(|t| {
+            let levels = match t.content {
+                FileContent::Directory => t.path.components().count(),
+                _ => t.path.components().count() - 1,
+            };
+
+            levels > naming::MAX_DEPTH
+        });
+
+        if too_deep {
+            Err(IsoFileError::DepthLimit(naming::MAX_DEPTH))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_pins(&self, layout: &Layout) -> Result<()> {
+        match layout.pin_conflict {
+            Some(index) => Err(IsoFileError::LocationConflict {
+                path: self.files[index].path.clone(),
+                location: self.files[index].location.unwrap_or_default(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    // size of the blocks files and directories are laid out in
+    fn block_size(&self) -> usize {
+        match self.header.logical_block_size {
+            512 => 512,
+            1024 => 1024,
+            _ => core::LOGICAL_BLOCK_SIZE,
+        }
+    }
+
+    // blocks of the system area, the volume descriptors and the reserved
+    // sectors, which the path tables follow unless written last
+    fn head_blocks(&self) -> usize {
+        // primary volume descriptor and set terminator, followed by an empty
+        // sector
+        let descriptors = 2;
+        let sectors = 16 + descriptors + 1 + self.reserved_sectors;
+
+        sectors * core::LOGICAL_BLOCK_SIZE / self.block_size()
+    }
+
+    // blocks file extents are aligned to
+    fn alignment_blocks(&self) -> usize {
+        self.extent_alignment.div_ceil(self.block_size()).max(1)
+    }
+
+    // blocks taken by file data of `length` bytes
+    fn file_blocks(&self, length: usize) -> usize {
+        length
+            .div_ceil(self.block_size())
+            .next_multiple_of(self.alignment_blocks())
+    }
+
+    // first block of the file data written ahead of the directories
+    fn streamed_start(&self) -> usize {
+        self.head_blocks().next_multiple_of(self.alignment_blocks())
+    }
+
+    fn check_block_size(&self) -> Result<()> {
+        match self.header.logical_block_size {
+            512 | 1024 | 2048 => Ok(()),
+            block_size => Err(IsoFileError::InvalidLogicalBlockSize(block_size)),
+        }
+    }
+
+    // volume of the set this image is, from the header
+    fn volume_seq_number(&self) -> u16 {
+        self.header.volume_sequence_number.max(1)
+    }
+
+    // locations of path tables, directories and file data, in this order
+    // unless `dirs_after_files` is set, which puts file data first and the
+    // path tables last
+    fn layout(&self, dirs_after_files: bool) -> Layout {
+        let mut dirs_sectors: Vec<(Vec<IsoDirectoryEntry>, SectorProps)> = Vec::new();
+        let mut files_order = Vec::new();
+        let mut files_blocks = self.streamed_blocks;
+
+        let mut group_no = 0;
+        let timestamp = self.epoch.unwrap_or_else(Utc::now);
+
+        let relocate = self.deep_directories() == DeepDirectories::Relocate;
+        let mut moved = BTreeMap::new();
+
+        let paths: Vec<PathBuf> = self
+            .files
+            .iter()
+            .map(|t| {
+                if relocate {
+                    let is_dir = matches!(t.content, FileContent::Directory);
+                    naming::relocate(&t.path, is_dir, self.naming.level, &mut moved)
+                } else {
+                    t.path.clone()
+                }
+            })
+            .collect();
+
+        // all-zero files share the extent of the first one as long
+        let mut zeroed = HashMap::new();
+        let shared: BTreeMap<usize, usize> = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(index, t)| match &t.content {
+                FileContent::Bytes(content)
+                    if !content.is_empty()
+                        && t.location.is_none()
+                        && content.iter().all(|t| *t == 0) =>
+                {
+                    let first = *zeroed
+                        .entry(self.file_blocks(content.len()))
+                        .or_insert(index);
+                    (first != index).then_some((index, first))
+                }
+                _ => None,
+            })
+            .collect();
+
+        // hard links and shared files, with the relocated path of their file
+        let links: BTreeMap<PathBuf, PathBuf> = self
+            .files
+            .iter()
+            .zip(&paths)
+            .enumerate()
+            .filter_map(|(index, (t, path))| match &t.content {
+                FileContent::Link { target, .. } => {
+                    let index = self.files.iter().rposition(|t| t.path == *target)?;
+                    Some((path.clone(), paths[index].clone()))
+                }
+                _ => {
+                    let first = shared.get(&index)?;
+                    Some((path.clone(), paths[*first].clone()))
+                }
+            })
+            .collect();
+
+        let placeholders: Vec<FileEntry<'_>> = moved
+            .keys()
+            .map(|path| FileEntry {
+                path: path.clone(),
+                content: FileContent::Relocated,
+                timestamp: timestamp.fixed_offset(),
+                version: None,
+                hidden: false,
+                volume_seq_number: None,
+                weight: 0,
+                location: None,
+            })
+            .collect();
+
+        // placeholders have no data, so their index is never used
+        let entries: Vec<(PathBuf, usize, &FileEntry<'_>)> = paths
+            .into_iter()
+            .zip(&self.files)
+            .enumerate()
+            .map(|(index, (path, t))| (path, index, t))
+            .chain(placeholders.iter().map(|t| (t.path.clone(), usize::MAX, t)))
+            .collect();
+
+        let context = DirContext {
+            timestamp,
+            volume: self.volume_seq_number(),
+            block_size: self.block_size(),
+            alignment: self.alignment_blocks(),
+            moved: &moved,
+            shared: &shared,
+        };
+
+        build_sectors(
+            &mut dirs_sectors,
+            &mut files_order,
+            &mut files_blocks,
             &mut group_no,
-            &self.files,
+            &entries,
             0,
             None,
+            &context,
         );
 
-        let path_groups = set_locations(23, &mut dirs_sectors);
+        let head_blocks = self.head_blocks();
+        let dirs_blocks = dirs_sectors.len() * core::LOGICAL_BLOCK_SIZE / self.block_size();
+        // at least two sectors for each table
+        let table_sectors = path_table_size(&dirs_sectors)
+            .div_ceil(core::LOGICAL_BLOCK_SIZE)
+            .max(2);
+        let table_blocks = table_sectors * core::LOGICAL_BLOCK_SIZE / self.block_size();
 
-        // create path table
-        let l_path_table = IsoPathTable::new_l_table(&path_groups);
-        let l_path_table_raw = l_path_table.as_vec();
-        let l_path_table_len = l_path_table_raw.len();
-
-        /*
-        for (i_sector, (entries, _)) in dirs_sectors.iter().enumerate() {
-            println!("> [{}]", i_sector + 23);
-            for (i_entry, entry) in entries.iter().enumerate() {
-                println!(
-                    ">> [{}] loc: {} entry: {:?}",
-                    i_entry,
-                    entry.record.location(None),
-                    entry.entry
-                );
-            }
+        let files_start = if dirs_after_files {
+            self.streamed_start()
+        } else {
+            (head_blocks + 2 * table_blocks + dirs_blocks).next_multiple_of(self.alignment_blocks())
+        };
+
+        let (files_order, files_blocks, pin_conflict) = place_files(
+            &self.files,
+            files_order,
+            &mut dirs_sectors,
+            self.streamed_blocks,
+            files_start,
+            &context,
+        );
+
+        let (l_table_start, dirs_start) = if dirs_after_files {
+            let dirs_start = files_start + files_blocks;
+            (dirs_start + dirs_blocks, dirs_start)
+        } else {
+            (head_blocks, head_blocks + 2 * table_blocks)
+        };
+
+        let path_groups = set_locations(
+            dirs_start,
+            files_start,
+            &mut dirs_sectors,
+            self.block_size(),
+        );
+
+        link_extents(dirs_start, &mut dirs_sectors, &path_groups, &moved, &links);
+
+        let sector_blocks = core::LOGICAL_BLOCK_SIZE / self.block_size();
+        let (used_blocks, gap_blocks) = if dirs_after_files {
+            (l_table_start + 2 * table_blocks, files_start - head_blocks)
+        } else {
+            (
+                files_start + files_blocks,
+                files_start - dirs_start - dirs_blocks,
+            )
+        };
+        let blocks = (used_blocks + self.padding_sectors * sector_blocks)
+            .next_multiple_of(self.padding_multiple.max(1) * sector_blocks);
+
+        Layout {
+            dirs_sectors,
+            files_order,
+            dirs_start,
+            l_table_start,
+            m_table_start: l_table_start + table_blocks,
+            table_blocks,
+            blocks,
+            padding_blocks: blocks - used_blocks,
+            gap_blocks,
+            path_groups,
+            timestamp,
+            pin_conflict,
         }
-        */
+    }
 
+    // system area, descriptors and reserved sectors
+    async fn write_head(&mut self, layout: &Layout) -> Result<()> {
         // reserved for boot sector
-        self.writer.write_all(&[0u8; 0x8000]).await?;
+        write_zeroes(&mut self.writer, 0x8000).await?;
 
         // save header
-        let header = IsoHeader {
-            volume_space_size: (22 + dirs_sectors.len() + files_sectors.len()) as u32,
-            volume_set_size: 1,
-            volume_sequence_number: 1,
-            path_table_size: l_path_table_len as u32,
-            loc_of_type_l_path_table: 19,
-            loc_of_type_m_path_table: 21,
+        let mut header = IsoHeader {
+            volume_space_size: layout.blocks as u32,
+            volume_set_size: self.header.volume_set_size.max(1),
+            volume_sequence_number: self.volume_seq_number(),
+            path_table_size: path_table_size(&layout.dirs_sectors) as u32,
+            loc_of_type_l_path_table: layout.l_table_start as u32,
+            loc_of_type_m_path_table: layout.m_table_start as u32,
             ..self.header.clone()
         };
 
+        if let Some(epoch) = self.epoch {
+            header.volume_creation_date = Some(epoch.into());
+            header.volume_modification_date = Some(epoch.into());
+            header.volume_effective_date = Some(epoch.into());
+        }
+
         // root directory entry
-        let root_sectors = dirs_sectors.iter().filter(|t| t.1.group_no == 0).count();
+        let root_sectors = layout
+            .dirs_sectors
+            .iter()
+            .filter(|t| t.1.group_no == 0)
+            .count();
 
         let root_directory = RootDirectoryEntry {
-            location_of_extent: 23,
+            location_of_extent: layout.dirs_start,
             data_length: root_sectors * core::LOGICAL_BLOCK_SIZE,
-            datetime: Utc::now(),
+            datetime: layout.timestamp,
+            volume_seq_number: self.volume_seq_number(),
         };
 
         let header_raw = header.into_raw(root_directory)?;
@@ -521,56 +2659,219 @@ where
         let header_term = IsoHeaderRaw::terminator();
         header_term.write(&mut self.writer).await?;
 
-        self.writer.write_all(&[0u8; 0x800]).await?;
+        let reserved = (1 + self.reserved_sectors) * core::LOGICAL_BLOCK_SIZE;
+        write_zeroes(&mut self.writer, reserved).await?;
+        self.progress
+            .advance(self.head_blocks() * self.block_size());
 
-        // save path table
-        let m_path_table = l_path_table.convert_to_m_table();
-        let m_path_table_raw = m_path_table.as_vec();
+        Ok(())
+    }
 
-        {
-            let mut l_path_table_buffer = vec![0u8; core::LOGICAL_BLOCK_SIZE * 2];
+    // L and M path tables, each padded to its blocks
+    async fn write_path_tables(&mut self, layout: &Layout) -> Result<()> {
+        let l_path_table = IsoPathTable::from_directories(layout.dirs_start, &layout.path_groups);
+        let l_path_table_raw = l_path_table.as_vec();
+        let m_path_table_raw = l_path_table.convert_to_m_table().as_vec();
 
-            assert!(
-                l_path_table_len <= l_path_table_buffer.len(),
-                "l path table is too large"
-            );
+        let table_size = layout.table_blocks * self.block_size();
 
-            l_path_table_buffer[..l_path_table_raw.len()].copy_from_slice(&l_path_table_raw);
-            self.writer.write_all(&l_path_table_buffer).await?;
+        for raw in [l_path_table_raw, m_path_table_raw] {
+            self.writer.write_all(&raw).await?;
+            write_zeroes(&mut self.writer, table_size - raw.len()).await?;
+            self.progress.advance(table_size);
         }
 
-        {
-            let mut m_path_table_buffer = vec![0u8; core::LOGICAL_BLOCK_SIZE * 2];
+        Ok(())
+    }
 
-            assert!(
-                l_path_table_len <= m_path_table_buffer.len(),
-                "m path table is too large"
-            );
+    async fn write_zeroed(&mut self, blocks: usize) -> Result<()> {
+        let length = blocks * self.block_size();
+        write_zeroes(&mut self.writer, length).await?;
+        self.progress.advance(length);
 
-            m_path_table_buffer[..m_path_table_raw.len()].copy_from_slice(&m_path_table_raw);
-            self.writer.write_all(&m_path_table_buffer).await?;
-        }
+        Ok(())
+    }
 
+    async fn write_dirs(
+        &mut self,
+        dirs_sectors: &[(Vec<IsoDirectoryEntry>, SectorProps)],
+    ) -> Result<()> {
         // save dirs sectors
         for (sector, _) in dirs_sectors {
+            self.cancel.check()?;
             let mut size = core::LOGICAL_BLOCK_SIZE;
 
             for entry in sector {
                 size -= entry.write(&mut self.writer).await?;
             }
 
-            let zeroed = vec![0u8; size];
-            self.writer.write_all(&zeroed).await?;
+            write_zeroes(&mut self.writer, size).await?;
+            self.progress.advance(core::LOGICAL_BLOCK_SIZE);
+        }
+
+        Ok(())
+    }
+
+    async fn write_files(&mut self, files_order: &[(usize, usize)]) -> Result<()> {
+        // save files sectors, sources are read through a single sector buffer
+        let mut sector = vec![0u8; self.block_size()];
+        // blocks from the data start written so far
+        let mut position = self.streamed_blocks;
+
+        for &(index, location) in files_order {
+            // gap before a pinned file
+            self.write_zeroed(location - position).await?;
+            position = location + self.file_blocks(self.files[index].content.len());
+
+            let file = &mut self.files[index];
+            let length = file.content.len();
+            let (progress, cancel) = (&self.progress, &self.cancel);
+            cancel.check()?;
+            progress.set_path(Some(&file.path));
+
+            let actual = match &mut file.content {
+                FileContent::Bytes(content) => {
+                    self.writer.write_all(content).await?;
+                    progress.advance(length);
+                    length
+                }
+                FileContent::Reader { reader, .. } => {
+                    let writer = &mut self.writer;
+                    copy_sectors(reader, writer, length, &mut sector, progress, cancel).await?
+                }
+                FileContent::Path { source, .. } => {
+                    let mut source = File::open(&source).await?;
+                    let writer = &mut self.writer;
+                    copy_sectors(&mut source, writer, length, &mut sector, progress, cancel).await?
+                }
+                FileContent::Stream { stream, .. } => {
+                    let mut actual = 0;
+
+                    while actual < length {
+                        cancel.check()?;
+
+                        let Some(chunk) = stream.next().await else {
+                            break;
+                        };
+
+                        let chunk = &chunk[..chunk.len().min(length - actual)];
+                        self.writer.write_all(chunk).await?;
+                        progress.advance(chunk.len());
+                        actual += chunk.len();
+                    }
+
+                    actual
+                }
+                FileContent::Written { .. }
+                | FileContent::Directory
+                | FileContent::Relocated
+                | FileContent::Link { .. } => length,
+            };
+
+            if actual < length {
+                return Err(IsoFileError::ShortSource {
+                    path: file.path.clone(),
+                    length,
+                    actual,
+                });
+            }
+
+            // up to the next aligned extent
+            let padding = self.file_blocks(length) * sector.len() - length;
+            write_zeroes(&mut self.writer, padding).await?;
+            self.progress.advance(padding);
         }
 
-        // save files sectors
-        for sector in files_sectors {
-            let mut buffer = vec![0u8; core::LOGICAL_BLOCK_SIZE];
-            let len = sector.len().min(core::LOGICAL_BLOCK_SIZE);
-            buffer[..len].copy_from_slice(&sector[..len]);
-            self.writer.write_all(&buffer).await?;
+        self.progress.set_path(None);
+
+        Ok(())
+    }
+}
+
+impl<'r, W> IsoFileWriter<'r, W>
+where
+    W: AsyncWrite + AsyncSeek + Unpin,
+{
+    /// Copies `reader` to its end into the image right away, so its size need
+    /// not be known beforehand. Returns the number of bytes copied.
+    ///
+    /// Directories, descriptors and path tables are written afterwards by
+    /// [`close_seekable`](Self::close_seekable).
+    pub async fn stream_file<R: AsyncRead + Unpin, T: Into<DateTime<FixedOffset>>>(
+        &mut self,
+        path: &str,
+        mut reader: R,
+        timestamp: T,
+    ) -> Result<usize> {
+        self.cancel.check()?;
+        self.check_block_size()?;
+        self.check_path(path, false)?;
+
+        let block_size = self.block_size();
+        let offset = (self.streamed_start() + self.streamed_blocks) * block_size;
+        self.writer.seek(SeekFrom::Start(offset as u64)).await?;
+
+        let mut sector = vec![0u8; block_size];
+        let length = copy_sectors(
+            &mut reader,
+            &mut self.writer,
+            usize::MAX,
+            &mut sector,
+            &Progress::default(),
+            &self.cancel,
+        )
+        .await?;
+
+        let padding = self.file_blocks(length) * block_size - length;
+        write_zeroes(&mut self.writer, padding).await?;
+
+        let content = FileContent::Written {
+            location: self.streamed_blocks,
+            length,
+        };
+
+        self.streamed_blocks += self.file_blocks(length);
+        self.push_file(path, content, timestamp.into())?;
+
+        Ok(length)
+    }
+
+    /// Finishes the image like [`close`](Self::close), also when files were
+    /// added with [`stream_file`](Self::stream_file). Remaining file data and
+    /// directories follow the streamed data, then the descriptors and path
+    /// tables are filled in at the start.
+    pub async fn close_seekable(mut self) -> std::result::Result<W, CloseError<Self>> {
+        match self.write_image_seekable().await {
+            Ok(()) => Ok(self.writer),
+            Err(error) => Err(CloseError::new(self, error)),
         }
+    }
+
+    async fn write_image_seekable(&mut self) -> Result<()> {
+        self.cancel.check()?;
+        self.check_block_size()?;
+        self.check_depth()?;
+
+        let layout = self.layout(true);
+        self.check_pins(&layout)?;
+        self.progress.start(
+            self.streamed_blocks * self.block_size(),
+            layout.blocks * self.block_size(),
+        );
+
+        let offset = (self.streamed_start() + self.streamed_blocks) * self.block_size();
+        self.writer.seek(SeekFrom::Start(offset as u64)).await?;
+
+        self.write_files(&layout.files_order).await?;
+        self.write_dirs(&layout.dirs_sectors).await?;
+        self.write_path_tables(&layout).await?;
+        self.write_zeroed(layout.padding_blocks).await?;
+
+        self.writer.seek(SeekFrom::Start(0)).await?;
+        self.write_head(&layout).await?;
+        self.write_zeroed(layout.gap_blocks).await?;
 
+        self.writer.seek(SeekFrom::End(0)).await?;
         self.writer.flush().await?;
 
         Ok(())
@@ -0,0 +1,20 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::{IsoFileReader, Result};
+
+impl IsoFileReader<Cursor<Mmap>> {
+    /// Maps a local image into memory instead of reading it through syscalls.
+    ///
+    /// The file must not be modified or truncated while it is mapped.
+    pub async fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+
+        // SAFETY: the caller keeps the file unchanged while the map is alive
+        let map = unsafe { Mmap::map(&file)? };
+
+        Self::read(Cursor::new(map)).await
+    }
+}
@@ -0,0 +1,364 @@
+//! Identifier rules of the ISO 9660 interchange levels for the writer.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Deepest directory level, the root being level 1, ECMA-119 allows.
+pub(crate) const MAX_DEPTH: usize = 8;
+
+/// Directory of the root the directories too deep to keep are moved to.
+pub(crate) const RR_MOVED: &str = "RR_MOVED";
+
+/// ISO 9660 interchange level the writer restricts identifiers to, see
+/// [`IsoFileWriter::set_interchange_level`](crate::IsoFileWriter::set_interchange_level).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterchangeLevel {
+    /// 8.3 file names and directory names of up to 8 characters.
+    Level1,
+    /// File names of up to 30 characters and directory names of up to 31.
+    Level2,
+    /// Same names as level 2.
+    Level3,
+}
+
+impl InterchangeLevel {
+    /// `name` as a file identifier of this level, without version.
+    pub(crate) fn file_id(&self, name: &str, naming: Naming) -> String {
+        let (stem, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+        let mut stem = d_characters(stem, naming);
+        let mut extension = d_characters(extension, naming);
+
+        match self {
+            Self::Level1 => {
+                stem.truncate(8);
+                extension.truncate(3);
+            }
+            Self::Level2 | Self::Level3 => {
+                extension.truncate(29);
+                stem.truncate(30 - extension.len());
+            }
+        }
+
+        if extension.is_empty() {
+            stem
+        } else {
+            format!("{stem}.{extension}")
+        }
+    }
+
+    /// `name` as a directory identifier of this level.
+    pub(crate) fn dir_id(&self, name: &str, naming: Naming) -> String {
+        let mut id = d_characters(name, naming);
+
+        match self {
+            Self::Level1 => id.truncate(8),
+            Self::Level2 | Self::Level3 => id.truncate(31),
+        }
+
+        id
+    }
+}
+
+/// What the writer does with directories deeper than 8 levels, see
+/// [`IsoFileWriter::set_deep_directories`](crate::IsoFileWriter::set_deep_directories).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeepDirectories {
+    /// Writes them where they are, as most readers accept.
+    Keep,
+    /// Fails with [`IsoFileError::DepthLimit`](crate::IsoFileError::DepthLimit).
+    Reject,
+    /// Moves them to `/RR_MOVED` and links them back to their parent with Rock
+    /// Ridge `CL`, `PL` and `RE` entries.
+    Relocate,
+}
+
+/// Identifier options of the writer.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Naming {
+    pub level: Option<InterchangeLevel>,
+    pub transliterate: bool,
+    pub lowercase: bool,
+    pub full_ascii: bool,
+    pub untranslated: bool,
+    pub omit_version: bool,
+}
+
+/// Original paths given to the writer and the paths stored for them, with
+/// colliding identifiers made unique by a `~N` suffix.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NameMap {
+    paths: BTreeMap<String, PathBuf>,
+    taken: BTreeMap<PathBuf, String>,
+}
+
+impl NameMap {
+    /// Stored path of `path`, registering it and its parents if new. The last
+    /// component is a file unless `is_dir` is set.
+    pub(crate) fn resolve(&mut self, path: &str, is_dir: bool, naming: Naming) -> PathBuf {
+        let components: Vec<&str> = path.split('/').filter(|t| !t.is_empty()).collect();
+
+        let mut original = String::new();
+        let mut stored = PathBuf::from("/");
+
+        for (index, name) in components.iter().enumerate() {
+            original.push('/');
+            original.push_str(name);
+
+            if let Some(known) = self.paths.get(&original) {
+                stored = known.clone();
+                continue;
+            }
+
+            let is_dir = is_dir || index + 1 < components.len();
+            let id = identifier(name, is_dir, naming);
+            let mut candidate = stored.join(&id);
+
+            for n in 1.. {
+                if !self.taken.contains_key(&candidate) {
+                    break;
+                }
+
+                candidate = stored.join(mangle(&id, n, is_dir, naming.level));
+            }
+
+            self.paths.insert(original.clone(), candidate.clone());
+            self.taken.insert(candidate.clone(), original.clone());
+            stored = candidate;
+        }
+
+        stored
+    }
+
+    /// Stored path of `path` if it was resolved before.
+    pub(crate) fn get(&self, path: &str) -> Option<&PathBuf> {
+        let original: String = path
+            .split('/')
+            .filter(|t| !t.is_empty())
+            .flat_map(|t| ["/", t])
+            .collect();
+
+        self.paths.get(&original)
+    }
+
+    pub(crate) fn paths(&self) -> &BTreeMap<String, PathBuf> {
+        &self.paths
+    }
+}
+
+/// `path` with the directories that would be deeper than [`MAX_DEPTH`] moved
+/// to [`RR_MOVED`]. `moved` maps the path each moved directory leaves behind to
+/// its new one and receives the moves not known yet. The last component is a
+/// file unless `is_dir` is set.
+pub(crate) fn relocate(
+    path: &Path,
+    is_dir: bool,
+    level: Option<InterchangeLevel>,
+    moved: &mut BTreeMap<PathBuf, PathBuf>,
+) -> PathBuf {
+    let names: Vec<&str> = path.iter().skip(1).filter_map(|t| t.to_str()).collect();
+    let dirs = if is_dir {
+        names.len()
+    } else {
+        names.len().saturating_sub(1)
+    };
+
+    let mut relocated = PathBuf::from("/");
+    // the root is level 1
+    let mut depth = 1;
+
+    for (index, name) in names.into_iter().enumerate() {
+        if index >= dirs || depth < MAX_DEPTH {
+            relocated.push(name);
+            depth += 1;
+            continue;
+        }
+
+        let placeholder = relocated.join(name);
+
+        relocated = match moved.get(&placeholder) {
+            Some(known) => known.clone(),
+            None => {
+                let parent = Path::new("/").join(RR_MOVED);
+                let mut target = parent.join(name);
+
+                for n in 1.. {
+                    if !moved.values().any(|t| *t == target) {
+                        break;
+                    }
+
+                    target = parent.join(mangle(name, n, true, level));
+                }
+
+                moved.insert(placeholder, target.clone());
+                target
+            }
+        };
+
+        // moved directories are children of RR_MOVED, itself a child of the root
+        depth = 3;
+    }
+
+    relocated
+}
+
+fn identifier(name: &str, is_dir: bool, naming: Naming) -> String {
+    let name = if naming.transliterate {
+        transliterate(name)
+    } else {
+        name.to_string()
+    };
+
+    let id = match naming.level {
+        _ if naming.untranslated => untranslated(&name),
+        Some(level) if is_dir => level.dir_id(&name, naming),
+        Some(level) => level.file_id(&name, naming),
+        None => a_characters(&name, naming),
+    };
+
+    // a name without any usable character
+    if id.is_empty() { "_".to_string() } else { id }
+}
+
+// `id` with a `~n` suffix on its name part, shortened to keep it within the
+// limits of `level`
+fn mangle(id: &str, n: usize, is_dir: bool, level: Option<InterchangeLevel>) -> String {
+    let suffix = format!("~{n}");
+
+    let (stem, extension) = if is_dir {
+        (id, None)
+    } else {
+        match id.rsplit_once('.') {
+            Some((stem, extension)) => (stem, Some(extension)),
+            None => (id, None),
+        }
+    };
+
+    let extension_len = extension.map_or(0, str::len);
+    let max_stem = match level {
+        Some(InterchangeLevel::Level1) => 8,
+        Some(_) if is_dir => 31,
+        Some(_) => 30 - extension_len,
+        None => 222 - extension_len.min(221),
+    };
+
+    let stem: String = stem
+        .chars()
+        .take(max_stem.saturating_sub(suffix.len()))
+        .collect();
+
+    match extension {
+        Some(extension) => format!("{stem}{suffix}.{extension}"),
+        None => format!("{stem}{suffix}"),
+    }
+}
+
+// ASCII spelling of accented Latin letters, with spaces and other characters
+// replaced by _
+fn transliterate(name: &str) -> String {
+    let mut ascii = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        let replacement = match c {
+            ' ' => "_",
+            c if c.is_ascii() => {
+                ascii.push(c);
+                continue;
+            }
+            'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+            'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+            'Æ' => "AE",
+            'æ' => "ae",
+            'Ç' | 'Ć' | 'Č' => "C",
+            'ç' | 'ć' | 'č' => "c",
+            'Ð' | 'Ď' | 'Đ' => "D",
+            'ð' | 'ď' | 'đ' => "d",
+            'È'..='Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => "E",
+            'è'..='ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+            'Ğ' => "G",
+            'ğ' => "g",
+            'Ì'..='Ï' | 'Ī' | 'Į' | 'İ' => "I",
+            'ì'..='ï' | 'ī' | 'į' | 'ı' => "i",
+            'Ł' | 'Ľ' => "L",
+            'ł' | 'ľ' => "l",
+            'Ñ' | 'Ń' | 'Ň' => "N",
+            'ñ' | 'ń' | 'ň' => "n",
+            'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ő' => "O",
+            'ò'..='ö' | 'ø' | 'ō' | 'ő' => "o",
+            'Œ' => "OE",
+            'œ' => "oe",
+            'Ř' => "R",
+            'ř' => "r",
+            'Ś' | 'Ş' | 'Š' => "S",
+            'ś' | 'ş' | 'š' => "s",
+            'ß' => "ss",
+            'Ť' | 'Ţ' => "T",
+            'ť' | 'ţ' => "t",
+            'Þ' => "TH",
+            'þ' => "th",
+            'Ù'..='Ü' | 'Ū' | 'Ů' | 'Ű' | 'Ų' => "U",
+            'ù'..='ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+            'Ý' | 'Ÿ' => "Y",
+            'ý' | 'ÿ' => "y",
+            'Ź' | 'Ż' | 'Ž' => "Z",
+            'ź' | 'ż' | 'ž' => "z",
+            _ => "_",
+        };
+
+        ascii.push_str(replacement);
+    }
+
+    ascii
+}
+
+// `name` as given, cut to the longest identifier a directory record holds
+fn untranslated(name: &str) -> String {
+    let mut end = name.len().min(222);
+
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    name[..end].to_string()
+}
+
+// `name` in the case kept by `naming`
+fn cased(name: &str, naming: Naming) -> String {
+    if naming.lowercase {
+        name.to_string()
+    } else {
+        name.to_uppercase()
+    }
+}
+
+// characters allowed on top of the strict sets by `naming`
+fn relaxed(c: char, naming: Naming) -> bool {
+    (naming.lowercase && c.is_ascii_lowercase())
+        || (naming.full_ascii && matches!(c, ' '..='~') && c != '/' && c != ';')
+}
+
+// upper case, keeping a-characters only, as names are stored without a level
+fn a_characters(name: &str, naming: Naming) -> String {
+    cased(name, naming)
+        .chars()
+        .filter(|&c| {
+            matches!(c,
+            'A'..='Z' | '0'..='9' | '_' |
+            '!' | '"' | '%' | '&' | '\'' | '(' | ')' | '*' | '+' | ',' | '-' | '.' |
+            ':' | ';' | '<' | '=' | '>' | '?')
+                || relaxed(c, naming)
+        })
+        .take(222)
+        .collect()
+}
+
+// upper case, with every other character than A-Z, 0-9 and _ replaced by _
+fn d_characters(name: &str, naming: Naming) -> String {
+    cased(name, naming)
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | '0'..='9' | '_' => c,
+            c if relaxed(c, naming) => c,
+            _ => '_',
+        })
+        .collect()
+}
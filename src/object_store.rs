@@ -0,0 +1,65 @@
+use std::io;
+use std::ops::Range;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use object_store::ObjectStore;
+use object_store::path::Path;
+
+use crate::remote::{RangeReader, RangeSource};
+use crate::{IsoFileReader, Result};
+
+/// Image stored in an [`ObjectStore`] such as S3, GCS or Azure Blob Storage.
+#[derive(Debug, Clone)]
+pub struct ObjectStoreSource {
+    store: Arc<dyn ObjectStore>,
+    location: Path,
+    size: u64,
+}
+
+impl ObjectStoreSource {
+    /// Looks up the object size once so ranges can be clamped to it.
+    pub async fn new(store: Arc<dyn ObjectStore>, location: Path) -> Result<Self> {
+        let meta = store.head(&location).await.map_err(io::Error::other)?;
+
+        Ok(Self {
+            store,
+            location,
+            size: meta.size,
+        })
+    }
+}
+
+impl RangeSource for ObjectStoreSource {
+    fn size(&self) -> BoxFuture<'static, io::Result<u64>> {
+        let size = self.size;
+        Box::pin(async move { Ok(size) })
+    }
+
+    fn fetch(&self, range: Range<u64>) -> BoxFuture<'static, io::Result<Bytes>> {
+        let store = self.store.clone();
+        let location = self.location.clone();
+        let range = range.start.min(self.size)..range.end.min(self.size);
+
+        Box::pin(async move {
+            if range.is_empty() {
+                return Ok(Bytes::new());
+            }
+
+            store
+                .get_range(&location, range)
+                .await
+                .map_err(io::Error::other)
+        })
+    }
+}
+
+impl IsoFileReader<RangeReader<ObjectStoreSource>> {
+    /// Reads an image from an object store with ranged `get` calls.
+    pub async fn open_object(store: Arc<dyn ObjectStore>, location: Path) -> Result<Self> {
+        let source = ObjectStoreSource::new(store, location).await?;
+
+        Self::read(RangeReader::new(source)).await
+    }
+}
@@ -0,0 +1,158 @@
+use crate::Charset;
+
+/// Options for [`IsoFileReader::read_with_options`](crate::IsoFileReader::read_with_options).
+#[derive(Debug, Clone)]
+pub struct IsoReadOptions {
+    pub(crate) lazy: bool,
+    pub(crate) start_offset: u64,
+    pub(crate) session_start: u32,
+    pub(crate) scan_sessions: bool,
+    pub(crate) path_table_lookup: bool,
+    pub(crate) joliet: bool,
+    pub(crate) paired_names: bool,
+    pub(crate) enhanced: bool,
+    pub(crate) rock_ridge: bool,
+    pub(crate) udf: bool,
+    pub(crate) hidden: bool,
+    pub(crate) trans_tbl: bool,
+    pub(crate) charset: Charset,
+    pub(crate) limits: Limits,
+}
+
+impl Default for IsoReadOptions {
+    fn default() -> Self {
+        Self {
+            lazy: false,
+            start_offset: 0,
+            session_start: 0,
+            scan_sessions: false,
+            path_table_lookup: false,
+            joliet: false,
+            paired_names: false,
+            enhanced: false,
+            rock_ridge: true,
+            udf: false,
+            hidden: true,
+            trans_tbl: false,
+            charset: Charset::Utf8,
+            limits: Limits::default(),
+        }
+    }
+}
+
+/// Bounds enforced while parsing untrusted images.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limits {
+    pub max_allocation: u64,
+    pub max_entries: usize,
+    pub max_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_allocation: 256 << 20,
+            max_entries: 10_000_000,
+            max_depth: 255,
+        }
+    }
+}
+
+impl IsoReadOptions {
+    /// Only parse the root directory when opening, subdirectories are read on
+    /// first access through the reader.
+    pub fn set_lazy(&mut self, lazy: bool) {
+        self.lazy = lazy;
+    }
+
+    /// Byte offset of the image inside the reader, all block addresses are
+    /// relative to it.
+    pub fn set_start_offset(&mut self, start_offset: u64) {
+        self.start_offset = start_offset;
+    }
+
+    /// First block of the session to open on a multi-session image. Volume
+    /// descriptors are read from block 16 of that session.
+    pub fn set_session_start(&mut self, session_start: u32) {
+        self.session_start = session_start;
+    }
+
+    /// Look for further sessions after the first one and overlay their
+    /// directory trees, later sessions win on conflicting paths.
+    pub fn set_scan_sessions(&mut self, scan_sessions: bool) {
+        self.scan_sessions = scan_sessions;
+    }
+
+    /// Locate directories through the L path table and only parse the one
+    /// holding the requested entry. Implies lazy mode.
+    pub fn set_path_table_lookup(&mut self, path_table_lookup: bool) {
+        self.path_table_lookup = path_table_lookup;
+    }
+
+    /// Key the entry map by the long names of the Joliet tree when the image
+    /// has one, the primary tree is used otherwise.
+    pub fn set_joliet(&mut self, joliet: bool) {
+        self.joliet = joliet;
+    }
+
+    /// Also read the tree of the primary or Joliet descriptor not used for the
+    /// entry map and pair its names with the entries, see
+    /// [`IsoDirectoryEntry::joliet_name`](crate::IsoDirectoryEntry::joliet_name).
+    pub fn set_paired_names(&mut self, paired_names: bool) {
+        self.paired_names = paired_names;
+    }
+
+    /// Key the entry map by the long names of an ISO 9660:1999 enhanced
+    /// descriptor when the image has one. Joliet takes precedence if both are
+    /// requested.
+    pub fn set_enhanced(&mut self, enhanced: bool) {
+        self.enhanced = enhanced;
+    }
+
+    /// Key the entry map by Rock Ridge `NM` names when the tree has them, on
+    /// by default.
+    pub fn set_rock_ridge(&mut self, rock_ridge: bool) {
+        self.rock_ridge = rock_ridge;
+    }
+
+    /// Read the UDF file system of bridge discs as well. Lookups prefer it
+    /// over the ISO 9660 tree for paths found in both.
+    pub fn set_udf(&mut self, udf: bool) {
+        self.udf = udf;
+    }
+
+    /// Include entries marked hidden, on by default. Without them hidden
+    /// directories are left out with their contents.
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+
+    /// Read the `TRANS.TBL` files of the primary tree to recover original
+    /// names, see [`IsoDirectoryEntry::trans_name`](crate::IsoDirectoryEntry::trans_name).
+    pub fn set_trans_tbl(&mut self, trans_tbl: bool) {
+        self.trans_tbl = trans_tbl;
+    }
+
+    /// Encoding of file identifiers and header strings, UTF-8 by default.
+    pub fn set_charset(&mut self, charset: Charset) {
+        self.charset = charset;
+    }
+
+    /// Largest buffer allocated for a single file or directory extent, 256 MiB
+    /// by default. Larger files are still read through
+    /// [`open_file`](crate::IsoFileReader::open_file).
+    pub fn set_max_allocation(&mut self, max_allocation: u64) {
+        self.limits.max_allocation = max_allocation;
+    }
+
+    /// Most directory records kept in the entry map, path table records
+    /// included.
+    pub fn set_max_entries(&mut self, max_entries: usize) {
+        self.limits.max_entries = max_entries;
+    }
+
+    /// Deepest directory nesting followed, guards against looping trees.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.limits.max_depth = max_depth;
+    }
+}
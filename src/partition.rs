@@ -0,0 +1,205 @@
+//! Partition tables embedded in the system area by isohybrid tools.
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+use crate::Result;
+
+// the 16 sectors before the volume descriptors
+const SYSTEM_AREA_SIZE: usize = 16 * 2048;
+
+const MBR_SECTOR_SIZE: u64 = 512;
+
+// sanity bounds on the GPT partition entry array
+const GPT_ENTRY_LIMIT: u32 = 1024;
+const GPT_ENTRY_SIZE_LIMIT: u32 = 4096;
+
+// bounds the Apple partition map entries read
+const APM_ENTRY_LIMIT: u32 = 64;
+
+/// Primary partition of a master boot record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MbrPartition {
+    pub bootable: bool,
+    pub partition_type: u8,
+    /// First 512 byte sector of the partition.
+    pub start_lba: u32,
+    pub sectors: u32,
+}
+
+/// Used entry of a GUID partition table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GptPartition {
+    /// Partition type GUID in its on-disk mixed-endian form.
+    pub type_guid: [u8; 16],
+    pub guid: [u8; 16],
+    pub first_lba: u64,
+    /// Last 512 byte sector of the partition, inclusive.
+    pub last_lba: u64,
+    pub attributes: u64,
+    pub name: String,
+}
+
+/// Entry of an Apple partition map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApmPartition {
+    pub name: String,
+    pub partition_type: String,
+    /// First block of the partition, in the block size of the map.
+    pub start_block: u32,
+    pub blocks: u32,
+    pub block_size: u16,
+}
+
+/// Partition tables found in the system area of an image.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartitionTables {
+    pub mbr: Vec<MbrPartition>,
+    pub gpt: Vec<GptPartition>,
+    pub apm: Vec<ApmPartition>,
+}
+
+impl PartitionTables {
+    /// A partition table makes the image bootable when written to a USB drive.
+    pub fn is_hybrid(&self) -> bool {
+        !self.mbr.is_empty() || !self.gpt.is_empty() || !self.apm.is_empty()
+    }
+
+    pub(crate) async fn read<R: AsyncRead + AsyncSeekExt + Unpin>(
+        reader: &mut R,
+        base: u64,
+    ) -> Result<Self> {
+        let mut system_area = vec![0u8; SYSTEM_AREA_SIZE];
+        reader.seek(SeekFrom::Start(base)).await?;
+        reader.read_exact(&mut system_area).await?;
+
+        Ok(Self {
+            mbr: parse_mbr(&system_area),
+            gpt: read_gpt(reader, base, &system_area).await?,
+            apm: parse_apm(&system_area),
+        })
+    }
+}
+
+fn u32_le(raw: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(raw[at..at + 4].try_into().unwrap())
+}
+
+fn u64_le(raw: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(raw[at..at + 8].try_into().unwrap())
+}
+
+fn u32_be(raw: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(raw[at..at + 4].try_into().unwrap())
+}
+
+fn parse_mbr(system_area: &[u8]) -> Vec<MbrPartition> {
+    if system_area[510..512] != [0x55, 0xaa] {
+        return Vec::new();
+    }
+
+    system_area[446..510]
+        .chunks_exact(16)
+        .filter(|t| t[4] != 0)
+        .map(|t| MbrPartition {
+            bootable: t[0] == 0x80,
+            partition_type: t[4],
+            start_lba: u32_le(t, 8),
+            sectors: u32_le(t, 12),
+        })
+        .collect()
+}
+
+async fn read_gpt<R: AsyncRead + AsyncSeekExt + Unpin>(
+    reader: &mut R,
+    base: u64,
+    system_area: &[u8],
+) -> Result<Vec<GptPartition>> {
+    // the header is in the second 512 byte sector
+    let header = &system_area[512..1024];
+
+    if &header[..8] != b"EFI PART" {
+        return Ok(Vec::new());
+    }
+
+    let entries_lba = u64_le(header, 72);
+    let count = u32_le(header, 80).min(GPT_ENTRY_LIMIT);
+    let entry_size = u32_le(header, 84);
+
+    if !(128..=GPT_ENTRY_SIZE_LIMIT).contains(&entry_size) {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = vec![0u8; (count * entry_size) as usize];
+    reader
+        .seek(SeekFrom::Start(base + entries_lba * MBR_SECTOR_SIZE))
+        .await?;
+    reader.read_exact(&mut entries).await?;
+
+    Ok(entries
+        .chunks_exact(entry_size as usize)
+        .filter(|t| t[..16].iter().any(|&t| t != 0))
+        .map(|t| {
+            let name: Vec<u16> = t[56..128]
+                .chunks_exact(2)
+                .map(|t| u16::from_le_bytes([t[0], t[1]]))
+                .take_while(|&t| t != 0)
+                .collect();
+
+            GptPartition {
+                type_guid: t[..16].try_into().unwrap(),
+                guid: t[16..32].try_into().unwrap(),
+                first_lba: u64_le(t, 32),
+                last_lba: u64_le(t, 40),
+                attributes: u64_le(t, 48),
+                name: String::from_utf16_lossy(&name),
+            }
+        })
+        .collect())
+}
+
+fn parse_apm(system_area: &[u8]) -> Vec<ApmPartition> {
+    // driver descriptor map with the block size of the partition map
+    if &system_area[..2] != b"ER" {
+        return Vec::new();
+    }
+
+    let block_size = u16::from_be_bytes([system_area[2], system_area[3]]);
+
+    if !block_size.is_power_of_two() || block_size < 512 {
+        return Vec::new();
+    }
+
+    let text = |raw: &[u8]| {
+        String::from_utf8_lossy(raw)
+            .trim_end_matches('\0')
+            .to_string()
+    };
+
+    let mut partitions = Vec::new();
+
+    for block in 1..=APM_ENTRY_LIMIT {
+        let start = block as usize * usize::from(block_size);
+        let Some(entry) = system_area.get(start..start + 512) else {
+            break;
+        };
+
+        if &entry[..2] != b"PM" {
+            break;
+        }
+
+        partitions.push(ApmPartition {
+            name: text(&entry[16..48]),
+            partition_type: text(&entry[48..80]),
+            start_block: u32_be(entry, 8),
+            blocks: u32_be(entry, 12),
+            block_size,
+        });
+
+        // number of entries in the map, recorded in every entry
+        if block >= u32_be(entry, 4) {
+            break;
+        }
+    }
+
+    partitions
+}
@@ -0,0 +1,89 @@
+//! Progress and cancellation of [`IsoFileWriter::close`](crate::IsoFileWriter::close).
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::watch;
+
+use crate::{IsoFileError, Result};
+
+/// Where writing an image stands, see
+/// [`IsoFileWriter::subscribe_progress`](crate::IsoFileWriter::subscribe_progress).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteProgress {
+    /// File whose data is being written, `None` while the descriptors, path
+    /// tables and directories are.
+    pub path: Option<PathBuf>,
+    /// Bytes of the image written so far.
+    pub written: u64,
+    /// Bytes of the whole image.
+    pub total: u64,
+}
+
+/// Sender of the progress, once someone subscribed.
+#[derive(Debug, Default)]
+pub(crate) struct Progress(Option<watch::Sender<WriteProgress>>);
+
+impl Progress {
+    pub fn subscribe(&mut self) -> watch::Receiver<WriteProgress> {
+        self.0
+            .get_or_insert_with(|| watch::channel(WriteProgress::default()).0)
+            .subscribe()
+    }
+
+    /// Starts an image of `total` bytes, `written` of which are already.
+    pub fn start(&self, written: usize, total: usize) {
+        if let Some(sender) = &self.0 {
+            sender.send_replace(WriteProgress {
+                path: None,
+                written: written as u64,
+                total: total as u64,
+            });
+        }
+    }
+
+    /// Data of `path` follows, or volume structures if `None`.
+    pub fn set_path(&self, path: Option<&Path>) {
+        if let Some(sender) = &self.0 {
+            sender.send_modify(|t| t.path = path.map(Path::to_path_buf));
+        }
+    }
+
+    /// `bytes` more were written.
+    pub fn advance(&self, bytes: usize) {
+        if let Some(sender) = &self.0 {
+            sender.send_modify(|t| t.written += bytes as u64);
+        }
+    }
+}
+
+/// Flag that cancels writing once set, latched so the writer keeps failing.
+#[derive(Debug, Default)]
+pub(crate) struct Cancel {
+    flag: Option<Arc<AtomicBool>>,
+    cancelled: AtomicBool,
+}
+
+impl Cancel {
+    pub fn set_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.flag = Some(flag);
+    }
+
+    /// Fails with [`IsoFileError::Cancelled`] once the flag was set.
+    pub fn check(&self) -> Result<()> {
+        if self
+            .flag
+            .as_ref()
+            .is_some_and(|t| t.load(Ordering::Relaxed))
+        {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+
+        if self.cancelled.load(Ordering::Relaxed) {
+            Err(IsoFileError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::io::{self, SeekFrom};
+use std::ops::Range;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+/// Bytes fetched per request when reading sequentially.
+pub const DEFAULT_FETCH_SIZE: u64 = 256 * 1024;
+
+/// Storage that can serve byte ranges of an image, e.g. over HTTP.
+pub trait RangeSource {
+    /// Total size of the image in bytes.
+    fn size(&self) -> BoxFuture<'static, io::Result<u64>>;
+
+    /// Bytes in `range`, shorter or empty when it goes past the end.
+    fn fetch(&self, range: Range<u64>) -> BoxFuture<'static, io::Result<Bytes>>;
+}
+
+/// Reader over a [`RangeSource`]. A read outside the last fetched block
+/// fetches the next fetch size worth of bytes from its position, and only
+/// that block is kept in memory.
+pub struct RangeReader<S: RangeSource> {
+    source: S,
+    fetch_size: u64,
+    position: u64,
+    length: Option<u64>,
+    // start offset and content of the last fetched block
+    cache: Option<(u64, Bytes)>,
+    fetching: Option<BoxFuture<'static, io::Result<(u64, Bytes)>>>,
+    measuring: Option<BoxFuture<'static, io::Result<u64>>>,
+    seek_end: Option<i64>,
+}
+
+impl<S: RangeSource> RangeReader<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+            fetch_size: DEFAULT_FETCH_SIZE,
+            position: 0,
+            length: None,
+            cache: None,
+            fetching: None,
+            measuring: None,
+            seek_end: None,
+        }
+    }
+
+    /// Bytes requested from the source at once, [`DEFAULT_FETCH_SIZE`] by default.
+    pub fn set_fetch_size(&mut self, fetch_size: u64) {
+        self.fetch_size = fetch_size.max(1);
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    fn cached(&self) -> Option<&[u8]> {
+        let (start, data) = self.cache.as_ref()?;
+        let offset = self.position.checked_sub(*start)?;
+
+        (offset < data.len() as u64).then(|| &data[offset as usize..])
+    }
+}
+
+impl<S: RangeSource> fmt::Debug for RangeReader<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangeReader")
+            .field("fetch_size", &self.fetch_size)
+            .field("position", &self.position)
+            .field("length", &self.length)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: RangeSource + Unpin> AsyncRead for RangeReader<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(data) = this.cached() {
+                let len = data.len().min(buf.remaining());
+                buf.put_slice(&data[..len]);
+                this.position += len as u64;
+
+                return Poll::Ready(Ok(()));
+            }
+
+            if this.length.is_some_and(|t| this.position >= t) || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let fetching = this.fetching.get_or_insert_with(|| {
+                let start = this.position;
+                let fetch = this.source.fetch(start..start + this.fetch_size);
+
+                Box::pin(async move { Ok((start, fetch.await?)) })
+            });
+
+            let (start, data) = ready!(fetching.as_mut().poll(cx))?;
+            this.fetching = None;
+
+            if data.is_empty() {
+                this.length = Some(start);
+                return Poll::Ready(Ok(()));
+            }
+
+            this.cache = Some((start, data));
+        }
+    }
+}
+
+impl<S: RangeSource + Unpin> AsyncSeek for RangeReader<S> {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+
+        this.seek_end = None;
+        // a fetch for the old position is no longer useful
+        this.fetching = None;
+
+        let target = match position {
+            SeekFrom::Start(t) => Some(t),
+            SeekFrom::Current(t) => this.position.checked_add_signed(t),
+            SeekFrom::End(t) => match this.length {
+                Some(length) => length.checked_add_signed(t),
+                None => {
+                    this.seek_end = Some(t);
+                    return Ok(());
+                }
+            },
+        };
+
+        this.position = target.ok_or_else(invalid_seek)?;
+
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+
+        if let Some(offset) = this.seek_end {
+            let measuring = this.measuring.get_or_insert_with(|| this.source.size());
+            let length = ready!(measuring.as_mut().poll(cx))?;
+
+            this.measuring = None;
+            this.seek_end = None;
+            this.length = Some(length);
+            this.position = length.checked_add_signed(offset).ok_or_else(invalid_seek)?;
+        }
+
+        Poll::Ready(Ok(this.position))
+    }
+}
+
+fn invalid_seek() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "invalid seek to a negative or overflowing position",
+    )
+}
@@ -0,0 +1,258 @@
+//! Rock Ridge Interchange Protocol (IEEE P1282) entries.
+
+use std::mem::transmute;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+
+use crate::susp;
+use crate::types::{DecDateTime, IsoDateTime};
+use crate::xa::{XA_SIZE, XaAttributes};
+use crate::zisofs::Zisofs;
+
+// NM flags
+const NM_CONTINUE: u8 = 0x01;
+const NM_CURRENT: u8 = 0x02;
+const NM_PARENT: u8 = 0x04;
+
+// SL flags, the component flags share CONTINUE
+const SL_CONTINUE: u8 = 0x01;
+const SL_CURRENT: u8 = 0x02;
+const SL_PARENT: u8 = 0x04;
+const SL_ROOT: u8 = 0x08;
+
+// TF flags
+const TF_CREATION: u8 = 0x01;
+const TF_MODIFY: u8 = 0x02;
+const TF_ACCESS: u8 = 0x04;
+const TF_ATTRIBUTES: u8 = 0x08;
+const TF_LONG_FORM: u8 = 0x80;
+
+/// Offset of the SUSP entries in every system use area, from the `SP` entry at
+/// the start of the root `.` record. `None` if SUSP is not in use.
+///
+/// On XA discs the `SP` entry follows the XA record, which is skipped as well.
+pub(crate) fn susp_skip(root_area: &[u8]) -> Option<u8> {
+    match root_area {
+        [b'S', b'P', 7, _, 0xbe, 0xef, skip, ..] => Some(*skip),
+        _ if XaAttributes::parse(root_area).is_some() => {
+            susp_skip(&root_area[XA_SIZE..]).map(|t| t.saturating_add(XA_SIZE as u8))
+        }
+        _ => None,
+    }
+}
+
+/// POSIX file attributes from a `PX` entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PosixAttributes {
+    /// File mode including the file type bits, as in `st_mode`.
+    pub mode: u32,
+    pub links: u32,
+    pub uid: u32,
+    pub gid: u32,
+    /// File serial number, only recorded by RRIP 1.12.
+    pub serial: Option<u32>,
+}
+
+/// Timestamps from a `TF` entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RockRidgeTimes {
+    pub created: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+    pub accessed: Option<DateTime<Utc>>,
+    /// Last change of the attributes, as in `st_ctime`.
+    pub attributes_changed: Option<DateTime<Utc>>,
+}
+
+/// Rock Ridge fields decoded from a system use area.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RockRidge {
+    pub name: Option<String>,
+    pub posix: Option<PosixAttributes>,
+    pub times: RockRidgeTimes,
+    pub symlink: Option<PathBuf>,
+    /// Block of a directory moved away from here, from the `CL` entry.
+    pub child_link: Option<u32>,
+    /// Set by the `RE` entry on the moved directory in its new parent.
+    pub relocated: bool,
+    pub zisofs: Option<Zisofs>,
+}
+
+impl RockRidge {
+    pub fn parse(area: &[u8]) -> Self {
+        Self {
+            name: name(area),
+            posix: posix(area),
+            times: times(area),
+            symlink: symlink(area).map(PathBuf::from),
+            child_link: susp::entries(area)
+                .find(|t| &t.signature == b"CL")
+                .and_then(|t| lsb_u32(t.data, 0)),
+            relocated: susp::entries(area).any(|t| &t.signature == b"RE"),
+            zisofs: susp::entries(area)
+                .find(|t| &t.signature == b"ZF")
+                .and_then(|t| Zisofs::parse(t.data)),
+        }
+    }
+}
+
+/// `SP` entry the root `.` record of a written image starts its system use
+/// area with, with no bytes to skip.
+pub(crate) fn sp_entry() -> Vec<u8> {
+    vec![b'S', b'P', 7, 1, 0xbe, 0xef, 0]
+}
+
+/// `CL` entry of the placeholder of a moved directory, linking to its extent.
+pub(crate) fn cl_entry(location: u32) -> Vec<u8> {
+    location_entry(*b"CL", location)
+}
+
+/// `PL` entry of the `..` record of a moved directory, linking to the extent
+/// of its original parent.
+pub(crate) fn pl_entry(location: u32) -> Vec<u8> {
+    location_entry(*b"PL", location)
+}
+
+/// `RE` entry of the record of a moved directory in its new parent.
+pub(crate) fn re_entry() -> Vec<u8> {
+    vec![b'R', b'E', 4, 1]
+}
+
+fn location_entry(signature: [u8; 2], location: u32) -> Vec<u8> {
+    let mut entry = vec![signature[0], signature[1], 12, 1];
+    entry.extend(location.to_le_bytes());
+    entry.extend(location.to_be_bytes());
+    entry
+}
+
+// lsb half of the both-endian u32 at `index`
+fn lsb_u32(data: &[u8], index: usize) -> Option<u32> {
+    let bytes = data.get(index * 8..index * 8 + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn posix(area: &[u8]) -> Option<PosixAttributes> {
+    let data = susp::entries(area).find(|t| &t.signature == b"PX")?.data;
+
+    Some(PosixAttributes {
+        mode: lsb_u32(data, 0)?,
+        links: lsb_u32(data, 1)?,
+        uid: lsb_u32(data, 2)?,
+        gid: lsb_u32(data, 3)?,
+        serial: lsb_u32(data, 4),
+    })
+}
+
+// 7-byte or 17-byte ISO 9660 timestamp
+pub(crate) fn timestamp(bytes: &[u8]) -> Option<DateTime<Utc>> {
+    match bytes.len() {
+        7 => {
+            let raw: [u8; 7] = bytes.try_into().ok()?;
+            let datetime: IsoDateTime = unsafe { transmute(raw) };
+            datetime.try_into().ok()
+        }
+        17 => {
+            let raw: [u8; 17] = bytes.try_into().ok()?;
+            let datetime: DecDateTime = unsafe { transmute(raw) };
+            datetime.try_into().ok()
+        }
+        _ => None,
+    }
+}
+
+fn times(area: &[u8]) -> RockRidgeTimes {
+    let Some((&flags, data)) = susp::entries(area)
+        .find(|t| &t.signature == b"TF")
+        .and_then(|t| t.data.split_first())
+    else {
+        return RockRidgeTimes::default();
+    };
+
+    let size = if flags & TF_LONG_FORM != 0 { 17 } else { 7 };
+    let mut stamps = data.chunks_exact(size);
+    // stamps are recorded in flag order, only for the flags that are set
+    let mut next = |flag: u8| {
+        if flags & flag != 0 {
+            stamps.next().and_then(timestamp)
+        } else {
+            None
+        }
+    };
+
+    RockRidgeTimes {
+        created: next(TF_CREATION),
+        modified: next(TF_MODIFY),
+        accessed: next(TF_ACCESS),
+        attributes_changed: next(TF_ATTRIBUTES),
+    }
+}
+
+/// Alternate name from the `NM` entries of a system use area.
+fn name(area: &[u8]) -> Option<String> {
+    let mut name = Vec::new();
+    let mut found = false;
+
+    for entry in susp::entries(area).filter(|t| &t.signature == b"NM") {
+        let Some((&flags, content)) = entry.data.split_first() else {
+            continue;
+        };
+
+        if flags & (NM_CURRENT | NM_PARENT) != 0 {
+            continue;
+        }
+
+        name.extend_from_slice(content);
+        found = true;
+
+        if flags & NM_CONTINUE == 0 {
+            break;
+        }
+    }
+
+    found.then(|| String::from_utf8_lossy(&name).to_string())
+}
+
+/// Symbolic link target from the `SL` entries of a system use area.
+fn symlink(area: &[u8]) -> Option<String> {
+    let mut target = String::new();
+    let mut found = false;
+    // the previous component was complete, a separator goes before the next
+    let mut separate = false;
+
+    for entry in susp::entries(area).filter(|t| &t.signature == b"SL") {
+        let Some((&flags, mut components)) = entry.data.split_first() else {
+            continue;
+        };
+
+        found = true;
+
+        while let [component_flags, length, rest @ ..] = components {
+            let Some(content) = rest.get(..usize::from(*length)) else {
+                break;
+            };
+
+            if separate && !target.ends_with('/') {
+                target.push('/');
+            }
+
+            if component_flags & SL_ROOT != 0 {
+                target.push('/');
+            } else if component_flags & SL_CURRENT != 0 {
+                target.push('.');
+            } else if component_flags & SL_PARENT != 0 {
+                target.push_str("..");
+            } else {
+                target.push_str(&String::from_utf8_lossy(content));
+            }
+
+            separate = component_flags & SL_CONTINUE == 0;
+            components = &rest[content.len()..];
+        }
+
+        if flags & SL_CONTINUE == 0 {
+            break;
+        }
+    }
+
+    found.then_some(target)
+}
@@ -0,0 +1,232 @@
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+use crate::core::LOGICAL_BLOCK_SIZE;
+
+/// Size of a raw CD sector including sync, header and error correction.
+pub const RAW_SECTOR_SIZE: usize = 2352;
+
+/// Layout of the sectors stored in an image file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectorMode {
+    /// Plain 2048-byte user data sectors, as found in `.iso` files.
+    Cooked,
+    /// 2352-byte Mode 1 sectors: 12 sync, 4 header, 2048 data, 288 EDC/ECC.
+    Mode1,
+    /// 2352-byte Mode 2 Form 1 (XA) sectors: 12 sync, 4 header, 8 subheader,
+    /// 2048 data, 280 EDC/ECC.
+    Mode2Form1,
+}
+
+impl SectorMode {
+    pub fn sector_size(&self) -> usize {
+        match self {
+            Self::Cooked => LOGICAL_BLOCK_SIZE,
+            Self::Mode1 | Self::Mode2Form1 => RAW_SECTOR_SIZE,
+        }
+    }
+
+    /// Offset of the user data inside a sector.
+    pub fn data_offset(&self) -> usize {
+        match self {
+            Self::Cooked => 0,
+            Self::Mode1 => 16,
+            Self::Mode2Form1 => 24,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SeekState {
+    Idle,
+    // seek relative to the end, waiting for the image length
+    End(i64),
+    // inner seek to the end in progress to measure the image length
+    Measuring(i64),
+}
+
+/// Exposes the 2048-byte user data of every sector of `R` as one contiguous
+/// stream, stripping sync, headers and error correction codes.
+#[derive(Debug)]
+pub struct RawSectorReader<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    inner: R,
+    mode: SectorMode,
+    // position in user data bytes
+    position: u64,
+    // user data length, known once measured
+    length: Option<u64>,
+    // raw position of the inner reader, if known
+    inner_position: Option<u64>,
+    pending_seek: bool,
+    seek_state: SeekState,
+}
+
+impl<R> RawSectorReader<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    pub fn new(inner: R, mode: SectorMode) -> Self {
+        Self {
+            inner,
+            mode,
+            position: 0,
+            length: None,
+            inner_position: None,
+            pending_seek: false,
+            seek_state: SeekState::Idle,
+        }
+    }
+
+    pub fn mode(&self) -> SectorMode {
+        self.mode
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn raw_position(&self, position: u64) -> u64 {
+        let sector = position / LOGICAL_BLOCK_SIZE as u64;
+        let within = position % LOGICAL_BLOCK_SIZE as u64;
+
+        sector * self.mode.sector_size() as u64 + self.mode.data_offset() as u64 + within
+    }
+
+    fn poll_pending_seek(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.pending_seek {
+            let position = ready!(Pin::new(&mut self.inner).poll_complete(cx))?;
+            self.pending_seek = false;
+            self.inner_position = Some(position);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_sync_inner(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let target = self.raw_position(self.position);
+
+        loop {
+            ready!(self.poll_pending_seek(cx))?;
+
+            if self.inner_position == Some(target) {
+                return Poll::Ready(Ok(()));
+            }
+
+            Pin::new(&mut self.inner).start_seek(SeekFrom::Start(target))?;
+            self.pending_seek = true;
+        }
+    }
+}
+
+impl<R> AsyncRead for RawSectorReader<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        ready!(this.poll_sync_inner(cx))?;
+
+        // never read past the user data of the current sector
+        let within = (this.position % LOGICAL_BLOCK_SIZE as u64) as usize;
+        let max = (LOGICAL_BLOCK_SIZE - within).min(buf.remaining());
+        let mut limited = buf.take(max);
+
+        ready!(Pin::new(&mut this.inner).poll_read(cx, &mut limited))?;
+
+        let read = limited.filled().len();
+
+        // SAFETY: `limited` was filled by the inner reader over `buf`'s unfilled part
+        unsafe { buf.assume_init(read) };
+        buf.advance(read);
+
+        this.position += read as u64;
+        this.inner_position = this.inner_position.map(|t| t + read as u64);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R> AsyncSeek for RawSectorReader<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+
+        let target = match position {
+            SeekFrom::Start(t) => Some(t),
+            SeekFrom::Current(t) => this.position.checked_add_signed(t),
+            SeekFrom::End(t) => {
+                this.seek_state = SeekState::End(t);
+                return Ok(());
+            }
+        };
+
+        match target {
+            Some(t) => {
+                this.position = t;
+                this.seek_state = SeekState::Idle;
+                Ok(())
+            }
+            None => Err(invalid_seek()),
+        }
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+
+        loop {
+            match this.seek_state {
+                SeekState::Idle => return Poll::Ready(Ok(this.position)),
+                SeekState::End(offset) => match this.length {
+                    Some(length) => {
+                        this.position =
+                            length.checked_add_signed(offset).ok_or_else(invalid_seek)?;
+                        this.seek_state = SeekState::Idle;
+                    }
+                    None => {
+                        ready!(this.poll_pending_seek(cx))?;
+
+                        Pin::new(&mut this.inner).start_seek(SeekFrom::End(0))?;
+                        this.inner_position = None;
+                        this.seek_state = SeekState::Measuring(offset);
+                    }
+                },
+                SeekState::Measuring(offset) => {
+                    let end = ready!(Pin::new(&mut this.inner).poll_complete(cx))?;
+                    let sectors = end / this.mode.sector_size() as u64;
+
+                    this.inner_position = Some(end);
+                    this.length = Some(sectors * LOGICAL_BLOCK_SIZE as u64);
+                    this.seek_state = SeekState::End(offset);
+                }
+            }
+        }
+    }
+}
+
+fn invalid_seek() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "invalid seek to a negative or overflowing position",
+    )
+}
@@ -0,0 +1,127 @@
+//! System Use Sharing Protocol (IEEE P1281) entries, the container format of
+//! Rock Ridge and other extensions stored in the system use area of a record.
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+use crate::Result;
+use crate::core::BlockLayout;
+
+// upper bound on the continuation areas followed from one record
+const CONTINUATION_LIMIT: usize = 64;
+
+/// One entry of a system use area.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SuspEntry<'a> {
+    pub signature: [u8; 2],
+    pub data: &'a [u8],
+}
+
+/// Location of a continuation area, from a `CE` entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Continuation {
+    pub block: u32,
+    pub offset: u32,
+    pub length: u32,
+}
+
+impl SuspEntry<'_> {
+    pub fn continuation(&self) -> Option<Continuation> {
+        if &self.signature != b"CE" || self.data.len() < 24 {
+            return None;
+        }
+
+        let lsb = |at: usize| u32::from_le_bytes(self.data[at..at + 4].try_into().unwrap());
+
+        Some(Continuation {
+            block: lsb(0),
+            offset: lsb(8),
+            length: lsb(16),
+        })
+    }
+}
+
+/// Iterates the entries of a system use area, stopping at `ST`, at padding or
+/// at the first malformed entry.
+pub(crate) fn entries(area: &[u8]) -> impl Iterator<Item = SuspEntry<'_>> {
+    let mut rest = area;
+
+    std::iter::from_fn(move || {
+        if rest.len() < 4 {
+            return None;
+        }
+
+        let length = usize::from(rest[2]);
+
+        if length < 4 || length > rest.len() || &rest[..2] == b"ST" {
+            return None;
+        }
+
+        let entry = SuspEntry {
+            signature: [rest[0], rest[1]],
+            data: &rest[4..length],
+        };
+
+        rest = &rest[length..];
+
+        Some(entry)
+    })
+}
+
+/// System use area of a raw directory record, after the file identifier and
+/// its padding byte.
+pub(crate) fn system_use(record: &[u8]) -> &[u8] {
+    let Some(&id_len) = record.get(32) else {
+        return &[];
+    };
+
+    let start = 33 + usize::from(id_len) + usize::from(id_len % 2 == 0);
+    let end = usize::from(record[0]).min(record.len());
+
+    record.get(start..end).unwrap_or_default()
+}
+
+/// Entries of a system use area followed by those of its `CE` continuation
+/// areas, stored back to back so they can be walked with [`entries`].
+pub(crate) async fn read_entries<R: AsyncRead + AsyncSeekExt + Unpin>(
+    reader: &mut R,
+    layout: BlockLayout,
+    area: &[u8],
+) -> Result<Vec<u8>> {
+    let mut collected = Vec::with_capacity(area.len());
+    let mut area = area.to_vec();
+    let block_size = u64::from(layout.logical_block_size());
+
+    for _ in 0..CONTINUATION_LIMIT {
+        let mut next = None;
+
+        for entry in entries(&area) {
+            match entry.continuation() {
+                Some(continuation) => next = Some(continuation),
+                None => {
+                    collected.extend_from_slice(&entry.signature);
+                    collected.extend([entry.data.len() as u8 + 4, 1]);
+                    collected.extend_from_slice(entry.data);
+                }
+            }
+        }
+
+        // a continuation area lies within one logical block
+        let Some(Continuation {
+            block,
+            offset,
+            length,
+        }) = next.filter(|t| u64::from(t.offset) + u64::from(t.length) <= block_size)
+        else {
+            break;
+        };
+
+        reader
+            .seek(SeekFrom::Start(layout.offset(block) + u64::from(offset)))
+            .await?;
+
+        area = vec![0u8; length as usize];
+        reader.read_exact(&mut area).await?;
+    }
+
+    Ok(collected)
+}
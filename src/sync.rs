@@ -0,0 +1,220 @@
+//! Blocking API over [`std::io::Read`] + [`std::io::Seek`], sharing the parser
+//! of the async reader. It does not need a tokio runtime, tokio is only
+//! built with its I/O traits, `fs` and `sync`.
+
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::executor::block_on;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, ReadBuf};
+
+use crate::{ChecksumAlgorithm, FileMetadata, IsoReadOptions, Result};
+
+/// Adapts a blocking reader to the async traits, every call completes at once.
+#[derive(Debug)]
+pub struct SyncIo<R> {
+    inner: R,
+    seek: Option<io::Result<u64>>,
+}
+
+impl<R> SyncIo<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, seek: None }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Unpin> AsyncRead for SyncIo<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let read = self.get_mut().inner.read(buf.initialize_unfilled())?;
+        buf.advance(read);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: Seek + Unpin> AsyncSeek for SyncIo<R> {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        this.seek = Some(this.inner.seek(position));
+
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+
+        match this.seek.take() {
+            Some(t) => Poll::Ready(t),
+            None => Poll::Ready(this.inner.stream_position()),
+        }
+    }
+}
+
+/// Blocking counterpart of [`crate::IsoFileReader`].
+///
+/// Queries that do not touch the image, like `entries()` or `header()`, are
+/// reached through `Deref`.
+#[derive(Debug)]
+pub struct IsoFileReader<R>
+where
+    R: Read + Seek + Unpin,
+{
+    inner: crate::IsoFileReader<SyncIo<R>>,
+}
+
+impl<R> IsoFileReader<R>
+where
+    R: Read + Seek + Unpin,
+{
+    pub fn read(reader: R) -> Result<Self> {
+        Self::read_with_options(reader, IsoReadOptions::default())
+    }
+
+    pub fn read_with_options(reader: R, options: IsoReadOptions) -> Result<Self> {
+        let inner = block_on(crate::IsoFileReader::read_with_options(
+            SyncIo::new(reader),
+            options,
+        ))?;
+
+        Ok(Self { inner })
+    }
+
+    pub fn set_recovery(&mut self, recovery: bool) {
+        self.inner.set_recovery(recovery);
+    }
+
+    pub fn exists<P: Into<PathBuf> + Ord>(&mut self, path: P) -> bool {
+        block_on(self.inner.exists(path))
+    }
+
+    pub fn is_file<P: Into<PathBuf> + Ord>(&mut self, path: P) -> bool {
+        block_on(self.inner.is_file(path))
+    }
+
+    pub fn is_dir<P: Into<PathBuf> + Ord>(&mut self, path: P) -> bool {
+        block_on(self.inner.is_dir(path))
+    }
+
+    pub fn metadata<P: Into<PathBuf> + Ord>(&mut self, path: P) -> Result<FileMetadata> {
+        block_on(self.inner.metadata(path))
+    }
+
+    pub fn read_file<P: Into<PathBuf> + Ord>(&mut self, path: P) -> Result<Vec<u8>> {
+        block_on(self.inner.read_file(path))
+    }
+
+    pub fn read_file_into<P: Into<PathBuf> + Ord>(
+        &mut self,
+        path: P,
+        buffer: &mut Vec<u8>,
+    ) -> Result<()> {
+        block_on(self.inner.read_file_into(path, buffer))
+    }
+
+    pub fn read_file_to_slice<P: Into<PathBuf> + Ord>(
+        &mut self,
+        path: P,
+        buffer: &mut [u8],
+    ) -> Result<usize> {
+        block_on(self.inner.read_file_to_slice(path, buffer))
+    }
+
+    pub fn read_dir_raw<P: Into<PathBuf> + Ord>(&mut self, path: P) -> Result<Vec<u8>> {
+        block_on(self.inner.read_dir_raw(path))
+    }
+
+    pub fn open_file<P: Into<PathBuf> + Ord>(
+        &mut self,
+        path: P,
+    ) -> Result<IsoFileHandle<'_, SyncIo<R>>> {
+        let inner = block_on(self.inner.open_file(path))?;
+
+        Ok(IsoFileHandle { inner })
+    }
+
+    pub fn checksum_file<P: Into<PathBuf> + Ord>(
+        &mut self,
+        path: P,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<Vec<u8>> {
+        block_on(self.inner.checksum_file(path, algorithm))
+    }
+
+    pub fn load_dir<P: Into<PathBuf> + Ord>(&mut self, path: P) -> Result<()> {
+        block_on(self.inner.load_dir(path))
+    }
+
+    pub fn load_all(&mut self) -> Result<()> {
+        block_on(self.inner.load_all())
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner().into_inner()
+    }
+}
+
+impl<R> Deref for IsoFileReader<R>
+where
+    R: Read + Seek + Unpin,
+{
+    type Target = crate::IsoFileReader<SyncIo<R>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// Blocking counterpart of [`crate::IsoFileHandle`].
+#[derive(Debug)]
+pub struct IsoFileHandle<'r, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    inner: crate::IsoFileHandle<'r, R>,
+}
+
+impl<R> IsoFileHandle<'_, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    pub fn len(&self) -> u64 {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+impl<R> Read for IsoFileHandle<'_, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        block_on(self.inner.read(buf))
+    }
+}
+
+impl<R> Seek for IsoFileHandle<'_, R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        block_on(self.inner.seek(position))
+    }
+}
@@ -1,7 +1,25 @@
-use chrono::Utc;
+use std::io::{Cursor, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use bytes::Bytes;
+use chrono::{FixedOffset, TimeZone, Utc};
+use flate2::write::ZlibEncoder;
+use futures::future::BoxFuture;
+use futures::{StreamExt, TryStreamExt};
+use md5::Md5;
+use sha2::{Digest, Sha256};
 use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
 
-use crate::{IsoFileReader, IsoFileWriter, core::IsoHeader};
+use crate::core::IsoHeader;
+use crate::{BootMediaType, BootPlatform, Charset, VolumeDescriptor, VolumeDescriptorType};
+use crate::{ChecksumAlgorithm, IsoDirectoryEntry, IsoFileReader, IsoFileWriter, IsoIndex};
+use crate::{CloseError, DeepDirectories, IsoEntry, PathFilter, Violation, WriteProgress};
+use crate::{CueSheet, RAW_SECTOR_SIZE, RangeReader, RangeSource, RawSectorReader, SectorMode};
+use crate::{InterchangeLevel, IsoCapabilities, IsoFileError, IsoPathTable, IsoReadOptions};
 
 #[tokio::test]
 async fn main() {
@@ -10,24 +28,4570 @@ async fn main() {
     let mut header = IsoHeader::default();
     header.set_volumen_id("DICOM");
 
-    let mut writer = IsoFileWriter::new(&mut buffer1, header)
+    let mut writer = IsoFileWriter::new(&mut buffer1, header).await.unwrap();
+
+    writer
+        .append_file("/hello.txt", b"Hello, World!", Utc::now())
+        .unwrap();
+
+    writer
+        .append_file("/one/hello2.txt", b"Hello, World!", Utc::now())
+        .unwrap();
+    writer
+        .append_file("/one/hello3.txt", b"Hello, World!", Utc::now())
+        .unwrap();
+
+    writer
+        .append_file("/one/three/hello8.txt", b"Hello, World!", Utc::now())
+        .unwrap();
+    writer
+        .append_file("/one/three/hello9.txt", b"Hello, World!", Utc::now())
+        .unwrap();
+
+    writer
+        .append_file("/two/hello4.txt", b"Hello, World!", Utc::now())
+        .unwrap();
+    writer
+        .append_file("/two/hellowaka.txt", b"Hello, Worldx!", Utc::now())
+        .unwrap();
+
+    writer.close().await.unwrap();
+
+    let mut buffer2 = File::open("image2.iso").await.unwrap();
+
+    IsoFileReader::read(&mut buffer2).await.unwrap();
+}
+
+#[tokio::test]
+async fn list_dir() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/hello.txt", b"A", Utc::now()).unwrap();
+    writer.append_file("/one/a.txt", b"B", Utc::now()).unwrap();
+    writer
+        .append_file("/one/three/b.txt", b"C", Utc::now())
+        .unwrap();
+    writer.append_file("/two/c.txt", b"D", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    let reader = IsoFileReader::read(Cursor::new(buffer.into_inner()))
+        .await
+        .unwrap();
+
+    let root = reader.entries().list_dir(Path::new("/")).unwrap();
+    let names: Vec<&str> = root.iter().map(|t| t.0.as_str()).collect();
+    assert_eq!(names, ["HELLO.TXT", "ONE", "TWO"]);
+
+    let one = reader.entries().list_dir(Path::new("/ONE")).unwrap();
+    assert!(one.iter().any(|t| t.0 == "THREE" && t.1.is_directory()));
+    assert!(reader.entries().list_dir(Path::new("/HELLO.TXT")).is_none());
+}
+
+#[tokio::test]
+async fn walk_entries() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
         .await
         .unwrap();
 
-    writer.append_file("/hello.txt", b"Hello, World!", Utc::now());
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.append_file("/dir/b.txt", b"B", Utc::now()).unwrap();
+    writer
+        .append_file("/dir/sub/c.txt", b"C", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    buffer.set_position(0);
+    let reader = IsoFileReader::read(&mut buffer).await.unwrap();
 
-    writer.append_file("/one/hello2.txt", b"Hello, World!", Utc::now());
-    writer.append_file("/one/hello3.txt", b"Hello, World!", Utc::now());
+    let paths: Vec<PathBuf> = reader.entries().walk(true).map(|t| t.0).collect();
+    assert_eq!(
+        paths,
+        ["/A.TXT", "/DIR", "/DIR/B.TXT", "/DIR/SUB", "/DIR/SUB/C.TXT"].map(PathBuf::from)
+    );
+
+    let all = reader.entries().walk(false).count();
+    assert_eq!(all, paths.len() + 4);
+}
 
-    writer.append_file("/one/three/hello8.txt", b"Hello, World!", Utc::now());
-    writer.append_file("/one/three/hello9.txt", b"Hello, World!", Utc::now());
+#[tokio::test]
+async fn stream_entries() {
+    let mut buffer = Cursor::new(Vec::new());
 
-    writer.append_file("/two/hello4.txt", b"Hello, World!", Utc::now());
-    writer.append_file("/two/hellowaka.txt", b"Hello, Worldx!", Utc::now());
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
 
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.append_file("/dir/b.txt", b"B", Utc::now()).unwrap();
+    writer
+        .append_file("/dir/sub/c.txt", b"C", Utc::now())
+        .unwrap();
     writer.close().await.unwrap();
 
-    let mut buffer2 = File::open("image2.iso").await.unwrap();
+    let entries: Vec<(PathBuf, IsoDirectoryEntry)> = IsoFileReader::stream_entries(buffer)
+        .try_collect()
+        .await
+        .unwrap();
+
+    let files: Vec<&Path> = entries
+        .iter()
+        .filter(|t| t.1.entry().is_file())
+        .map(|t| t.0.as_path())
+        .collect();
+
+    assert_eq!(
+        files,
+        ["/A.TXT", "/DIR/B.TXT", "/DIR/SUB/C.TXT"].map(Path::new)
+    );
+}
+
+#[tokio::test]
+async fn open_file_handle() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/first.txt", b"0123456789", Utc::now())
+        .unwrap();
+    writer
+        .append_file("/second.txt", b"abcdefghij", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    buffer.set_position(0);
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+
+    let mut handle = reader.open_file("/SECOND.TXT").await.unwrap();
+    assert_eq!(handle.len(), 10);
+
+    handle.seek(SeekFrom::Start(4)).await.unwrap();
+    let mut content = String::new();
+    handle.read_to_string(&mut content).await.unwrap();
+    assert_eq!(content, "efghij");
+
+    handle.seek(SeekFrom::End(-3)).await.unwrap();
+    let mut tail = [0u8; 8];
+    let read = handle.read(&mut tail).await.unwrap();
+    assert_eq!(&tail[..read], b"hij");
+
+    assert!(handle.seek(SeekFrom::Current(-20)).await.is_err());
+}
+
+#[tokio::test]
+async fn file_metadata() {
+    let mut reader = IsoFileReader::read(one_file_image().await).await.unwrap();
+
+    let metadata = reader.metadata("/A.TXT").await.unwrap();
+    assert_eq!(metadata.size, 1);
+    assert!(!metadata.is_dir && !metadata.hidden);
+    assert!(metadata.datetime.is_some());
+    assert!(reader.metadata("/").await.unwrap().is_dir);
+    assert!(matches!(
+        reader.metadata("/MISSING.TXT").await,
+        Err(IsoFileError::FileNotFound)
+    ));
+}
+
+#[tokio::test]
+async fn path_queries() {
+    let mut reader = IsoFileReader::read(one_file_image().await).await.unwrap();
+
+    assert!(reader.exists("/A.TXT").await && reader.is_file("/A.TXT").await);
+    assert!(reader.is_dir("/").await && !reader.is_file("/").await);
+    assert!(!reader.exists("/MISSING.TXT").await && !reader.is_dir("/MISSING.TXT").await);
+}
+
+#[tokio::test]
+async fn read_into_buffers() {
+    let mut reader = IsoFileReader::read(one_file_image().await).await.unwrap();
+
+    let mut content = b"previous content".to_vec();
+    reader.read_file_into("/A.TXT", &mut content).await.unwrap();
+    assert_eq!(content, b"A");
+
+    let mut slice = [0u8; 12];
+    let read = reader.read_file_to_slice("/A.TXT", &mut slice).await;
+    assert_eq!(&slice[..read.unwrap()], b"A");
+    assert!(matches!(
+        reader.read_file_to_slice("/A.TXT", &mut []).await,
+        Err(IsoFileError::BufferTooSmall(1))
+    ));
+}
+
+#[tokio::test]
+async fn file_stream_chunks() {
+    let content: Vec<u8> = (0..5000u32).map(|t| t as u8).collect();
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/data.bin", &content, Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+
+    let chunks: Vec<Bytes> = reader
+        .file_stream("/DATA.BIN")
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+
+    let sizes: Vec<usize> = chunks.iter().map(|t| t.len()).collect();
+    assert_eq!(sizes, [2048, 2048, 904]);
+    assert_eq!(chunks.concat(), content);
+
+    // the owning stream outlives the reader, so it can move to another task
+    let stream = reader.into_file_stream("/DATA.BIN").await.unwrap();
+    let chunks = tokio::spawn(stream.try_collect::<Vec<Bytes>>());
+    assert_eq!(chunks.await.unwrap().unwrap().concat(), content);
+}
+
+#[tokio::test]
+async fn file_checksums() {
+    let content: Vec<u8> = (0..40000u32).map(|t| t as u8).collect();
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/dir/data.bin", &content, Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+
+    let md5 = reader
+        .checksum_file("/DIR/DATA.BIN", ChecksumAlgorithm::Md5)
+        .await
+        .unwrap();
+    assert_eq!(md5, Md5::digest(&content).to_vec());
+
+    let sha256 = reader
+        .checksum_file("/DIR/DATA.BIN", ChecksumAlgorithm::Sha256)
+        .await
+        .unwrap();
+    assert_eq!(sha256, Sha256::digest(&content).to_vec());
+
+    assert!(matches!(
+        reader.checksum_file("/DIR", ChecksumAlgorithm::Md5).await,
+        Err(IsoFileError::EntryDirectory)
+    ));
+    assert!(matches!(
+        reader
+            .checksum_file("/DIR/MISSING.BIN", ChecksumAlgorithm::Md5)
+            .await,
+        Err(IsoFileError::FileNotFound)
+    ));
+}
+
+#[tokio::test]
+async fn lazy_loading() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.append_file("/dir/b.txt", b"B", Utc::now()).unwrap();
+    writer
+        .append_file("/dir/sub/c.txt", b"C", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut options = IsoReadOptions::default();
+    options.set_lazy(true);
+
+    let mut reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+
+    assert!(reader.is_dir("/DIR").await && reader.entries().is_pending(Path::new("/DIR")));
+
+    // looking a path up loads the directories on the way
+    assert!(reader.exists("/DIR/B.TXT").await);
+    assert!(!reader.entries().is_pending(Path::new("/DIR")));
+    assert!(!reader.exists("/DIR/MISSING.TXT").await);
+    assert_eq!(reader.metadata("/DIR/SUB/C.TXT").await.unwrap().size, 1);
+
+    assert_eq!(reader.read_file("/DIR/SUB/C.TXT").await.unwrap(), b"C");
+
+    reader.load_all().await.unwrap();
+    assert_eq!(reader.entries().pending().count(), 0);
+}
+
+#[tokio::test]
+async fn path_table_lookup() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.append_file("/dir/b.txt", b"B", Utc::now()).unwrap();
+    writer
+        .append_file("/dir/sub/c.txt", b"C", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut options = IsoReadOptions::default();
+    options.set_path_table_lookup(true);
+
+    let mut reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+
+    let paths = reader.path_table().paths();
+    assert_eq!(paths.len(), 3);
+    assert_eq!(paths[2].0, Path::new("/DIR/SUB"));
+
+    assert_eq!(reader.read_file("/DIR/SUB/C.TXT").await.unwrap(), b"C");
+    assert!(reader.entries().is_pending(Path::new("/DIR")));
+    assert!(reader.is_file("/DIR/B.TXT").await);
+    assert!(!reader.entries().is_pending(Path::new("/DIR")));
+
+    reader.load_all().await.unwrap();
+    assert_eq!(reader.entries().pending().count(), 0);
+    assert!(reader.is_dir("/DIR/SUB").await && reader.is_file("/DIR/B.TXT").await);
+}
+
+#[tokio::test]
+async fn volume_stats() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer
+        .append_file("/big/data.bin", &[7; 5000], Utc::now())
+        .unwrap();
+    writer
+        .append_file("/x/y/z/deep.txt", b"DEEP", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut options = IsoReadOptions::default();
+    options.set_lazy(true);
+
+    let mut reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+
+    // only the root has been read, its subdirectories are still pending
+    let stats = reader.stats();
+    assert_eq!((stats.files, stats.directories), (1, 2));
+
+    reader.load_all().await.unwrap();
+
+    let stats = reader.stats();
+    assert_eq!(
+        (stats.files, stats.directories, stats.total_size),
+        (3, 4, 5005)
+    );
+    assert_eq!(stats.deepest_path.unwrap(), Path::new("/X/Y/Z/DEEP.TXT"));
+    assert_eq!(
+        stats.largest_file.unwrap(),
+        (PathBuf::from("/BIG/DATA.BIN"), 5000)
+    );
+}
+
+// image holding only /A.TXT with "A", for tests patching its sectors
+async fn one_file_image() -> Cursor<Vec<u8>> {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    buffer
+}
+
+#[tokio::test]
+async fn read_dir_raw() {
+    let mut buffer = one_file_image().await;
+
+    let location = IsoFileReader::read(buffer.clone())
+        .await
+        .unwrap()
+        .metadata("/A.TXT")
+        .await
+        .unwrap()
+        .location;
+
+    // a system use area the reader does not interpret, kept in the raw bytes
+    let system_use = susp_entry(b"XY", b"vendor");
+    let records = [
+        directory_record(23, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(location, 1, 0, b"A.TXT;1", &system_use),
+    ];
+    let sector = directory_sector(&records);
+    buffer.get_mut()[23 * 2048..24 * 2048].copy_from_slice(&sector);
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let raw = reader.read_dir_raw("/").await.unwrap();
+    assert_eq!(raw, sector);
+
+    let third = records[0].len() + records[1].len();
+    let record = &raw[third..third + records[2].len()];
+    assert!(record.ends_with(&system_use));
+
+    assert!(matches!(
+        reader.read_dir_raw("/A.TXT").await,
+        Err(IsoFileError::NotDirectory)
+    ));
+    assert!(matches!(
+        reader.read_dir_raw("/MISSING").await,
+        Err(IsoFileError::FileNotFound)
+    ));
+}
+
+#[tokio::test]
+async fn capabilities() {
+    let buffer = one_file_image().await;
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(
+        reader.capabilities().await.unwrap(),
+        IsoCapabilities::default()
+    );
+
+    // the tree is already parsed, reuse blocks 17 to 22 for other descriptors
+    let image = reader.get_mut().get_mut();
+    let descriptor = |block: usize| block * 2048..(block + 1) * 2048;
+    image.copy_within(descriptor(17), 19 * 2048);
+
+    image[descriptor(17)].fill(0);
+    image[17 * 2048..17 * 2048 + 30].copy_from_slice(b"\0CD001\x01EL TORITO SPECIFICATION");
+
+    image[descriptor(18)].fill(0);
+    image[18 * 2048..18 * 2048 + 7].copy_from_slice(b"\x02CD001\x01");
+    image[18 * 2048 + 88..18 * 2048 + 91].copy_from_slice(b"%/E");
+
+    for (block, id) in [(20, b"BEA01"), (21, b"NSR02"), (22, b"TEA01")] {
+        image[descriptor(block)].fill(0);
+        image[block * 2048 + 1..block * 2048 + 6].copy_from_slice(id);
+    }
+
+    // root "." record with SP and ER entries in its system use area
+    let root = descriptor(23);
+    let used = image[root.clone()].iter().rposition(|&t| t != 0).unwrap() + 2;
+    let mut sector = image[root.start..root.start + 34].to_vec();
+    sector[0] = 60;
+    sector.extend_from_slice(b"SP\x07\x01\xbe\xef\x00");
+    sector.extend_from_slice(b"ER\x12\x01\x0a\x00\x00\x01RRIP_1991A\x00");
+    sector.extend_from_slice(&image[root.start + 34..root.start + used]);
+    image[root.start..root.start + sector.len()].copy_from_slice(&sector);
+
+    let capabilities = reader.capabilities().await.unwrap();
+    assert!(capabilities.joliet && capabilities.rock_ridge);
+    assert!(capabilities.el_torito && capabilities.udf);
+
+    let types = reader
+        .volume_descriptors()
+        .await
+        .unwrap()
+        .iter()
+        .map(VolumeDescriptor::descriptor_type)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        types,
+        [
+            VolumeDescriptorType::Primary,
+            VolumeDescriptorType::BootRecord,
+            VolumeDescriptorType::Supplementary,
+            VolumeDescriptorType::Terminator
+        ]
+    );
+}
+
+// multiplies the both-endian block number at `at`
+fn scale_block(bytes: &mut [u8], at: usize, factor: u32) {
+    let block = u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) * factor;
+
+    bytes[at..at + 4].copy_from_slice(&block.to_le_bytes());
+    bytes[at + 4..at + 8].copy_from_slice(&block.to_be_bytes());
+}
+
+#[tokio::test]
+async fn small_logical_blocks() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"AAAA", Utc::now()).unwrap();
+    writer.append_file("/b.txt", b"BBBB", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    // same image addressed with 512 byte blocks
+    let image = buffer.get_mut();
+    let pvd = 0x8000;
+
+    image[pvd + 128..pvd + 130].copy_from_slice(&512u16.to_le_bytes());
+    image[pvd + 130..pvd + 132].copy_from_slice(&512u16.to_be_bytes());
+    scale_block(image, pvd + 80, 4);
+    scale_block(image, pvd + 158, 4);
+
+    let l_table = u32::from_le_bytes(image[pvd + 140..pvd + 144].try_into().unwrap()) * 4;
+    image[pvd + 140..pvd + 144].copy_from_slice(&l_table.to_le_bytes());
+
+    let mut record = 23 * 2048;
+    while image[record] != 0 {
+        scale_block(image, record + 2, 4);
+        record += usize::from(image[record]);
+    }
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    assert_eq!(reader.header().logical_block_size, 512);
+    assert_eq!(reader.read_file("/B.TXT").await.unwrap(), b"BBBB");
+
+    buffer.get_mut()[pvd + 128..pvd + 130].copy_from_slice(&1000u16.to_le_bytes());
+    assert!(matches!(
+        IsoFileReader::read(buffer).await,
+        Err(IsoFileError::InvalidLogicalBlockSize(1000))
+    ));
+}
+
+#[tokio::test]
+async fn directory_extent_bounds() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.append_file("/b.txt", b"B", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    // shrink the root extent in the PVD so it ends right after "A.TXT"
+    let image = buffer.get_mut();
+    let root = 23 * 2048;
+    let dot = usize::from(image[root]);
+    let length = dot + usize::from(image[root + dot]) + usize::from(image[root + 2 * dot]);
+    image[0x8000 + 166..0x8000 + 170].copy_from_slice(&(length as u32).to_le_bytes());
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    assert!(reader.exists("/A.TXT").await && !reader.exists("/B.TXT").await);
+
+    let paths = IsoFileReader::stream_entries(buffer)
+        .map_ok(|(path, _)| path)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(paths.last().unwrap(), Path::new("/A.TXT"));
+}
+
+#[tokio::test]
+async fn multi_sector_directory() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    for i in 0..100 {
+        writer
+            .append_file(&format!("/file_{i:02}.txt"), b"data", Utc::now())
+            .unwrap();
+        writer
+            .append_file(&format!("/dir/file_{i:02}.txt"), b"data", Utc::now())
+            .unwrap();
+    }
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert!(reader.metadata("/").await.unwrap().size > 2048);
+    assert_eq!(
+        reader.entries().list_dir(Path::new("/")).unwrap().len(),
+        101
+    );
+    assert_eq!(
+        reader.entries().list_dir(Path::new("/DIR")).unwrap().len(),
+        100
+    );
+}
+
+#[tokio::test]
+async fn truncated_recovery() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/big.bin", &[7u8; 5000], Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let image = buffer.into_inner();
+    let mut reader = IsoFileReader::read(Cursor::new(image.clone()))
+        .await
+        .unwrap();
+
+    // cut the image in the middle of the last sector of the file
+    let location = reader.metadata("/BIG.BIN").await.unwrap().location as usize;
+    let cut = location * 2048 + 4096 + 100;
+    reader.get_mut().get_mut().truncate(cut);
+
+    assert!(matches!(
+        reader.read_file("/BIG.BIN").await,
+        Err(IsoFileError::StdIo(_))
+    ));
+
+    let mut content = Vec::new();
+    let mut handle = reader.open_file("/BIG.BIN").await.unwrap();
+    assert!(handle.read_to_end(&mut content).await.is_err());
+
+    reader.set_recovery(true);
+    match reader.read_file("/BIG.BIN").await {
+        Err(IsoFileError::Truncated { data, expected }) => {
+            assert_eq!((data.len(), expected), (4196, 5000));
+            assert!(data.iter().all(|&t| t == 7));
+        }
+        other => panic!("unexpected result {other:?}"),
+    }
+
+    // handles, streams and slices get the same part
+    let mut content = Vec::new();
+    let mut handle = reader.open_file("/BIG.BIN").await.unwrap();
+    handle.read_to_end(&mut content).await.unwrap();
+    assert_eq!((content.len(), handle.position()), (4196, 4196));
+
+    let chunks: Vec<_> = reader
+        .file_stream("/BIG.BIN")
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(
+        chunks
+            .iter()
+            .map(|t| t.as_ref().map_or(0, |t| t.len()))
+            .sum::<usize>(),
+        4196
+    );
+    assert!(matches!(
+        chunks.last(),
+        Some(Err(IsoFileError::Truncated { expected: 5000, .. }))
+    ));
+
+    let mut slice = [0u8; 5000];
+    assert!(matches!(
+        reader.read_file_to_slice("/BIG.BIN", &mut slice).await,
+        Err(IsoFileError::Truncated { data, .. }) if data.len() == 4196
+    ));
+}
+
+#[tokio::test]
+async fn resource_limits() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/big.bin", &[0u8; 4096], Utc::now())
+        .unwrap();
+    writer
+        .append_file("/a/b/c/d.txt", b"D", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut options = IsoReadOptions::default();
+    options.set_max_allocation(1024);
+
+    let mut reader = IsoFileReader::read_with_options(buffer.clone(), options)
+        .await
+        .unwrap();
+    assert!(matches!(
+        reader.read_file("/BIG.BIN").await,
+        Err(IsoFileError::AllocationLimit(4096))
+    ));
+    assert_eq!(reader.read_file("/A/B/C/D.TXT").await.unwrap(), b"D");
+
+    let mut options = IsoReadOptions::default();
+    options.set_max_entries(5);
+    assert!(matches!(
+        IsoFileReader::read_with_options(buffer.clone(), options).await,
+        Err(IsoFileError::EntryLimit(5))
+    ));
+
+    let mut options = IsoReadOptions::default();
+    options.set_max_depth(2);
+    assert!(matches!(
+        IsoFileReader::read_with_options(buffer.clone(), options).await,
+        Err(IsoFileError::DepthLimit(2))
+    ));
+
+    // a directory pointing back at the root
+    let image = buffer.get_mut();
+    let root = 23 * 2048;
+    let mut dir = root;
+    while &image[dir + 32..dir + 34] != b"\x01A" {
+        dir += usize::from(image[dir]);
+    }
+    image.copy_within(root + 2..root + 18, dir + 2);
+
+    assert!(matches!(
+        IsoFileReader::read(buffer).await,
+        Err(IsoFileError::DepthLimit(255) | IsoFileError::EntryLimit(_))
+    ));
+
+    // a file declaring 1 GiB fails under the default limit
+    let mut buffer = one_file_image().await;
+    let image = buffer.get_mut();
+    let record = image.windows(5).position(|t| t == b"A.TXT").unwrap() - 33;
+    image[record + 10..record + 14].copy_from_slice(&(1u32 << 30).to_le_bytes());
+    image[record + 14..record + 18].copy_from_slice(&(1u32 << 30).to_be_bytes());
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert!(matches!(
+        reader.read_file("/A.TXT").await,
+        Err(IsoFileError::AllocationLimit(0x4000_0000))
+    ));
+}
+
+// raw directory record with an optional system use area
+fn directory_record(
+    location: u32,
+    length: u32,
+    flags: u8,
+    id: &[u8],
+    system_use: &[u8],
+) -> Vec<u8> {
+    let mut record = vec![0u8; 33];
+
+    record[2..6].copy_from_slice(&location.to_le_bytes());
+    record[6..10].copy_from_slice(&location.to_be_bytes());
+    record[10..14].copy_from_slice(&length.to_le_bytes());
+    record[14..18].copy_from_slice(&length.to_be_bytes());
+    record[18..25].copy_from_slice(&[124, 1, 1, 0, 0, 0, 0]);
+    record[25] = flags;
+    record[28..32].copy_from_slice(&[1, 0, 0, 1]);
+    record[32] = id.len() as u8;
+
+    record.extend_from_slice(id);
+
+    if id.len().is_multiple_of(2) {
+        record.push(0);
+    }
+
+    record.extend_from_slice(system_use);
+    record[0] = record.len() as u8;
+
+    record
+}
+
+// Joliet descriptor in place of the terminator at block 17, which moves on
+fn joliet_descriptor(image: &mut [u8], root: u32, path_table: u32) {
+    let svd = 17 * 2048;
+    image.copy_within(svd..svd + 2048, 18 * 2048);
+    image.copy_within(16 * 2048..17 * 2048, svd);
+    image[svd] = 2;
+    image[svd + 88..svd + 91].copy_from_slice(b"%/E");
+    image[svd + 140..svd + 144].copy_from_slice(&path_table.to_le_bytes());
+    image[svd + 158..svd + 162].copy_from_slice(&root.to_le_bytes());
+}
+
+fn ucs2(name: &str) -> Vec<u8> {
+    name.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+// directory extent of one sector holding `records`
+fn directory_sector(records: &[Vec<u8>]) -> Vec<u8> {
+    let mut sector = records.concat();
+    sector.resize(2048, 0);
+    sector
+}
+
+#[tokio::test]
+async fn joliet_names() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/readme.txt", b"R", Utc::now()).unwrap();
+    writer
+        .append_file("/docs/guide.txt", b"G", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let readme = reader.metadata("/README.TXT").await.unwrap().location;
+    let guide = reader.metadata("/DOCS/GUIDE.TXT").await.unwrap().location;
+
+    // Joliet tree and path table appended after the primary volume
+    let image = buffer.get_mut();
+    let root = (image.len() / 2048) as u32;
+
+    image.extend(directory_sector(&[
+        directory_record(root, 2048, 2, &[0], &[]),
+        directory_record(root, 2048, 2, &[1], &[]),
+        directory_record(readme, 1, 0, &ucs2("ReadMe Long Name.txt;1"), &[]),
+        directory_record(root + 1, 2048, 2, &ucs2("Docs"), &[]),
+    ]));
+    image.extend(directory_sector(&[
+        directory_record(root + 1, 2048, 2, &[0], &[]),
+        directory_record(root, 2048, 2, &[1], &[]),
+        directory_record(guide, 1, 0, &ucs2("Guide \u{fc}.txt;1"), &[]),
+    ]));
+
+    let mut path_table = vec![1, 0];
+    path_table.extend(root.to_le_bytes());
+    path_table.extend([1, 0, 0, 0, 8, 0]);
+    path_table.extend((root + 1).to_le_bytes());
+    path_table.extend([1, 0]);
+    path_table.extend(ucs2("Docs"));
+    path_table.resize(2048, 0);
+    image.extend(path_table);
+
+    joliet_descriptor(image, root, root + 2);
+
+    let mut options = IsoReadOptions::default();
+    options.set_paired_names(true);
+
+    let reader = IsoFileReader::read_with_options(buffer.clone(), options.clone())
+        .await
+        .unwrap();
+    let readme = reader.entries().get(Path::new("/README.TXT")).unwrap();
+    assert_eq!(readme.joliet_name(), Some("ReadMe Long Name.txt"));
+
+    // not read unless asked for
+    let reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let readme = reader.entries().get(Path::new("/README.TXT")).unwrap();
+    assert_eq!(readme.joliet_name(), None);
+
+    options.set_joliet(true);
+
+    let mut reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+
+    let names = reader
+        .entries()
+        .walk(true)
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            Path::new("/Docs"),
+            Path::new("/Docs/Guide \u{fc}.txt"),
+            Path::new("/ReadMe Long Name.txt")
+        ]
+    );
+
+    assert_eq!(
+        reader.read_file("/ReadMe Long Name.txt").await.unwrap(),
+        b"R"
+    );
+    assert_eq!(
+        reader.read_file("/Docs/Guide \u{fc}.txt").await.unwrap(),
+        b"G"
+    );
+    assert_eq!(reader.path_table().paths()[1].0, Path::new("/Docs"));
+
+    let primary_names = ["/Docs", "/Docs/Guide \u{fc}.txt", "/ReadMe Long Name.txt"]
+        .map(|t| reader.entries().get(Path::new(t)).unwrap().primary_name());
+    assert_eq!(
+        primary_names,
+        [Some("DOCS"), Some("GUIDE.TXT"), Some("README.TXT")]
+    );
+}
+
+#[tokio::test]
+async fn paired_directory_names() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.set_interchange_level(InterchangeLevel::Level1);
+
+    let alpha = writer
+        .append_file("/longname_alpha/x.txt", b"X", Utc::now())
+        .unwrap();
+    let beta = writer
+        .append_file("/longname_beta/y.txt", b"Y", Utc::now())
+        .unwrap();
+    writer.create_dir("/empty", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    // truncated names alike, told apart by the files below them
+    let (alpha, beta) = (alpha.parent().unwrap(), beta.parent().unwrap());
+    assert_eq!(&alpha.to_str().unwrap()[..7], &beta.to_str().unwrap()[..7]);
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let x = reader.metadata(alpha.join("X.TXT")).await.unwrap().location;
+    let y = reader.metadata(beta.join("Y.TXT")).await.unwrap().location;
+
+    // Joliet tree listing the directories the other way round
+    let image = buffer.get_mut();
+    let root = (image.len() / 2048) as u32;
+
+    image.extend(directory_sector(&[
+        directory_record(root, 2048, 2, &[0], &[]),
+        directory_record(root, 2048, 2, &[1], &[]),
+        directory_record(root + 3, 2048, 2, &ucs2("Empty"), &[]),
+        directory_record(root + 1, 2048, 2, &ucs2("longname_beta"), &[]),
+        directory_record(root + 2, 2048, 2, &ucs2("longname_alpha"), &[]),
+    ]));
+
+    for (location, file, id) in [(root + 1, y, "y.txt;1"), (root + 2, x, "x.txt;1")] {
+        image.extend(directory_sector(&[
+            directory_record(location, 2048, 2, &[0], &[]),
+            directory_record(root, 2048, 2, &[1], &[]),
+            directory_record(file, 1, 0, &ucs2(id), &[]),
+        ]));
+    }
+
+    image.extend(directory_sector(&[
+        directory_record(root + 3, 2048, 2, &[0], &[]),
+        directory_record(root, 2048, 2, &[1], &[]),
+    ]));
+
+    let mut path_table = vec![1, 0];
+    path_table.extend(root.to_le_bytes());
+    path_table.extend([1, 0, 0, 0]);
+    path_table.resize(2048, 0);
+    image.extend(path_table);
+
+    joliet_descriptor(image, root, root + 4);
+
+    let mut options = IsoReadOptions::default();
+    options.set_paired_names(true);
+
+    let reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+    let joliet_name = |path: &Path| reader.entries().get(path).unwrap().joliet_name();
+
+    assert_eq!(joliet_name(alpha), Some("longname_alpha"));
+    assert_eq!(joliet_name(beta), Some("longname_beta"));
+    assert_eq!(joliet_name(&alpha.join("X.TXT")), Some("x.txt"));
+    assert_eq!(joliet_name(Path::new("/EMPTY")), Some("Empty"));
+}
+
+#[tokio::test]
+async fn enhanced_names() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/readme.txt", b"R", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let readme = reader.metadata("/README.TXT").await.unwrap().location;
+
+    // enhanced tree appended after the primary volume
+    let image = buffer.get_mut();
+    let root = (image.len() / 2048) as u32;
+    let long_name = "readme with a rather long name, well past level 1.txt";
+
+    image.extend(directory_sector(&[
+        directory_record(root, 2048, 2, &[0], &[]),
+        directory_record(root, 2048, 2, &[1], &[]),
+        directory_record(readme, 1, 0, long_name.as_bytes(), &[]),
+    ]));
+
+    let svd = 17 * 2048;
+    image.copy_within(svd..svd + 2048, 18 * 2048);
+    image.copy_within(16 * 2048..17 * 2048, svd);
+    image[svd] = 2;
+    image[svd + 6] = 2;
+    image[svd + 881] = 2;
+    image[svd + 158..svd + 162].copy_from_slice(&root.to_le_bytes());
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    assert!(reader.exists("/README.TXT").await);
+    assert!(reader.capabilities().await.unwrap().enhanced);
+
+    let mut options = IsoReadOptions::default();
+    options.set_enhanced(true);
+    options.set_paired_names(true);
+
+    let mut reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+
+    let path = PathBuf::from("/").join(long_name);
+    assert_eq!(reader.read_file(&path).await.unwrap(), b"R");
+
+    let entry = reader.entries().get(&path).unwrap();
+    assert_eq!(entry.primary_name(), Some("README.TXT"));
+}
+
+fn susp_entry(signature: &[u8; 2], data: &[u8]) -> Vec<u8> {
+    let mut entry = signature.to_vec();
+    entry.extend([data.len() as u8 + 4, 1]);
+    entry.extend_from_slice(data);
+    entry
+}
+
+#[tokio::test]
+async fn rock_ridge_names() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/readme.txt", b"R", Utc::now()).unwrap();
+    writer
+        .append_file("/docs/guide.txt", b"G", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let location = |path: &str| {
+        reader
+            .entries()
+            .get(Path::new(path))
+            .unwrap()
+            .metadata()
+            .location
+    };
+    let (readme, docs, guide) = (
+        location("/README.TXT"),
+        location("/DOCS"),
+        location("/DOCS/GUIDE.TXT"),
+    );
+
+    let sp = [
+        susp_entry(b"SP", &[0xbe, 0xef, 0]),
+        susp_entry(b"ER", b"\x0a\x00\x00\x01RRIP_1991A"),
+    ]
+    .concat();
+    let px = susp_entry(
+        b"PX",
+        &[0o100755u32, 1, 1000, 100]
+            .into_iter()
+            .flat_map(|t| [t.to_le_bytes(), t.to_be_bytes()].concat())
+            .collect::<Vec<_>>(),
+    );
+    let tf = susp_entry(
+        b"TF",
+        &[&[0x86][..], b"2024031512304550\0", b"2024031613000000\0"].concat(),
+    );
+    let nm = |flags: u8, name: &str| susp_entry(b"NM", &[&[flags], name.as_bytes()].concat());
+
+    // the rest of the readme name is in a continuation area past the image
+    let continuation = buffer.get_ref().len() as u32 / 2048;
+    let ce = susp_entry(
+        b"CE",
+        &[continuation, 16, 64]
+            .into_iter()
+            .flat_map(|t| [t.to_le_bytes(), t.to_be_bytes()].concat())
+            .collect::<Vec<_>>(),
+    );
+
+    let root = directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &sp),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(docs, 2048, 2, b"DOCS", &nm(0, "docs")),
+        directory_record(
+            readme,
+            1,
+            0,
+            b"README.TXT;1",
+            &[nm(1, "ReadMe."), ce.clone()].concat(),
+        ),
+    ]);
+    let docs_sector = directory_sector(&[
+        directory_record(docs, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(
+            guide,
+            1,
+            0,
+            b"GUIDE.TXT;1",
+            &[nm(0, "guide.md"), px.clone(), tf.clone()].concat(),
+        ),
+    ]);
+
+    let image = buffer.get_mut();
+    image[23 * 2048..24 * 2048].copy_from_slice(&root);
+    image[docs as usize * 2048..(docs as usize + 1) * 2048].copy_from_slice(&docs_sector);
+    image.extend(directory_sector(&[vec![0; 16], nm(0, "txt")]));
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    assert_eq!(reader.read_file("/ReadMe.txt").await.unwrap(), b"R");
+    assert_eq!(reader.read_file("/docs/guide.md").await.unwrap(), b"G");
+
+    let entry = reader.entries().get(Path::new("/docs/guide.md")).unwrap();
+    assert_eq!(entry.rock_ridge_name(), Some("guide.md"));
+    assert_eq!(entry.primary_name(), Some("GUIDE.TXT"));
+
+    let posix = entry.posix_attributes().unwrap();
+    assert_eq!(
+        (posix.mode, posix.uid, posix.gid, posix.serial),
+        (0o100755, 1000, 100, None)
+    );
+
+    let modified = Utc.with_ymd_and_hms(2024, 3, 15, 12, 30, 45).unwrap()
+        + chrono::Duration::milliseconds(500);
+    let times = entry.rock_ridge_times();
+    assert_eq!((times.created, times.modified), (None, Some(modified)));
+    assert_eq!(
+        times.accessed,
+        Utc.with_ymd_and_hms(2024, 3, 16, 13, 0, 0).single()
+    );
+    assert_eq!(entry.metadata().datetime, Some(modified));
+
+    assert!(reader.capabilities().await.unwrap().rock_ridge);
+
+    let mut options = IsoReadOptions::default();
+    options.set_rock_ridge(false);
+
+    let mut reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+    assert!(reader.exists("/DOCS/GUIDE.TXT").await);
+    assert_eq!(
+        reader.entries().get(Path::new("/")).unwrap().system_use(),
+        sp
+    );
+}
+
+#[tokio::test]
+async fn rock_ridge_symlinks() {
+    let mut buffer = one_file_image().await;
+
+    let sp = [
+        susp_entry(b"SP", &[0xbe, 0xef, 0]),
+        susp_entry(b"ER", b"\x0a\x00\x00\x01RRIP_1991A"),
+    ]
+    .concat();
+
+    let image = buffer.get_mut();
+    let a = image.windows(5).position(|t| t == b"A.TXT").unwrap() - 33;
+    let location = u32::from_le_bytes(image[a + 2..a + 6].try_into().unwrap());
+
+    // a link split over two SL entries
+    image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &sp),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(location, 1, 0, b"A.TXT;1", &[]),
+        directory_record(
+            location,
+            0,
+            0,
+            b"LINK;1",
+            &[
+                susp_entry(b"SL", b"\x01\x08\x00\x00\x03usr\x01\x03sha"),
+                susp_entry(b"SL", b"\x00\x00\x02re\x04\x00\x00\x04bash"),
+            ]
+            .concat(),
+        ),
+    ]));
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let link = reader.entries().get(Path::new("/LINK")).unwrap();
+    assert_eq!(link.symlink_target(), Some(Path::new("/usr/share/../bash")));
+    assert!(link.metadata().is_symlink);
+    assert!(!reader.metadata("/A.TXT").await.unwrap().is_symlink);
+}
+
+#[tokio::test]
+async fn continuation_bounds() {
+    let mut buffer = one_file_image().await;
+    let continuation = buffer.get_ref().len() as u32 / 2048;
+
+    let sp = [
+        susp_entry(b"SP", &[0xbe, 0xef, 0]),
+        susp_entry(b"ER", b"\x0a\x00\x00\x01RRIP_1991A"),
+    ]
+    .concat();
+    let nm = |flags: u8, name: &str| susp_entry(b"NM", &[&[flags], name.as_bytes()].concat());
+    let ce = |offset: u32| {
+        susp_entry(
+            b"CE",
+            &[continuation, offset, 64]
+                .into_iter()
+                .flat_map(|t| [t.to_le_bytes(), t.to_be_bytes()].concat())
+                .collect::<Vec<_>>(),
+        )
+    };
+
+    let image = buffer.get_mut();
+    let a = image.windows(5).position(|t| t == b"A.TXT").unwrap() - 33;
+    let location = u32::from_le_bytes(image[a + 2..a + 6].try_into().unwrap());
+
+    let mut area = vec![0; 2048];
+    area[16..24].copy_from_slice(&nm(0, "txt"));
+    area[2000..2008].copy_from_slice(&nm(0, "txt"));
+    image.extend(area);
+
+    // an area crossing the end of its block is not followed
+    for (offset, name) in [(16, "/Alpha.txt"), (2000, "/Alpha.")] {
+        let image = buffer.get_mut();
+        image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+            directory_record(23, 2048, 2, &[0], &sp),
+            directory_record(23, 2048, 2, &[1], &[]),
+            directory_record(
+                location,
+                1,
+                0,
+                b"A.TXT;1",
+                &[nm(1, "Alpha."), ce(offset)].concat(),
+            ),
+        ]));
+
+        let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+        assert_eq!(reader.read_file(name).await.unwrap(), b"A");
+    }
+}
+
+#[tokio::test]
+async fn rock_ridge_relocation() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a/b", b"", Utc::now()).unwrap();
+    writer
+        .append_file("/rr_moved/b/deep.txt", b"D", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let location = |path: &str| {
+        reader
+            .entries()
+            .get(Path::new(path))
+            .unwrap()
+            .metadata()
+            .location
+    };
+    let (a, placeholder, moved, b) = (
+        location("/A"),
+        location("/A/B"),
+        location("/RR_MOVED"),
+        location("/RR_MOVED/B"),
+    );
+
+    let sp = susp_entry(b"SP", &[0xbe, 0xef, 0]);
+    let root = directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &sp),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(a, 2048, 2, b"A", &[]),
+        directory_record(moved, 2048, 2, b"RR_MOVED", &[]),
+    ]);
+    let a_sector = directory_sector(&[
+        directory_record(a, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(
+            placeholder,
+            0,
+            0,
+            b"B;1",
+            &susp_entry(b"CL", &[b.to_le_bytes(), b.to_be_bytes()].concat()),
+        ),
+    ]);
+    let moved_sector = directory_sector(&[
+        directory_record(moved, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(b, 2048, 2, b"B", &susp_entry(b"RE", &[])),
+    ]);
+
+    let image = buffer.get_mut();
+    for (block, sector) in [(23, root), (a, a_sector), (moved, moved_sector)] {
+        let block = block as usize;
+        image[block * 2048..(block + 1) * 2048].copy_from_slice(&sector);
+    }
+
+    for lazy in [false, true] {
+        let mut options = IsoReadOptions::default();
+        options.set_lazy(lazy);
+
+        let mut reader = IsoFileReader::read_with_options(buffer.clone(), options)
+            .await
+            .unwrap();
+
+        assert_eq!(reader.read_file("/A/B/DEEP.TXT").await.unwrap(), b"D");
+        assert!(reader.is_dir("/A/B").await);
+        assert!(!reader.exists("/RR_MOVED/B").await);
+    }
+}
+
+#[tokio::test]
+async fn custom_boot_record() {
+    let mut buffer = one_file_image().await;
+
+    let image = buffer.get_mut();
+    image.copy_within(17 * 2048..18 * 2048, 18 * 2048);
+    image[17 * 2048..18 * 2048].fill(0);
+    image[17 * 2048..17 * 2048 + 7].copy_from_slice(b"\0CD001\x01");
+    image[17 * 2048 + 7..17 * 2048 + 14].copy_from_slice(b"MYBOOT ");
+    image[17 * 2048 + 39..17 * 2048 + 43].copy_from_slice(b"DISK");
+    image[17 * 2048 + 71..17 * 2048 + 74].copy_from_slice(&[1, 2, 3]);
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let record = reader.boot_record().await.unwrap().unwrap();
+    assert_eq!(
+        (record.system_id.as_str(), record.boot_id.as_str()),
+        ("MYBOOT", "DISK")
+    );
+    assert_eq!(record.system_use.len(), 1977);
+    assert_eq!(record.system_use[..4], [1, 2, 3, 0]);
+    assert_eq!(reader.boot_catalog().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn volume_partition_descriptor() {
+    let mut buffer = one_file_image().await;
+
+    let image = buffer.get_mut();
+    let start = 17 * 2048;
+    image.copy_within(start..start + 2048, start + 2048);
+    image[start..start + 2048].fill(0);
+    image[start..start + 7].copy_from_slice(b"\x03CD001\x01");
+    image[start + 8..start + 13].copy_from_slice(b"LINUX");
+    image[start + 40..start + 44].copy_from_slice(b"PART");
+
+    for (at, value) in [(72, 100u32), (80, 50)] {
+        image[start + at..start + at + 4].copy_from_slice(&value.to_le_bytes());
+        image[start + at + 4..start + at + 8].copy_from_slice(&value.to_be_bytes());
+    }
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let partitions = reader.volume_partitions().await.unwrap();
+    assert_eq!(partitions.len(), 1);
+
+    let partition = &partitions[0];
+    assert_eq!(
+        (
+            partition.system_id.as_str(),
+            partition.partition_id.as_str()
+        ),
+        ("LINUX", "PART")
+    );
+    assert_eq!((partition.location, partition.size), (100, 50));
+    assert_eq!(partition.offset(), 100 * 2048);
+    assert_eq!(partition.system_use.len(), 1960);
+
+    // the offset counts from the start of the reader
+    let mut options = IsoReadOptions::default();
+    options.set_start_offset(4096);
+
+    let shifted = [vec![0; 4096], reader.into_inner().into_inner()].concat();
+    let mut reader = IsoFileReader::read_with_options(Cursor::new(shifted), options)
+        .await
+        .unwrap();
+    let partitions = reader.volume_partitions().await.unwrap();
+    assert_eq!(partitions[0].offset(), 4096 + 100 * 2048);
+}
+
+// El Torito boot record before the terminator and a catalog of `entries`
+// appended at `catalog`, the block past the end of the image
+fn boot_catalog(image: &mut Vec<u8>, catalog: u32, entries: &[[u8; 32]]) {
+    image.copy_within(17 * 2048..18 * 2048, 18 * 2048);
+    image[17 * 2048..18 * 2048].fill(0);
+    image[17 * 2048..17 * 2048 + 30].copy_from_slice(b"\0CD001\x01EL TORITO SPECIFICATION");
+    image[17 * 2048 + 71..17 * 2048 + 75].copy_from_slice(&catalog.to_le_bytes());
+
+    let mut validation = [0u8; 32];
+    validation[0] = 0x01;
+    validation[4..8].copy_from_slice(b"TEST");
+    validation[30..32].copy_from_slice(&[0x55, 0xaa]);
+    let sum = validation.chunks_exact(2).fold(0u16, |sum, t| {
+        sum.wrapping_add(u16::from_le_bytes([t[0], t[1]]))
+    });
+    validation[28..30].copy_from_slice(&sum.wrapping_neg().to_le_bytes());
+
+    let mut sector = vec![validation.to_vec()];
+    sector.extend(entries.iter().map(|t| t.to_vec()));
+    image.extend(directory_sector(&sector));
+}
+
+#[tokio::test]
+async fn el_torito_boot_image() {
+    let mut buffer = one_file_image().await;
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    assert_eq!(reader.boot_catalog().await.unwrap(), None);
+    assert!(matches!(
+        reader.read_boot_image().await,
+        Err(IsoFileError::NoBootRecord)
+    ));
+
+    // boot record before the terminator, catalog and image past the tree
+    let image = buffer.get_mut();
+    let catalog = (image.len() / 2048) as u32;
+
+    let mut default_entry = [0u8; 32];
+    default_entry[0] = 0x88;
+    default_entry[6] = 4;
+    default_entry[8..12].copy_from_slice(&(catalog + 1).to_le_bytes());
+
+    boot_catalog(image, catalog, &[default_entry]);
+    image.extend((0..2048).map(|t| t as u8));
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let boot = reader.boot_catalog().await.unwrap().unwrap();
+    assert_eq!(
+        (boot.platform, boot.id.as_str()),
+        (BootPlatform::X86, "TEST")
+    );
+
+    let entry = &boot.default_entry;
+    assert!(entry.bootable && entry.media_type == BootMediaType::NoEmulation);
+    assert_eq!((entry.sector_count, entry.load_rba), (4, catalog + 1));
+    assert_eq!(
+        reader.read_boot_image().await.unwrap(),
+        (0..2048).map(|t| t as u8).collect::<Vec<_>>()
+    );
+    assert!(reader.capabilities().await.unwrap().el_torito);
+    assert!(boot.sections.is_empty());
+}
+
+#[tokio::test]
+async fn boot_sections() {
+    let mut buffer = one_file_image().await;
+
+    let image = buffer.get_mut();
+    let catalog = (image.len() / 2048) as u32;
+
+    let entry = |sector_count: u16, block: u32| {
+        let mut entry = [0u8; 32];
+        entry[0] = 0x88;
+        entry[6..8].copy_from_slice(&sector_count.to_le_bytes());
+        entry[8..12].copy_from_slice(&block.to_le_bytes());
+        entry
+    };
+    let header = |indicator: u8, platform: u8, count: u16, id: &[u8]| {
+        let mut header = [0u8; 32];
+        header[..2].copy_from_slice(&[indicator, platform]);
+        header[2..4].copy_from_slice(&count.to_le_bytes());
+        header[4..4 + id.len()].copy_from_slice(id);
+        header
+    };
+    let mut extension = [0u8; 32];
+    extension[0] = 0x44;
+
+    // a BIOS section of two entries, then a final EFI section whose entry
+    // has an extension, and an entry past the final section that is ignored
+    boot_catalog(
+        image,
+        catalog,
+        &[
+            entry(4, catalog + 1),
+            header(0x90, 0x00, 2, b"BIOS"),
+            entry(2, catalog + 1),
+            entry(3, catalog + 1),
+            header(0x91, 0xef, 1, b"UEFI"),
+            entry(1, catalog + 2),
+            extension,
+            entry(8, catalog + 1),
+        ],
+    );
+    image.extend([0x11; 2048]);
+    image.extend([0x22; 2048]);
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let boot = reader.boot_catalog().await.unwrap().unwrap();
+
+    let sections: Vec<_> = boot
+        .sections
+        .iter()
+        .map(|t| (t.platform, t.id.as_str(), t.entries.len()))
+        .collect();
+    assert_eq!(
+        sections,
+        [
+            (BootPlatform::X86, "BIOS", 2),
+            (BootPlatform::Efi, "UEFI", 1)
+        ]
+    );
+
+    let counts: Vec<u16> = boot.entries().map(|t| t.sector_count).collect();
+    assert_eq!(counts, [4, 2, 3, 1]);
+
+    let efi = &boot.sections[1].entries[0];
+    assert_eq!(reader.read_boot_entry(efi).await.unwrap(), [0x22; 512]);
+}
+
+#[tokio::test]
+async fn hard_disk_boot_image() {
+    let mut buffer = one_file_image().await;
+
+    let image = buffer.get_mut();
+    let catalog = (image.len() / 2048) as u32;
+
+    // one sector recorded, the disk has a partition from sector 1 to 11
+    let mut entry = [0u8; 32];
+    entry[..2].copy_from_slice(&[0x88, 0x04]);
+    entry[6] = 1;
+    entry[8..12].copy_from_slice(&(catalog + 1).to_le_bytes());
+    boot_catalog(image, catalog, &[entry]);
+
+    let mut disk = vec![0x5a; 6 * 512];
+    disk[446 + 8..446 + 12].copy_from_slice(&1u32.to_le_bytes());
+    disk[446 + 12..446 + 16].copy_from_slice(&10u32.to_le_bytes());
+    disk[510..512].copy_from_slice(&[0x55, 0xaa]);
+    disk[446 + 16..510].fill(0);
+    disk.resize(11 * 512, 0x5a);
+    image.extend(&disk);
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let boot = reader.boot_catalog().await.unwrap().unwrap();
+    assert_eq!(boot.default_entry.media_type, BootMediaType::HardDisk);
+    assert_eq!(boot.default_entry.image_size(), 512);
+    assert_eq!(reader.read_boot_image().await.unwrap(), disk);
+
+    // without a master boot record only the recorded sectors are read
+    buffer.get_mut()[(catalog as usize + 1) * 2048 + 510] = 0;
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_boot_image().await.unwrap().len(), 512);
+}
+
+#[tokio::test]
+async fn hybrid_partition_tables() {
+    let buffer = one_file_image().await;
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let tables = reader.partition_tables().await.unwrap();
+    assert!(!tables.is_hybrid());
+
+    let image = reader.get_mut().get_mut();
+
+    // Apple driver descriptor and MBR share the first sector
+    image[..4].copy_from_slice(b"ER\x08\x00");
+    image[446..462].copy_from_slice(&[0x80, 0, 0, 0, 0x17, 0, 0, 0, 0, 0, 0, 0, 64, 0, 0, 0]);
+    image[510..512].copy_from_slice(&[0x55, 0xaa]);
+
+    // GPT header in sector 1, one used entry in sector 2
+    image[512..520].copy_from_slice(b"EFI PART");
+    image[512 + 72..512 + 80].copy_from_slice(&2u64.to_le_bytes());
+    image[512 + 80..512 + 84].copy_from_slice(&4u32.to_le_bytes());
+    image[512 + 84..512 + 88].copy_from_slice(&128u32.to_le_bytes());
+    image[1024..1040].fill(0xaa);
+    image[1024 + 32..1024 + 40].copy_from_slice(&100u64.to_le_bytes());
+    image[1024 + 40..1024 + 48].copy_from_slice(&199u64.to_le_bytes());
+    let name: Vec<u8> = "EFI".encode_utf16().flat_map(u16::to_le_bytes).collect();
+    image[1024 + 56..1024 + 62].copy_from_slice(&name);
+
+    // partition map entries start at block 1 of the map
+    for (block, name) in [(1, "Apple"), (2, "EFI")] {
+        let entry = &mut image[block * 2048..block * 2048 + 512];
+        entry[..2].copy_from_slice(b"PM");
+        entry[4..8].copy_from_slice(&2u32.to_be_bytes());
+        entry[8..12].copy_from_slice(&(block as u32 * 10).to_be_bytes());
+        entry[16..16 + name.len()].copy_from_slice(name.as_bytes());
+    }
+
+    let tables = reader.partition_tables().await.unwrap();
+    assert!(tables.is_hybrid());
+    assert_eq!(tables.mbr.len(), 1);
+    assert!(tables.mbr[0].bootable && tables.mbr[0].partition_type == 0x17);
+    assert_eq!(tables.mbr[0].sectors, 64);
+
+    assert_eq!(tables.gpt.len(), 1);
+    assert_eq!(
+        (tables.gpt[0].first_lba, tables.gpt[0].last_lba),
+        (100, 199)
+    );
+    assert_eq!(tables.gpt[0].name, "EFI");
+
+    let apm: Vec<_> = tables
+        .apm
+        .iter()
+        .map(|t| (t.name.as_str(), t.start_block))
+        .collect();
+    assert_eq!(apm, [("Apple", 10), ("EFI", 20)]);
+    assert_eq!(tables.apm[0].block_size, 2048);
+}
+
+#[tokio::test]
+async fn implanted_md5() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/a.txt", &[b'A'; 40000], Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.verify_implanted_md5().await.unwrap(), None);
+
+    // the application use area is written blank, as it is hashed
+    let image = reader.get_mut().get_mut();
+    let pvd = 16 * 2048;
+    let sectors = u32::from_le_bytes(image[pvd + 80..pvd + 84].try_into().unwrap()) as usize;
+    let digest = Md5::digest(&image[..(sectors - 2) * 2048]);
+    let hex: String = digest.iter().map(|t| format!("{t:02x}")).collect();
+
+    let implant = format!("ISO MD5SUM = {hex};SKIPSECTORS = 2;RHLISOSTATUS=1;");
+    image[pvd + 883..pvd + 883 + implant.len()].copy_from_slice(implant.as_bytes());
+    assert_eq!(reader.verify_implanted_md5().await.unwrap(), Some(true));
+
+    let location = reader.metadata("/A.TXT").await.unwrap().location as usize;
+    reader.get_mut().get_mut()[location * 2048] = b'B';
+    assert_eq!(reader.verify_implanted_md5().await.unwrap(), Some(false));
+}
+
+// UDF descriptor with its tag identifier and checksum filled in
+fn udf_tag(id: u16, mut raw: Vec<u8>) -> Vec<u8> {
+    raw[..2].copy_from_slice(&id.to_le_bytes());
+    raw[4] = raw[..16].iter().fold(0u8, |sum, t| sum.wrapping_add(*t)) - raw[4];
+    raw
+}
+
+// file identifier descriptor pointing at the file entry in partition block `block`
+fn udf_file_identifier(characteristics: u8, block: u32, name: &str) -> Vec<u8> {
+    let id = if name.is_empty() {
+        Vec::new()
+    } else {
+        [&[8], name.as_bytes()].concat()
+    };
+
+    let mut raw = vec![0u8; (38 + id.len()).next_multiple_of(4)];
+    raw[18] = characteristics;
+    raw[19] = id.len() as u8;
+    raw[20..24].copy_from_slice(&2048u32.to_le_bytes());
+    raw[24..28].copy_from_slice(&block.to_le_bytes());
+    raw[38..38 + id.len()].copy_from_slice(&id);
+    udf_tag(257, raw)
+}
+
+// file entry with embedded content or short allocation descriptors
+fn udf_file_entry(directory: bool, size: u64, embedded: &[u8], extents: &[(u32, u32)]) -> Vec<u8> {
+    let mut raw = vec![0u8; 2048];
+    raw[27] = if directory { 4 } else { 5 };
+    raw[34] = if extents.is_empty() { 3 } else { 0 };
+    raw[56..64].copy_from_slice(&size.to_le_bytes());
+
+    let descriptors: Vec<u8> = if extents.is_empty() {
+        embedded.to_vec()
+    } else {
+        extents
+            .iter()
+            .flat_map(|(length, block)| [length.to_le_bytes(), block.to_le_bytes()].concat())
+            .collect()
+    };
+
+    raw[172..176].copy_from_slice(&(descriptors.len() as u32).to_le_bytes());
+    raw[176..176 + descriptors.len()].copy_from_slice(&descriptors);
+    udf_tag(261, raw)
+}
+
+// bridge disc with files only in its UDF tree, the partition map at
+// 261 * 2048 + 440
+async fn udf_bridge_image() -> Cursor<Vec<u8>> {
+    let mut buffer = one_file_image().await;
+
+    // volume descriptors at 256 and 260, partition from block 270
+    let image = buffer.get_mut();
+    image.resize(300 * 2048, 0);
+    let mut put = |block: usize, raw: Vec<u8>| {
+        image[block * 2048..block * 2048 + raw.len()].copy_from_slice(&raw);
+    };
+
+    let mut anchor = vec![0u8; 512];
+    anchor[16..20].copy_from_slice(&(3 * 2048u32).to_le_bytes());
+    anchor[20..24].copy_from_slice(&260u32.to_le_bytes());
+    put(256, udf_tag(2, anchor));
+
+    let mut partition = vec![0u8; 512];
+    partition[188..192].copy_from_slice(&270u32.to_le_bytes());
+    put(260, udf_tag(5, partition));
+
+    let mut volume = vec![0u8; 512];
+    volume[212..216].copy_from_slice(&2048u32.to_le_bytes());
+    volume[264] = 6;
+    volume[268] = 1;
+    volume[440..446].copy_from_slice(&[1, 6, 1, 0, 0, 0]);
+    put(261, udf_tag(6, volume));
+    put(262, udf_tag(8, vec![0u8; 512]));
+
+    let mut file_set = vec![0u8; 512];
+    file_set[404..408].copy_from_slice(&1u32.to_le_bytes());
+    put(270, udf_tag(256, file_set));
+
+    let root = [
+        udf_file_identifier(0x0a, 1, ""),
+        udf_file_identifier(0, 2, "big.bin"),
+        udf_file_identifier(0, 3, "small.txt"),
+        udf_file_identifier(0x03, 4, "docs"),
+    ]
+    .concat();
+    put(271, udf_file_entry(true, root.len() as u64, &root, &[]));
+
+    // fragmented file modified at 2020-01-02 03:04:05 UTC+1
+    let mut big = udf_file_entry(false, 5000, &[], &[(2048, 10), (2952, 20)]);
+    big[84..96].copy_from_slice(&[60, 0x10, 0xe4, 0x07, 1, 2, 3, 4, 5, 0, 0, 0]);
+    put(272, udf_tag(261, big));
+    put(273, udf_file_entry(false, 5, b"hello", &[]));
+
+    let docs = udf_file_identifier(0x0a, 1, "");
+    put(274, udf_file_entry(true, docs.len() as u64, &docs, &[]));
+    put(280, vec![b'a'; 2048]);
+    put(290, vec![b'b'; 2952]);
+
+    buffer
+}
+
+#[tokio::test]
+async fn udf_bridge() {
+    let buffer = udf_bridge_image().await;
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    assert!(reader.udf_entries().is_none() && !reader.exists("/big.bin").await);
+
+    let mut options = IsoReadOptions::default();
+    options.set_udf(true);
+
+    let mut reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+
+    let big = [vec![b'a'; 2048], vec![b'b'; 2952]].concat();
+    assert_eq!(reader.read_file("/big.bin").await.unwrap(), big);
+    assert_eq!(reader.read_file("/small.txt").await.unwrap(), b"hello");
+    assert_eq!(reader.file_bytes("/small.txt").unwrap(), b"hello");
+    assert!(matches!(
+        reader.file_bytes("/big.bin"),
+        Err(IsoFileError::Fragmented)
+    ));
+
+    let metadata = reader.metadata("/big.bin").await.unwrap();
+    assert_eq!((metadata.size, metadata.location), (5000, 280));
+    assert_eq!(
+        metadata.datetime,
+        Utc.with_ymd_and_hms(2020, 1, 2, 2, 4, 5).single()
+    );
+    assert!(reader.is_dir("/docs").await && reader.metadata("/docs").await.unwrap().hidden);
+
+    let mut handle = reader.open_file("/big.bin").await.unwrap();
+    handle.seek(SeekFrom::Start(2040)).await.unwrap();
+    let mut span = [0u8; 16];
+    handle.read_exact(&mut span).await.unwrap();
+    assert_eq!(span, *b"aaaaaaaabbbbbbbb");
+
+    // files only in the ISO 9660 tree are still found
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"A");
+    assert_eq!(reader.udf_entries().unwrap().walk().count(), 4);
+
+    // and listed with the others
+    let paths: Vec<PathBuf> = reader.walk().map(|(path, _)| path).collect();
+    assert_eq!(
+        paths,
+        ["/", "/A.TXT", "/big.bin", "/docs", "/small.txt"].map(PathBuf::from)
+    );
+}
+
+#[tokio::test]
+async fn unsupported_udf() {
+    let mut buffer = udf_bridge_image().await;
+
+    // a virtual partition map
+    buffer.get_mut()[261 * 2048 + 440] = 2;
+
+    let mut options = IsoReadOptions::default();
+    options.set_udf(true);
+
+    let mut reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+    assert!(reader.udf_entries().is_none());
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"A");
+
+    let report = reader.verify().await.unwrap();
+    assert_eq!(report.violations, [Violation::UnsupportedUdf]);
+}
+
+#[tokio::test]
+async fn multi_extent_file() {
+    let mut buffer = one_file_image().await;
+
+    // three extents, the first two adjacent and the last one after a gap
+    let image = buffer.get_mut();
+    let first = (image.len() / 2048) as u32;
+    image.extend([vec![b'x'; 2048], vec![b'y'; 2048], vec![0; 2048]].concat());
+    image.extend(vec![b'z'; 2048]);
+
+    image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(first, 2048, 0x80, b"BIG.BIN;1", &[]),
+        directory_record(first + 1, 2048, 0x80, b"BIG.BIN;1", &[]),
+        directory_record(first + 3, 100, 0, b"BIG.BIN;1", &[]),
+    ]));
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let content = [vec![b'x'; 2048], vec![b'y'; 2048], vec![b'z'; 100]].concat();
+    assert_eq!(reader.read_file("/BIG.BIN").await.unwrap(), content);
+    assert_eq!(reader.metadata("/BIG.BIN").await.unwrap().size, 4196);
+
+    let entry = reader.entries().get(Path::new("/BIG.BIN")).unwrap();
+    assert_eq!(entry.extents().count(), 3);
+
+    let mut handle = reader.open_file("/BIG.BIN").await.unwrap();
+    handle.seek(SeekFrom::End(-102)).await.unwrap();
+    let mut tail = Vec::new();
+    handle.read_to_end(&mut tail).await.unwrap();
+    assert_eq!(tail, [&b"yy"[..], &[b'z'; 100]].concat());
+}
+
+#[tokio::test]
+async fn file_versions() {
+    let mut buffer = one_file_image().await;
+
+    let image = buffer.get_mut();
+    let first = (image.len() / 2048) as u32;
+    image.extend([vec![b'1'; 2048], vec![b'2'; 2048]].concat());
+
+    image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(first, 1, 0, b"BAR.TXT;1", &[]),
+        directory_record(first + 1, 2, 0, b"BAR.TXT;3", &[]),
+        directory_record(first + 1, 2, 0, b"FOO.TXT;2", &[]),
+        directory_record(first, 1, 0, b"FOO.TXT;1", &[]),
+    ]));
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert!(reader.is_file("/FOO.TXT").await);
+    assert!(reader.is_file("/FOO.TXT;2").await);
+    assert!(!reader.is_file("/FOO.TXT;3").await);
+    assert!(!reader.is_dir("/FOO.TXT;2").await);
+    assert_eq!(reader.read_file("/FOO.TXT").await.unwrap(), b"22");
+
+    // lower versions are kept behind their suffix, in either order
+    assert_eq!(reader.read_file("/FOO.TXT;1").await.unwrap(), b"1");
+    assert_eq!(reader.read_file("/BAR.TXT").await.unwrap(), b"22");
+    assert_eq!(reader.read_file("/BAR.TXT;1").await.unwrap(), b"1");
+    assert_eq!(reader.read_file("/BAR.TXT;3").await.unwrap(), b"22");
+
+    let entry = reader.entries().get(Path::new("/FOO.TXT;2")).unwrap();
+    assert_eq!(entry.version(), Some(2));
+
+    // identifiers without a record are files when they carry a version
+    assert!(matches!(
+        IsoEntry::from(b"FOO.TXT;2".to_vec()),
+        IsoEntry::File(t) if t == "FOO.TXT"
+    ));
+    assert!(matches!(
+        IsoEntry::from(b"A;1B".to_vec()),
+        IsoEntry::Directory(t) if t == "A;1B"
+    ));
+}
+
+#[tokio::test]
+async fn extended_attributes() {
+    let mut buffer = one_file_image().await;
+
+    // one block of extended attributes, then the data
+    let mut xar = vec![0u8; 2048];
+    xar[0..4].copy_from_slice(&[0xe8, 0x03, 0x03, 0xe8]);
+    xar[4..8].copy_from_slice(&[0x64, 0x00, 0x00, 0x64]);
+    // owner read/execute and group read allowed
+    xar[8..10].copy_from_slice(&0xfeafu16.to_be_bytes());
+    xar[27..44].copy_from_slice(b"2024010212304500\0");
+
+    let image = buffer.get_mut();
+    let first = (image.len() / 2048) as u32;
+    image.extend([xar, vec![b'd'; 2048]].concat());
+
+    let mut record = directory_record(first, 3, 0, b"DATA.BIN;1", &[]);
+    record[1] = 1;
+
+    image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        record,
+    ]));
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/DATA.BIN").await.unwrap(), b"ddd");
+
+    let attributes = reader
+        .extended_attributes("/DATA.BIN")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((attributes.owner, attributes.group), (1000, 100));
+    assert_eq!(attributes.mode(), 0o540);
+    assert_eq!(
+        attributes.modified,
+        Some(Utc.with_ymd_and_hms(2024, 1, 2, 12, 30, 45).unwrap())
+    );
+    assert_eq!(attributes.created, None);
+    assert_eq!(reader.extended_attributes("/").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn associated_file() {
+    let mut buffer = one_file_image().await;
+
+    let image = buffer.get_mut();
+    let first = (image.len() / 2048) as u32;
+    image.extend([vec![b'r'; 2048], vec![b'd'; 2048]].concat());
+
+    // the associated file is recorded first
+    image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(first, 4, 0x04, b"APP.BIN;1", &[]),
+        directory_record(first + 1, 2, 0, b"APP.BIN;1", &[]),
+    ]));
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/APP.BIN").await.unwrap(), b"dd");
+    assert_eq!(
+        reader.read_associated_file("/APP.BIN").await.unwrap(),
+        b"rrrr"
+    );
+    assert!(reader.read_associated_file("/A.TXT").await.is_err());
+}
+
+#[tokio::test]
+async fn hidden_entries() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.append_file("/dir/b.txt", b"B", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let a = reader.metadata("/A.TXT").await.unwrap().location;
+    let dir = reader.metadata("/DIR").await.unwrap().location;
+
+    // hide the file and the directory in the root
+    let image = buffer.get_mut();
+    image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(a, 1, 0x01, b"A.TXT;1", &[]),
+        directory_record(dir, 2048, 0x03, b"DIR", &[]),
+    ]));
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    assert!(
+        reader
+            .entries()
+            .get(Path::new("/A.TXT"))
+            .unwrap()
+            .is_hidden()
+    );
+    assert!(reader.is_file("/DIR/B.TXT").await);
+
+    let mut options = IsoReadOptions::default();
+    options.set_hidden(false);
+
+    let mut reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+    assert!(!reader.exists("/A.TXT").await);
+    assert!(!reader.exists("/DIR").await);
+    assert_eq!(reader.entries().walk(true).count(), 0);
+}
+
+#[tokio::test]
+async fn zisofs_file() {
+    let mut buffer = one_file_image().await;
+
+    // a compressed block of 32 KiB and a block of zeros left empty
+    let original = [
+        (0..32768).map(|t| (t % 251) as u8).collect::<Vec<_>>(),
+        vec![0; 1000],
+    ]
+    .concat();
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&original[..32768]).unwrap();
+    let block = encoder.finish().unwrap();
+
+    let start = 16 + 3 * 4;
+    let end = (start + block.len()) as u32;
+    let stored = [
+        &[0x37, 0xe4, 0x53, 0x96, 0xc9, 0xdb, 0xd6, 0x07][..],
+        &(original.len() as u32).to_le_bytes(),
+        &[4, 15, 0, 0],
+        &(start as u32).to_le_bytes(),
+        &end.to_le_bytes(),
+        &end.to_le_bytes(),
+        &block,
+    ]
+    .concat();
+
+    let image = buffer.get_mut();
+    let first = (image.len() / 2048) as u32;
+    image.extend(&stored);
+    image.resize(image.len().next_multiple_of(2048), 0);
+
+    let sp = susp_entry(b"SP", &[0xbe, 0xef, 0]);
+    let size = original.len() as u32;
+    let zf = susp_entry(
+        b"ZF",
+        &[
+            &b"pz"[..],
+            &[4, 15],
+            &size.to_le_bytes(),
+            &size.to_be_bytes(),
+        ]
+        .concat(),
+    );
+
+    image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &sp),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(first, stored.len() as u32, 0, b"DATA.BIN;1", &zf),
+    ]));
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert!(
+        reader
+            .entries()
+            .get(Path::new("/DATA.BIN"))
+            .unwrap()
+            .is_compressed()
+    );
+    assert_eq!(reader.metadata("/DATA.BIN").await.unwrap().size, 33768);
+    assert_eq!(reader.read_file("/DATA.BIN").await.unwrap(), original);
+    assert!(matches!(
+        reader.file_bytes("/DATA.BIN"),
+        Err(IsoFileError::Compressed)
+    ));
+}
+
+#[tokio::test]
+async fn trans_tbl_names() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    let table = b"F README.TXT;1\tReadMe.txt\r\nD DOCS          docs\r\nL LATEST;1    latest -->README.TXT\r\n";
+    writer.append_file("/readme.txt", b"R", Utc::now()).unwrap();
+    writer.append_file("/latest", b"", Utc::now()).unwrap();
+    writer
+        .append_file("/docs/guide.txt", b"G", Utc::now())
+        .unwrap();
+    writer.append_file("/trans.tbl", table, Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    let reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let entry = reader.entries().get(Path::new("/README.TXT")).unwrap();
+    assert_eq!(entry.trans_name(), None);
+
+    let mut options = IsoReadOptions::default();
+    options.set_trans_tbl(true);
+
+    let reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+    let name = |path: &str| reader.entries().get(Path::new(path)).unwrap().trans_name();
+    assert_eq!(name("/README.TXT"), Some("ReadMe.txt"));
+    assert_eq!(name("/DOCS"), Some("docs"));
+    assert_eq!(name("/LATEST"), Some("latest"));
+    assert_eq!(name("/DOCS/GUIDE.TXT"), None);
+}
+
+#[tokio::test]
+async fn apple_attributes() {
+    let mut buffer = one_file_image().await;
+
+    let image = buffer.get_mut();
+    let aa = [&b"AA\x0e\x02TEXTttxt"[..], &[0x01, 0x00]].concat();
+    let ba = b"BA\x07\x01\x06\x20\x00";
+
+    image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(24, 0, 0, b"NOTE.TXT;1", &aa),
+        directory_record(24, 0, 0, b"PRODOS.SYS;1", ba),
+        directory_record(24, 0, 0, b"PLAIN.TXT;1", &[]),
+    ]));
+
+    let reader = IsoFileReader::read(buffer).await.unwrap();
+    let attributes = |path: &str| {
+        reader
+            .entries()
+            .get(Path::new(path))
+            .unwrap()
+            .apple_attributes()
+    };
+
+    let note = attributes("/NOTE.TXT").unwrap();
+    assert_eq!((&note.file_type, &note.creator), (b"TEXT", b"ttxt"));
+    assert_eq!(note.finder_flags, 0x0100);
+
+    let prodos = attributes("/PRODOS.SYS").unwrap();
+    assert_eq!(prodos.file_type, [b'p', 0x06, 0x20, 0x00]);
+    assert_eq!(&prodos.creator, b"pdos");
+    assert_eq!(attributes("/PLAIN.TXT"), None);
+}
+
+#[tokio::test]
+async fn xa_records() {
+    let mut buffer = one_file_image().await;
+
+    let xa = |attributes: u16, file_number: u8| {
+        [
+            &[0, 0, 0, 0][..],
+            &attributes.to_be_bytes(),
+            b"XA",
+            &[file_number, 0, 0, 0, 0, 0],
+        ]
+        .concat()
+    };
+
+    // mkisofs puts the SP entry after the XA record of the root
+    let root = [xa(0x8d55, 0), susp_entry(b"SP", &[0xbe, 0xef, 0])].concat();
+    let movie = [xa(0x1555, 1), susp_entry(b"NM", b"\0movie.str")].concat();
+
+    let image = buffer.get_mut();
+    image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &root),
+        directory_record(23, 2048, 2, &[1], &xa(0x8d55, 0)),
+        directory_record(24, 0, 0, b"MOVIE.STR;1", &movie),
+    ]));
+
+    let reader = IsoFileReader::read(buffer).await.unwrap();
+    let entry = reader.entries().get(Path::new("/movie.str")).unwrap();
+    let attributes = entry.xa_attributes().unwrap();
+    assert!(attributes.is_form2() && !attributes.is_form1());
+    assert_eq!((attributes.file_number, attributes.mode()), (1, 0o555));
+    assert!(entry.apple_attributes().is_none());
+}
+
+#[tokio::test]
+async fn m_path_table_fallback() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer
+        .append_file("/dir/sub/c.txt", b"C", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    // no L table recorded in the primary descriptor
+    buffer.get_mut()[0x8000 + 140..0x8000 + 144].fill(0);
+
+    let mut options = IsoReadOptions::default();
+    options.set_path_table_lookup(true);
+
+    let mut reader = IsoFileReader::read_with_options(buffer.clone(), options.clone())
+        .await
+        .unwrap();
+
+    assert!(matches!(reader.path_table(), IsoPathTable::MTable(_)));
+    assert_eq!(reader.path_table().paths()[2].0, Path::new("/DIR/SUB"));
+    assert_eq!(reader.read_file("/DIR/SUB/C.TXT").await.unwrap(), b"C");
+
+    // entries in native order, big-endian again when written
+    let sub = reader.metadata("/DIR/SUB").await.unwrap().location;
+    let entry = &reader.path_table().entries()[2];
+    assert_eq!((entry.location(), entry.parent()), (sub, 2));
+
+    let raw = reader.path_table().as_vec();
+    let m_table = 21 * 2048;
+    assert_eq!(buffer.get_ref()[m_table..m_table + raw.len()], raw);
+
+    // an L table whose first entry is not the root
+    let image = buffer.get_mut();
+    image[0x8000 + 140..0x8000 + 144].copy_from_slice(&19u32.to_le_bytes());
+    image[19 * 2048 + 6] = 2;
+
+    let reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+
+    assert!(reader.path_table().is_consistent());
+    assert!(matches!(reader.path_table(), IsoPathTable::MTable(_)));
+}
+
+#[tokio::test]
+async fn latin1_identifiers() {
+    let mut buffer = one_file_image().await;
+
+    let image = buffer.get_mut();
+    image[0x8000 + 40..0x8000 + 45].copy_from_slice(b"D\xc9J\xc0 ");
+    image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        directory_record(24, 0, 0, b"CAF\xc9.TXT;1", &[]),
+    ]));
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    assert!(reader.exists("/CAF\u{fffd}.TXT").await);
+
+    let mut options = IsoReadOptions::default();
+    options.set_charset(Charset::Latin1);
+
+    let mut reader = IsoFileReader::read_with_options(buffer.clone(), options)
+        .await
+        .unwrap();
+    assert!(reader.is_file("/CAFÉ.TXT").await);
+    assert_eq!(reader.header().volumen_id.as_deref(), Some("DÉJÀ"));
+
+    let mut options = IsoReadOptions::default();
+    options.set_charset(Charset::Custom(|t| {
+        String::from_utf8_lossy(t).to_lowercase()
+    }));
+
+    let mut reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+    assert!(reader.is_file("/caf\u{fffd}.txt").await);
+}
+
+#[tokio::test]
+async fn timezone_offsets() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let created = FixedOffset::east_opt(8 * 3600)
+        .unwrap()
+        .with_ymd_and_hms(2024, 5, 6, 7, 8, 9)
+        .unwrap();
+
+    let mut header = IsoHeader::default();
+    header.set_volume_creation_date(created);
+
+    let mut writer = IsoFileWriter::new(&mut buffer, header).await.unwrap();
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    // recorded at 10:30 five hours west of GMT
+    let mut record = directory_record(24, 0, 0, b"WEST.TXT;1", &[]);
+    record[18..25].copy_from_slice(&[124, 1, 2, 10, 30, 0, (-20i8) as u8]);
+
+    let image = buffer.get_mut();
+    image[23 * 2048..24 * 2048].copy_from_slice(&directory_sector(&[
+        directory_record(23, 2048, 2, &[0], &[]),
+        directory_record(23, 2048, 2, &[1], &[]),
+        record,
+    ]));
+
+    let reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.header().volume_creation_date(), Some(created));
+    assert_eq!(
+        reader.header().volume_creation_date().unwrap().offset(),
+        created.offset()
+    );
+
+    let record = reader
+        .entries()
+        .get(Path::new("/WEST.TXT"))
+        .unwrap()
+        .record();
+    let local = record.datetime_with_offset().unwrap();
+    assert_eq!(local.offset().local_minus_utc(), -5 * 3600);
+    assert_eq!(
+        record.datetime().unwrap(),
+        Utc.with_ymd_and_hms(2024, 1, 2, 15, 30, 0).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn read_at_offset() {
+    let mut buffer = Cursor::new(vec![0xaa; 4096]);
+    buffer.set_position(4096);
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/dir/b.txt", b"B", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read_at(buffer, 4096).await.unwrap();
+    assert_eq!(reader.read_file("/DIR/B.TXT").await.unwrap(), b"B");
+}
+
+#[tokio::test]
+async fn scan_sessions() {
+    let mut session = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut session, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    let mut options = IsoReadOptions::default();
+    options.set_scan_sessions(true);
+
+    let reader = IsoFileReader::read_with_options(session, options)
+        .await
+        .unwrap();
+
+    assert_eq!(reader.sessions(), [0]);
+    assert_eq!(
+        reader.entries().get(Path::new("/A.TXT")).unwrap().session(),
+        0
+    );
+}
+
+// image of one file in the root written from block 0, as the session that
+// starts at block `start` of a disc: its extent locations become absolute
+async fn session_at(start: u32, path: &str, content: &[u8]) -> Vec<u8> {
+    let mut session = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut session, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file(path, content, Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    let mut image = session.into_inner();
+    let shift_le = |image: &mut [u8], at: usize| {
+        let value = u32::from_le_bytes(image[at..at + 4].try_into().unwrap()) + start;
+        image[at..at + 4].copy_from_slice(&value.to_le_bytes());
+    };
+    let shift_be = |image: &mut [u8], at: usize| {
+        let value = u32::from_be_bytes(image[at..at + 4].try_into().unwrap()) + start;
+        image[at..at + 4].copy_from_slice(&value.to_be_bytes());
+    };
+
+    // volume space size and both tables, with their single root entry
+    let pvd = 16 * 2048;
+    shift_le(&mut image, pvd + 80);
+    shift_be(&mut image, pvd + 84);
+
+    let l_table = u32::from_le_bytes(image[pvd + 140..pvd + 144].try_into().unwrap());
+    let m_table = u32::from_be_bytes(image[pvd + 148..pvd + 152].try_into().unwrap());
+    shift_le(&mut image, l_table as usize * 2048 + 2);
+    shift_be(&mut image, m_table as usize * 2048 + 2);
+    shift_le(&mut image, pvd + 140);
+    shift_be(&mut image, pvd + 148);
+
+    // every record of the root directory, and the root record itself
+    let root = u32::from_le_bytes(image[pvd + 158..pvd + 162].try_into().unwrap()) as usize;
+    let mut record = root * 2048;
+
+    while image[record] != 0 {
+        shift_le(&mut image, record + 2);
+        shift_be(&mut image, record + 6);
+        record += usize::from(image[record]);
+    }
+
+    shift_le(&mut image, pvd + 158);
+    shift_be(&mut image, pvd + 162);
+
+    image
+}
+
+#[tokio::test]
+async fn session_offsets() {
+    let mut disc = session_at(0, "/a.txt", b"A").await;
+    let start = (disc.len() / 2048) as u32;
+    disc.extend(session_at(start, "/b.txt", b"B").await);
+
+    let mut reader = IsoFileReader::read_session(Cursor::new(disc), start)
+        .await
+        .unwrap();
+
+    // read relative to the start of the disc, not of the session, which
+    // would find the data of the first one
+    assert_eq!(reader.sessions(), [start]);
+    assert_eq!(reader.read_file("/B.TXT").await.unwrap(), b"B");
+    assert!(!reader.exists("/A.TXT").await);
+    assert!(reader.metadata("/B.TXT").await.unwrap().location > start);
+}
+
+#[tokio::test]
+async fn scan_two_sessions() {
+    let mut disc = session_at(0, "/a.txt", b"A").await;
+    let start = (disc.len() / 2048) as u32 + 16;
+    disc.resize(start as usize * 2048, 0);
+    disc.extend(session_at(start, "/b.txt", b"B").await);
+
+    let mut options = IsoReadOptions::default();
+    options.set_scan_sessions(true);
+
+    let mut reader = IsoFileReader::read_with_options(Cursor::new(disc), options)
+        .await
+        .unwrap();
+
+    assert_eq!(reader.sessions(), [0, start]);
+    assert_eq!(
+        reader.entries().get(Path::new("/A.TXT")).unwrap().session(),
+        0
+    );
+    assert_eq!(
+        reader.entries().get(Path::new("/B.TXT")).unwrap().session(),
+        1
+    );
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"A");
+    assert_eq!(reader.read_file("/B.TXT").await.unwrap(), b"B");
+}
+
+#[tokio::test]
+async fn raw_sectors() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/dir/b.txt", b"raw sector content", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    for (mode, data_offset) in [(SectorMode::Mode1, 16), (SectorMode::Mode2Form1, 24)] {
+        let mut raw = Vec::new();
+
+        for sector in buffer.get_ref().chunks(2048) {
+            let mut raw_sector = vec![0xffu8; RAW_SECTOR_SIZE];
+            raw_sector[data_offset..data_offset + 2048].copy_from_slice(sector);
+            raw.extend_from_slice(&raw_sector);
+        }
+
+        let mut sectors = RawSectorReader::new(Cursor::new(raw.clone()), mode);
+        let end = sectors.seek(SeekFrom::End(0)).await.unwrap();
+        assert_eq!(end, buffer.get_ref().len() as u64);
+
+        let mut reader = IsoFileReader::read_raw(Cursor::new(raw), mode)
+            .await
+            .unwrap();
+
+        let content = reader.read_file("/DIR/B.TXT").await.unwrap();
+        assert_eq!(content, b"raw sector content");
+
+        let mut handle = reader.open_file("/DIR/B.TXT").await.unwrap();
+        handle.seek(SeekFrom::End(-7)).await.unwrap();
+        let mut tail = String::new();
+        handle.read_to_string(&mut tail).await.unwrap();
+        assert_eq!(tail, "content");
+    }
+}
+
+#[tokio::test]
+async fn bin_cue() {
+    let buffer = one_file_image().await;
+
+    // an audio track of 2 seconds before the data track
+    let mut bin = vec![0u8; 150 * RAW_SECTOR_SIZE];
+
+    for sector in buffer.get_ref().chunks(2048) {
+        let mut raw_sector = vec![0u8; RAW_SECTOR_SIZE];
+        raw_sector[16..16 + 2048].copy_from_slice(sector);
+        bin.extend_from_slice(&raw_sector);
+    }
+
+    let cue = "FILE \"my image.bin\" BINARY\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n  TRACK 02 MODE1/2352\n    INDEX 00 00:01:74\n    INDEX 01 00:02:00\n";
+
+    let sheet = CueSheet::parse(cue).unwrap();
+    let (file, track) = sheet.first_data_track().unwrap();
+    assert_eq!(file.name, "my image.bin");
+    assert_eq!((track.number, track.start), (2, 150));
+
+    let dir = std::env::temp_dir().join(format!("iso_file_cue_{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+    tokio::fs::write(dir.join("my image.bin"), bin)
+        .await
+        .unwrap();
+    tokio::fs::write(dir.join("image.cue"), cue).await.unwrap();
+
+    let mut reader = IsoFileReader::open_cue(dir.join("image.cue"))
+        .await
+        .unwrap();
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"A");
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn compressed_image() {
+    use async_compression::tokio::write::GzipEncoder;
+    use tokio::io::AsyncWriteExt;
+
+    use crate::Compression;
+
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"first", Utc::now()).unwrap();
+    writer
+        .append_file("/dir/b.txt", b"second", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut encoder = GzipEncoder::new(Vec::new());
+    encoder.write_all(buffer.get_ref()).await.unwrap();
+    encoder.shutdown().await.unwrap();
+
+    let compressed = Cursor::new(encoder.into_inner());
+    let mut reader = IsoFileReader::read_compressed(compressed, Compression::Gzip)
+        .await
+        .unwrap();
+
+    // A.TXT is stored before DIR/B.TXT, reading it second needs a rewind
+    assert_eq!(reader.read_file("/DIR/B.TXT").await.unwrap(), b"second");
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"first");
+}
+
+// counts the seeks of the source, which the decompressor only does to rewind
+#[cfg(feature = "gzip")]
+struct CountingSeeks(Cursor<Vec<u8>>, Arc<AtomicUsize>);
+
+#[cfg(feature = "gzip")]
+impl tokio::io::AsyncRead for CountingSeeks {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl tokio::io::AsyncSeek for CountingSeeks {
+    fn start_seek(mut self: std::pin::Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        self.1.fetch_add(1, Ordering::Relaxed);
+        std::pin::Pin::new(&mut self.0).start_seek(position)
+    }
+
+    fn poll_complete(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<u64>> {
+        std::pin::Pin::new(&mut self.0).poll_complete(cx)
+    }
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn compressed_block_cache() {
+    use async_compression::tokio::write::GzipEncoder;
+    use tokio::io::AsyncWriteExt;
+
+    use crate::{Compression, DecompressReader};
+
+    let big: Vec<u8> = (0..100_000u32).map(|t| t as u8).collect();
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"first", Utc::now()).unwrap();
+    writer.append_file("/big.bin", &big, Utc::now()).unwrap();
+    writer
+        .append_file("/dir/b.txt", b"second", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut encoder = GzipEncoder::new(Vec::new());
+    encoder.write_all(buffer.get_ref()).await.unwrap();
+    encoder.shutdown().await.unwrap();
+    let compressed = encoder.into_inner();
+
+    // parsing and reading in any order is served from the cached blocks
+    let seeks = Arc::new(AtomicUsize::new(0));
+    let source = CountingSeeks(Cursor::new(compressed.clone()), seeks.clone());
+    let mut reader = IsoFileReader::read(DecompressReader::new(source, Compression::Gzip))
+        .await
+        .unwrap();
+
+    reader.load_all().await.unwrap();
+    assert_eq!(reader.read_file("/DIR/B.TXT").await.unwrap(), b"second");
+    assert_eq!(reader.read_file("/BIG.BIN").await.unwrap(), big);
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"first");
+    assert_eq!(seeks.load(Ordering::Relaxed), 0);
+
+    // with a single block cached, going back past it rewinds
+    let seeks = Arc::new(AtomicUsize::new(0));
+    let source = CountingSeeks(Cursor::new(compressed), seeks.clone());
+    let mut reader = IsoFileReader::read(DecompressReader::new(source, Compression::Gzip))
+        .await
+        .unwrap();
+
+    reader.get_mut().set_cache_size(0);
+    assert_eq!(reader.read_file("/DIR/B.TXT").await.unwrap(), b"second");
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"first");
+    assert_eq!(seeks.load(Ordering::Relaxed), 1);
+}
+
+#[derive(Clone)]
+struct MemoryRangeSource(Bytes, Arc<AtomicUsize>);
+
+impl RangeSource for MemoryRangeSource {
+    fn size(&self) -> BoxFuture<'static, std::io::Result<u64>> {
+        let len = self.0.len() as u64;
+        Box::pin(async move { Ok(len) })
+    }
+
+    fn fetch(&self, range: Range<u64>) -> BoxFuture<'static, std::io::Result<Bytes>> {
+        self.1.fetch_add(1, Ordering::Relaxed);
+
+        let start = (range.start as usize).min(self.0.len());
+        let end = (range.end as usize).min(self.0.len());
+        let data = self.0.slice(start..end);
+
+        Box::pin(async move { Ok(data) })
+    }
+}
+
+#[tokio::test]
+async fn range_reader() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/dir/b.txt", b"remote", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let source = MemoryRangeSource(Bytes::from(buffer.into_inner()), fetches.clone());
+
+    let mut range_reader = RangeReader::new(source);
+    range_reader.set_fetch_size(4096);
+
+    let mut reader = IsoFileReader::read(range_reader).await.unwrap();
+    assert_eq!(reader.read_file("/DIR/B.TXT").await.unwrap(), b"remote");
+
+    let mut handle = reader.open_file("/DIR/B.TXT").await.unwrap();
+    handle.seek(SeekFrom::End(-3)).await.unwrap();
+    let mut tail = String::new();
+    handle.read_to_string(&mut tail).await.unwrap();
+    assert_eq!(tail, "ote");
+
+    assert!(fetches.load(Ordering::Relaxed) < 16);
+}
+
+#[cfg(feature = "object_store")]
+#[tokio::test]
+async fn object_store_reader() {
+    use object_store::memory::InMemory;
+    use object_store::{ObjectStore, PutPayload};
+
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"stored", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    let store = Arc::new(InMemory::new());
+    let location = object_store::path::Path::from("images/test.iso");
+
+    store
+        .put(&location, PutPayload::from(buffer.into_inner()))
+        .await
+        .unwrap();
+
+    let mut reader = IsoFileReader::open_object(store, location).await.unwrap();
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"stored");
+}
+
+#[cfg(feature = "mmap")]
+#[tokio::test]
+async fn mmap_reader() {
+    let reader = IsoFileReader::open_mmap("image.iso").await.unwrap();
+    assert_eq!(reader.file_bytes("/HELLO.TXT").unwrap(), b"Hello, World!\n");
+
+    let content: Vec<u8> = (0..5000u32).map(|t| t as u8).collect();
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/data.bin", &content, Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    // a file spanning several blocks is borrowed from the image, not copied
+    let reader = IsoFileReader::read(buffer).await.unwrap();
+    let bytes = reader.file_bytes("/DATA.BIN").unwrap();
+    assert_eq!(bytes, content);
+
+    let image = reader.reader.get_ref().as_slice().as_ptr_range();
+    assert!(image.contains(&bytes.as_ptr()));
+
+    assert!(matches!(
+        reader.file_bytes("/MISSING.BIN"),
+        Err(IsoFileError::FileNotFound)
+    ));
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn sync_reader() {
+    use std::io::{Read, Seek};
+
+    let image = std::fs::File::open("image.iso").unwrap();
+    let mut reader = crate::sync::IsoFileReader::read(image).unwrap();
+
+    assert!(reader.is_file("/ONE/HELLO2.TXT"));
+    assert_eq!(reader.read_file("/HELLO.TXT").unwrap(), b"Hello, World!\n");
+
+    let mut handle = reader.open_file("/HELLO.TXT").unwrap();
+    handle.seek(SeekFrom::Start(7)).unwrap();
+    let mut content = String::new();
+    handle.read_to_string(&mut content).unwrap();
+    assert_eq!(content, "World!\n");
+}
+
+#[tokio::test]
+async fn open_path() {
+    let mut reader = IsoFileReader::open("image.iso").await.unwrap();
+    assert_eq!(
+        reader.read_file("/HELLO.TXT").await.unwrap(),
+        b"Hello, World!\n"
+    );
+
+    assert!(matches!(
+        IsoFileReader::open("missing.iso").await,
+        Err(IsoFileError::StdIo(t)) if t.kind() == std::io::ErrorKind::NotFound
+    ));
+}
+
+#[tokio::test]
+async fn spawned_reader() {
+    let task = tokio::spawn(async {
+        let mut reader = IsoFileReader::open("image.iso").await.unwrap();
+
+        reader.load_all().await.unwrap();
+        reader.read_file("/ONE/HELLO2.TXT").await.unwrap()
+    });
+
+    assert!(!task.await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn shared_index() {
+    let mut image = File::open("image.iso").await.unwrap();
+
+    let mut options = IsoReadOptions::default();
+    options.set_lazy(true);
+
+    let index = Arc::new(IsoIndex::read(&mut image, &options).await.unwrap());
+
+    let mut first = IsoFileReader::with_index(image, index.clone());
+    let mut second = IsoFileReader::with_index(File::open("image.iso").await.unwrap(), index);
+
+    assert!(Arc::ptr_eq(first.index(), second.index()));
+    assert_eq!(
+        first.read_file("/HELLO.TXT").await.unwrap(),
+        b"Hello, World!\n"
+    );
+
+    // loading a skipped directory detaches the index from the other reader
+    assert!(
+        !second
+            .read_file("/ONE/HELLO2.TXT")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert!(!Arc::ptr_eq(first.index(), second.index()));
+    assert!(first.entries().is_pending(Path::new("/ONE")));
+}
+
+#[tokio::test]
+async fn inner_reader() {
+    let mut reader = IsoFileReader::read(File::open("image.iso").await.unwrap())
+        .await
+        .unwrap();
+
+    // reads seek on their own, whatever the caller did with the reader
+    reader.get_mut().seek(SeekFrom::End(0)).await.unwrap();
+    assert_eq!(
+        reader.read_file("/HELLO.TXT").await.unwrap(),
+        b"Hello, World!\n"
+    );
+
+    let mut image = reader.into_inner();
+    let length = image.seek(SeekFrom::End(0)).await.unwrap();
+    assert_eq!(length, image.metadata().await.unwrap().len());
+}
+
+#[tokio::test]
+async fn verify_image() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.append_file("/b.txt", b"B", Utc::now()).unwrap();
+    writer
+        .append_file("/dir/sub/c.txt", b"C", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+
+    // point b.txt at the extent of a.txt with a longer length
+    let image = buffer.get_mut();
+    let find = |image: &[u8], name: &[u8]| {
+        image
+            .windows(name.len())
+            .position(|t| t == name)
+            .map(|t| t - 33)
+            .unwrap()
+    };
+
+    let a = find(image, b"A.TXT");
+    let b = find(image, b"B.TXT");
+    image.copy_within(a + 2..a + 10, b + 2);
+    image[b + 10..b + 14].copy_from_slice(&3000u32.to_le_bytes());
+    image[b + 14..b + 18].copy_from_slice(&3000u32.to_be_bytes());
+
+    // corrupt the msb half of the volume space size and drop the last block
+    image[0x8000 + 84] ^= 0xff;
+    let length = image.len();
+    image.truncate(length - 2048);
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let violations = reader.verify().await.unwrap().violations;
+
+    assert!(violations.contains(&Violation::Overlap {
+        first: PathBuf::from("/A.TXT"),
+        second: PathBuf::from("/B.TXT"),
+    }));
+    assert!(violations.contains(&Violation::EndianMismatch {
+        field: "volume_space_size",
+        path: None,
+    }));
+    assert!(violations.contains(&Violation::ImageTruncated {
+        expected: length as u64,
+        actual: length as u64 - 2048,
+    }));
+}
+
+#[tokio::test]
+async fn volume_size() {
+    for files in [0, 1, 3] {
+        let mut buffer = Cursor::new(Vec::new());
+
+        let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+            .await
+            .unwrap();
+
+        for index in 0..files {
+            writer
+                .append_file(&format!("/dir/{index}.txt"), b"data", Utc::now())
+                .unwrap();
+        }
+        writer.close().await.unwrap();
+
+        let image = buffer.into_inner();
+        let reader = IsoFileReader::read(Cursor::new(image.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            reader.header().volume_space_size as usize * 2048,
+            image.len()
+        );
+    }
+}
+
+#[tokio::test]
+async fn optional_path_tables() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer
+        .append_file("/dir/sub/c.txt", b"C", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    assert!(reader.optional_path_tables().is_empty());
+
+    // optional tables pointing at copies of the main ones
+    let image = buffer.get_mut();
+    image[0x8000 + 144..0x8000 + 148].copy_from_slice(&19u32.to_le_bytes());
+    image[0x8000 + 152..0x8000 + 156].copy_from_slice(&21u32.to_be_bytes());
+
+    let reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let tables = reader.optional_path_tables();
+    assert_eq!(tables.len(), 2);
+    assert!(matches!(tables[0], IsoPathTable::LTable(_)));
+    assert!(matches!(tables[1], IsoPathTable::MTable(_)));
+    assert_eq!(tables[1].paths(), reader.path_table().paths());
+
+    // an M table location holding the L table does not agree
+    buffer.get_mut()[0x8000 + 152..0x8000 + 156].copy_from_slice(&19u32.to_be_bytes());
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.optional_path_tables().len(), 1);
+
+    let report = reader.verify().await.unwrap();
+    assert_eq!(
+        report.violations,
+        [Violation::OptionalPathTableMismatch { location: 19 }]
+    );
+}
+
+#[tokio::test]
+async fn owned_writer_in_task() {
+    let image = tokio::spawn(async move {
+        let mut writer = IsoFileWriter::new(Cursor::new(Vec::new()), IsoHeader::default())
+            .await
+            .unwrap();
+
+        for (path, content) in [("/a.txt", "A"), ("/dir/b.txt", "B")] {
+            tokio::task::yield_now().await;
+            writer
+                .append_file_owned(path, content.as_bytes().to_vec(), Utc::now())
+                .unwrap();
+        }
+
+        writer
+            .append_file_owned("/c.txt", Bytes::from_static(b"C"), Utc::now())
+            .unwrap();
+        writer.close().await.unwrap()
+    })
+    .await
+    .unwrap();
+
+    let mut reader = IsoFileReader::read(image).await.unwrap();
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"A");
+    assert_eq!(reader.read_file("/DIR/B.TXT").await.unwrap(), b"B");
+    assert_eq!(reader.read_file("/C.TXT").await.unwrap(), b"C");
+}
+
+#[tokio::test]
+async fn file_from_reader() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer
+        .append_file_from_reader("/big.bin", tokio::io::repeat(b'x'), 5000, Utc::now())
+        .unwrap();
+    writer
+        .append_file_from_reader("/dir/b.txt", &b"B"[..], 1, Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"A");
+    assert_eq!(
+        reader.read_file("/BIG.BIN").await.unwrap(),
+        vec![b'x'; 5000]
+    );
+    assert_eq!(reader.read_file("/DIR/B.TXT").await.unwrap(), b"B");
+
+    // a source shorter than its declared length
+    let mut writer = IsoFileWriter::new(Cursor::new(Vec::new()), IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file_from_reader("/short.bin", &b"abc"[..], 10, Utc::now())
+        .unwrap();
+
+    assert!(matches!(
+        writer.close().await.map_err(CloseError::into_error),
+        Err(IsoFileError::ShortSource {
+            length: 10,
+            actual: 3,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn file_from_path() {
+    let dir = std::env::temp_dir().join(format!("iso_file_source_{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+    tokio::fs::write(dir.join("a.txt"), b"first").await.unwrap();
+    tokio::fs::write(dir.join("b.bin"), vec![7u8; 3000])
+        .await
+        .unwrap();
+
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file_from_path("/a.txt", dir.join("a.txt"))
+        .await
+        .unwrap();
+    writer
+        .append_file_from_path("/dir/b.bin", dir.join("b.bin"))
+        .await
+        .unwrap();
+    assert!(
+        writer
+            .append_file_from_path("/c.txt", dir.join("missing"))
+            .await
+            .is_err()
+    );
+    writer.close().await.unwrap();
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"first");
+    assert_eq!(
+        reader.read_file("/DIR/B.BIN").await.unwrap(),
+        vec![7u8; 3000]
+    );
+}
+
+#[tokio::test]
+async fn dir_all() {
+    let dir = std::env::temp_dir().join(format!("iso_file_tree_{}", std::process::id()));
+
+    for (path, content) in [
+        ("a.txt", "A"),
+        ("b.log", "B"),
+        ("sub/c.txt", "C"),
+        ("sub/deep/d.txt", "D"),
+        ("tmp/e.txt", "E"),
+    ] {
+        let path = dir.join(path);
+        tokio::fs::create_dir_all(path.parent().unwrap())
+            .await
+            .unwrap();
+        tokio::fs::write(path, content).await.unwrap();
+    }
+
+    let modified = Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0).unwrap();
+    tokio::fs::create_dir(dir.join("empty")).await.unwrap();
+    std::fs::File::open(dir.join("sub"))
+        .unwrap()
+        .set_modified(modified.into())
+        .unwrap();
+
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    let mut filter = PathFilter::default();
+    filter.include("*.txt").exclude("tmp");
+
+    writer.append_dir_all("/data", &dir, &filter).await.unwrap();
+    writer.close().await.unwrap();
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/DATA/A.TXT").await.unwrap(), b"A");
+    assert_eq!(reader.read_file("/DATA/SUB/C.TXT").await.unwrap(), b"C");
+    assert_eq!(
+        reader.read_file("/DATA/SUB/DEEP/D.TXT").await.unwrap(),
+        b"D"
+    );
+    assert!(reader.read_file("/DATA/B.LOG").await.is_err());
+    assert!(reader.read_file("/DATA/TMP/E.TXT").await.is_err());
+
+    // directories keep their time and stay when empty
+    assert_eq!(
+        reader.metadata("/DATA/SUB").await.unwrap().datetime,
+        Some(modified)
+    );
+    assert!(reader.is_dir("/DATA/EMPTY").await);
+    assert!(!reader.exists("/DATA/TMP").await);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn dir_all_symlinks() {
+    let dir = std::env::temp_dir().join(format!("iso_file_links_{}", std::process::id()));
+
+    tokio::fs::create_dir_all(dir.join("sub")).await.unwrap();
+    tokio::fs::write(dir.join("sub/a.txt"), "A").await.unwrap();
+
+    // a link back to the top would recurse forever if it were followed
+    tokio::fs::symlink("..", dir.join("sub/loop"))
+        .await
+        .unwrap();
+    tokio::fs::symlink("sub/a.txt", dir.join("link.txt"))
+        .await
+        .unwrap();
+
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_dir_all("/data", &dir, &PathFilter::default())
+        .await
+        .unwrap();
+    writer.close().await.unwrap();
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/DATA/SUB/A.TXT").await.unwrap(), b"A");
+    assert!(!reader.exists("/DATA/SUB/LOOP").await);
+    assert!(!reader.exists("/DATA/LINK.TXT").await);
+}
+
+#[tokio::test]
+async fn empty_directories() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    let timestamp = Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0).unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.create_dir("/mnt", timestamp).unwrap();
+    writer.create_dir("/dir/empty", timestamp).unwrap();
+    writer.append_file("/dir/b.txt", b"B", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/DIR/B.TXT").await.unwrap(), b"B");
+
+    for path in ["/MNT", "/DIR/EMPTY"] {
+        assert!(reader.is_dir(path).await);
+
+        let entry = reader.entries().get(Path::new(path)).unwrap();
+        assert_eq!(entry.record().datetime().unwrap(), timestamp);
+
+        let mut walk = reader.entries().walk(true);
+        assert!(!walk.any(|(t, _)| t.starts_with(path) && t != Path::new(path)));
+    }
+}
+
+#[tokio::test]
+async fn file_from_stream() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    let chunks = (0..5u8).map(|t| Bytes::from(vec![t; 1000]));
+    writer
+        .append_file_from_stream("/a.bin", futures::stream::iter(chunks), 5000, Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let expected: Vec<u8> = (0..5u8).flat_map(|t| vec![t; 1000]).collect();
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/A.BIN").await.unwrap(), expected);
+
+    // a stream that ends before its declared length
+    let mut writer = IsoFileWriter::new(Cursor::new(Vec::new()), IsoHeader::default())
+        .await
+        .unwrap();
+
+    let chunks = futures::stream::iter([Bytes::from_static(b"abc")]);
+    writer
+        .append_file_from_stream("/short.bin", chunks, 10, Utc::now())
+        .unwrap();
+
+    assert!(matches!(
+        writer.close().await.map_err(CloseError::into_error),
+        Err(IsoFileError::ShortSource {
+            length: 10,
+            actual: 3,
+            ..
+        })
+    ));
+}
+
+// yields `remaining` bytes of 1, recording the largest read requested
+struct TrackingReader {
+    remaining: usize,
+    largest: Arc<AtomicUsize>,
+}
+
+impl tokio::io::AsyncRead for TrackingReader {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        _: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        self.largest.fetch_max(buf.remaining(), Ordering::Relaxed);
+
+        let len = self.remaining.min(buf.remaining());
+        buf.put_slice(&vec![1u8; len]);
+        self.remaining -= len;
+
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn sources_read_by_sector() {
+    let largest = Arc::new(AtomicUsize::new(0));
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    let source = TrackingReader {
+        remaining: 100_000,
+        largest: largest.clone(),
+    };
+
+    writer
+        .append_file_from_reader("/big.bin", source, 100_000, Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    assert_eq!(largest.load(Ordering::Relaxed), 2048);
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(
+        reader.read_file("/BIG.BIN").await.unwrap(),
+        vec![1u8; 100_000]
+    );
+}
+
+#[tokio::test]
+async fn seekable_writer() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    let big = vec![3u8; 5000];
+    let length = writer
+        .stream_file("/big.bin", &big[..], Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(length, 5000);
+
+    writer
+        .stream_file("/dir/b.txt", &b"B"[..], Utc::now())
+        .await
+        .unwrap();
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer.close_seekable().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/BIG.BIN").await.unwrap(), big);
+    assert_eq!(reader.read_file("/DIR/B.TXT").await.unwrap(), b"B");
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"A");
+
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+
+    // streamed data cannot be finished without seeking back
+    let mut writer = IsoFileWriter::new(Cursor::new(Vec::new()), IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .stream_file("/a.txt", &b"A"[..], Utc::now())
+        .await
+        .unwrap();
+
+    let error = writer.close().await.unwrap_err();
+    assert!(matches!(error.error(), IsoFileError::SeekRequired));
+
+    // but the writer comes back to be finished that way
+    let buffer = error.into_writer().close_seekable().await.unwrap();
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"A");
+}
+
+#[tokio::test]
+async fn estimated_size() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"A", Utc::now()).unwrap();
+    writer
+        .append_file_from_reader("/big.bin", tokio::io::repeat(1), 5000, Utc::now())
+        .unwrap();
+    writer.create_dir("/empty", Utc::now()).unwrap();
+
+    for t in 0..60 {
+        writer
+            .append_file(&format!("/dir/file{t}.txt"), b"content", Utc::now())
+            .unwrap();
+    }
+
+    let size = writer.estimated_size();
+    writer.close().await.unwrap();
+
+    assert_eq!(size, buffer.get_ref().len() as u64);
+}
+
+#[tokio::test]
+async fn reproducible_output() {
+    let epoch = Utc.with_ymd_and_hms(2001, 2, 3, 4, 5, 6).unwrap();
+    let files = [("/b.txt", "B"), ("/dir/c.txt", "C"), ("/a.txt", "A")];
+
+    let mut images = Vec::new();
+
+    for reversed in [false, true] {
+        let mut buffer = Cursor::new(Vec::new());
+
+        let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+            .await
+            .unwrap();
+
+        writer.set_reproducible(epoch);
+
+        let mut files = files.to_vec();
+
+        if reversed {
+            files.reverse();
+        }
+
+        for (path, content) in files {
+            writer.append_file(path, content.as_bytes(), epoch).unwrap();
+        }
+
+        writer.close().await.unwrap();
+        images.push(buffer.into_inner());
+    }
+
+    assert_eq!(images[0], images[1]);
+
+    let reader = IsoFileReader::read(Cursor::new(images.remove(0)))
+        .await
+        .unwrap();
+    assert_eq!(reader.header().volume_creation_date(), Some(epoch.into()));
+
+    let dir = reader.entries().get(Path::new("/DIR")).unwrap();
+    assert_eq!(dir.record().datetime().unwrap(), epoch);
+}
+
+#[tokio::test]
+async fn sorted_directory_records() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    for path in [
+        "/zeta.txt",
+        "/b/x.txt",
+        "/a.txt",
+        "/ab",
+        "/a_b.txt",
+        "/a/y.txt",
+    ] {
+        writer
+            .append_file(path, path.as_bytes(), Utc::now())
+            .unwrap();
+    }
+
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let raw = reader.read_dir_raw("/").await.unwrap();
+
+    let mut ids = Vec::new();
+    let mut offset = 0;
+
+    while offset < raw.len() && raw[offset] != 0 {
+        let length = raw[offset + 32] as usize;
+        ids.push(String::from_utf8_lossy(&raw[offset + 33..offset + 33 + length]).to_string());
+        offset += raw[offset] as usize;
+    }
+
+    // "A" pads to "A " which sorts before "AB" and "A_B"
+    assert_eq!(
+        ids[2..],
+        ["A", "A.TXT;1", "AB;1", "A_B.TXT;1", "B", "ZETA.TXT;1"]
+    );
+    assert_eq!(reader.read_file("/A/Y.TXT").await.unwrap(), b"/a/y.txt");
+    assert_eq!(reader.read_file("/B/X.TXT").await.unwrap(), b"/b/x.txt");
+}
+
+#[tokio::test]
+async fn sorted_path_table() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    for path in ["/b/z/deep/1.txt", "/b/a/2.txt", "/a/c/3.txt", "/c.txt"] {
+        writer.append_file(path, b"x", Utc::now()).unwrap();
+    }
+
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let table = reader.path_table();
+
+    let records: Vec<(&str, u16)> = table
+        .entries()
+        .iter()
+        .map(|t| (t.directory_id(), t.parent()))
+        .collect();
+
+    assert_eq!(
+        records[1..],
+        [
+            ("A", 1),
+            ("B", 1),
+            ("C", 2),
+            ("A", 3),
+            ("Z", 3),
+            ("DEEP", 6)
+        ]
+    );
+
+    let paths: Vec<PathBuf> = table.paths().into_iter().map(|t| t.0).collect();
+    assert_eq!(paths[6], Path::new("/B/Z/DEEP"));
+
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+}
+
+#[tokio::test]
+async fn interchange_levels() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.set_interchange_level(InterchangeLevel::Level1);
+    writer
+        .append_file("/long directory/report.final.html", b"A", Utc::now())
+        .unwrap();
+    writer
+        .append_file("/my-notes.markdown", b"B", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(
+        reader.read_file("/LONG_DIR/REPORT_F.HTM").await.unwrap(),
+        b"A"
+    );
+    assert_eq!(reader.read_file("/MY_NOTES.MAR").await.unwrap(), b"B");
+
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.set_interchange_level(InterchangeLevel::Level2);
+    writer
+        .append_file("/a very long file name for level two.txt", b"C", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let name = "/A_VERY_LONG_FILE_NAME_FOR_L.TXT";
+    assert_eq!(reader.read_file(name).await.unwrap(), b"C");
+
+    // nine directory levels including the root
+    let mut writer = IsoFileWriter::new(Cursor::new(Vec::new()), IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.set_interchange_level(InterchangeLevel::Level3);
+    writer
+        .append_file("/1/2/3/4/5/6/7/a.txt", b"D", Utc::now())
+        .unwrap();
+
+    assert!(matches!(
+        writer.create_dir("/1/2/3/4/5/6/7/8", Utc::now()),
+        Err(IsoFileError::DepthLimit(8))
+    ));
+}
+
+#[tokio::test]
+async fn mangled_names() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.set_interchange_level(InterchangeLevel::Level1);
+    writer
+        .append_file("/longname one.txt", b"1", Utc::now())
+        .unwrap();
+    writer
+        .append_file("/longname two.txt", b"2", Utc::now())
+        .unwrap();
+    writer
+        .append_file("/project alpha/a.txt", b"3", Utc::now())
+        .unwrap();
+    writer
+        .append_file("/project beta/a.txt", b"4", Utc::now())
+        .unwrap();
+    writer
+        .append_file("/project alpha/b.txt", b"5", Utc::now())
+        .unwrap();
+
+    let mapping = writer.name_mapping();
+    assert_eq!(mapping["/longname one.txt"], Path::new("/LONGNAME.TXT"));
+    assert_eq!(mapping["/longname two.txt"], Path::new("/LONGNA~1.TXT"));
+    assert_eq!(mapping["/project beta"], Path::new("/PROJEC~1"));
+    assert_eq!(
+        mapping["/project alpha/b.txt"],
+        Path::new("/PROJECT_/B.TXT")
+    );
+
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/LONGNAME.TXT").await.unwrap(), b"1");
+    assert_eq!(reader.read_file("/LONGNA~1.TXT").await.unwrap(), b"2");
+    assert_eq!(reader.read_file("/PROJECT_/A.TXT").await.unwrap(), b"3");
+    assert_eq!(reader.read_file("/PROJEC~1/A.TXT").await.unwrap(), b"4");
+    assert_eq!(reader.read_file("/PROJECT_/B.TXT").await.unwrap(), b"5");
+}
+
+#[tokio::test]
+async fn transliterated_names() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/naïve.txt", b"0", Utc::now()).unwrap();
+    writer.set_transliterate(true);
+    writer.append_file("/café.txt", b"1", Utc::now()).unwrap();
+    writer
+        .append_file("/my file.txt", b"2", Utc::now())
+        .unwrap();
+    writer
+        .append_file("/straße/Ærø.txt", b"3", Utc::now())
+        .unwrap();
+
+    let mapping = writer.name_mapping();
+    assert_eq!(mapping["/naïve.txt"], Path::new("/NAVE.TXT"));
+    assert_eq!(mapping["/café.txt"], Path::new("/CAFE.TXT"));
+    assert_eq!(mapping["/my file.txt"], Path::new("/MY_FILE.TXT"));
+    assert_eq!(mapping["/straße/Ærø.txt"], Path::new("/STRASSE/AERO.TXT"));
+
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/CAFE.TXT").await.unwrap(), b"1");
+    assert_eq!(reader.read_file("/MY_FILE.TXT").await.unwrap(), b"2");
+    assert_eq!(reader.read_file("/STRASSE/AERO.TXT").await.unwrap(), b"3");
+}
+
+#[tokio::test]
+async fn relaxed_names() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.set_allow_lowercase(true);
+    writer.append_file("/Readme.md", b"1", Utc::now()).unwrap();
+    writer.set_allow_full_ascii(true);
+    writer.append_file("/a b#c.txt", b"2", Utc::now()).unwrap();
+    writer.set_untranslated_names(true);
+    writer
+        .append_file("/Über Ñame.txt", b"3", Utc::now())
+        .unwrap();
+
+    let mapping = writer.name_mapping();
+    assert_eq!(mapping["/Readme.md"], Path::new("/Readme.md"));
+    assert_eq!(mapping["/a b#c.txt"], Path::new("/a b#c.txt"));
+    assert_eq!(mapping["/Über Ñame.txt"], Path::new("/Über Ñame.txt"));
+
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/Readme.md").await.unwrap(), b"1");
+    assert_eq!(reader.read_file("/a b#c.txt").await.unwrap(), b"2");
+    assert_eq!(reader.read_file("/Über Ñame.txt").await.unwrap(), b"3");
+}
+
+#[tokio::test]
+async fn omitted_version() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/KEPT.TXT", b"1", Utc::now()).unwrap();
+    writer.set_omit_version(true);
+    writer.append_file("/BARE.TXT", b"2", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    let image = buffer.get_ref();
+    let contains = |id: &[u8]| image.windows(id.len()).any(|t| t == id);
+    assert!(contains(b"KEPT.TXT;1"));
+    assert!(contains(b"BARE.TXT"));
+    assert!(!contains(b"BARE.TXT;1"));
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/KEPT.TXT").await.unwrap(), b"1");
+    assert_eq!(reader.read_file("/BARE.TXT").await.unwrap(), b"2");
+
+    let entry = reader.entries().get(Path::new("/BARE.TXT")).unwrap();
+    assert!(entry.entry().is_file());
+    assert_eq!(entry.version(), None);
+
+    // names leave the version to the record
+    assert_eq!(entry.entry().name(), "BARE.TXT");
+    let entry = reader.entries().get(Path::new("/KEPT.TXT")).unwrap();
+    assert_eq!(
+        (entry.entry().name(), entry.version()),
+        ("KEPT.TXT".into(), Some(1))
+    );
+}
+
+#[tokio::test]
+async fn written_hidden_entries() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/LICENSE.TXT", b"1", Utc::now())
+        .unwrap();
+    writer.append_file("/README.TXT", b"2", Utc::now()).unwrap();
+    writer.create_dir("/META", Utc::now()).unwrap();
+    writer
+        .append_file("/META/INFO.TXT", b"3", Utc::now())
+        .unwrap();
+    writer.set_hidden("/LICENSE.TXT", true).unwrap();
+    writer.set_hidden("/META", true).unwrap();
+    assert!(matches!(
+        writer.set_hidden("/MISSING.TXT", true),
+        Err(IsoFileError::FileNotFound)
+    ));
+    writer.close().await.unwrap();
+
+    let reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let entries = reader.entries();
+    assert!(entries.get(Path::new("/LICENSE.TXT")).unwrap().is_hidden());
+    assert!(entries.get(Path::new("/META")).unwrap().is_hidden());
+    assert!(!entries.get(Path::new("/README.TXT")).unwrap().is_hidden());
+
+    let mut options = IsoReadOptions::default();
+    options.set_hidden(false);
+
+    let mut reader = IsoFileReader::read_with_options(buffer, options)
+        .await
+        .unwrap();
+    assert!(!reader.exists("/LICENSE.TXT").await);
+    assert!(!reader.exists("/META/INFO.TXT").await);
+    assert!(reader.is_file("/README.TXT").await);
+}
+
+#[tokio::test]
+async fn timestamps_with_offset() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    let offset = FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap();
+    let local = offset.with_ymd_and_hms(2024, 3, 1, 9, 15, 0).unwrap();
+
+    writer.append_file("/LOCAL.TXT", b"1", local).unwrap();
+    writer
+        .append_file("/UTC.TXT", b"2", local.with_timezone(&Utc))
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let reader = IsoFileReader::read(buffer).await.unwrap();
+    let entries = reader.entries();
+
+    let datetime = |path: &str| {
+        entries
+            .get(Path::new(path))
+            .unwrap()
+            .record()
+            .datetime_with_offset()
+            .unwrap()
+    };
+
+    assert_eq!(datetime("/LOCAL.TXT"), local);
+    assert_eq!(datetime("/LOCAL.TXT").offset(), &offset);
+    assert_eq!(datetime("/UTC.TXT"), local);
+    assert_eq!(datetime("/UTC.TXT").offset().local_minus_utc(), 0);
+}
+
+#[tokio::test]
+async fn volume_sequence_numbers() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut header = IsoHeader::default();
+    header.set_volume_set_size(3);
+    header.set_volume_sequence_number(2);
+
+    let mut writer = IsoFileWriter::new(&mut buffer, header).await.unwrap();
+    writer
+        .append_file("/DIR/HERE.TXT", b"1", Utc::now())
+        .unwrap();
+    writer.append_file("/THERE.TXT", b"2", Utc::now()).unwrap();
+    writer.set_volume_sequence_number("/THERE.TXT", 3).unwrap();
+    writer.close().await.unwrap();
+
+    let reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    assert_eq!(reader.header().volume_set_size(), 3);
+    assert_eq!(reader.header().volume_sequence_number(), 2);
+
+    let entries = reader.entries();
+    let volume = |path: &str| {
+        let entry = entries.get(Path::new(path)).unwrap();
+        entry.record().volume_seq_number()
+    };
+
+    assert_eq!(volume("/DIR"), 2);
+    assert_eq!(volume("/DIR/HERE.TXT"), 2);
+    assert_eq!(volume("/THERE.TXT"), 3);
+
+    // both byte orders of the root record
+    let root = &buffer.get_ref()[16 * 2048 + 156..16 * 2048 + 190];
+    assert_eq!(&root[28..32], &[2, 0, 0, 2]);
+
+    // volume 1 of 1 when the header leaves them unset
+    let mut buffer = Cursor::new(Vec::new());
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+    writer.append_file("/A.TXT", b"1", Utc::now()).unwrap();
+    writer.close().await.unwrap();
+
+    let reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.header().volume_sequence_number(), 1);
+    let entry = reader.entries().get(Path::new("/A.TXT")).unwrap();
+    assert_eq!(entry.record().volume_seq_number(), 1);
+}
+
+#[tokio::test]
+async fn written_block_sizes() {
+    for block_size in [512u16, 1024] {
+        let mut buffer = Cursor::new(Vec::new());
+
+        let mut header = IsoHeader::default();
+        header.set_logical_block_size(block_size);
+
+        let mut writer = IsoFileWriter::new(&mut buffer, header).await.unwrap();
+        writer
+            .append_file("/A.TXT", &[b'a'; 700], Utc::now())
+            .unwrap();
+        writer.append_file("/DIR/B.TXT", b"b", Utc::now()).unwrap();
+
+        for n in 0..60 {
+            let path = format!("/MANY/FILE{n:02}.TXT");
+            writer
+                .append_file_owned(&path, vec![n as u8; 3], Utc::now())
+                .unwrap();
+        }
+
+        let estimated = writer.estimated_size();
+        writer.close().await.unwrap();
+        assert_eq!(buffer.get_ref().len() as u64, estimated);
+
+        let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+        let header = reader.header();
+        assert_eq!(header.logical_block_size(), block_size);
+        assert_eq!(
+            header.volume_space_size as usize * block_size as usize,
+            buffer.get_ref().len()
+        );
+
+        assert_eq!(reader.read_file("/A.TXT").await.unwrap(), [b'a'; 700]);
+        assert_eq!(reader.read_file("/DIR/B.TXT").await.unwrap(), b"b");
+        assert_eq!(
+            reader.read_file("/MANY/FILE59.TXT").await.unwrap(),
+            [59u8; 3]
+        );
+
+        let report = reader.verify().await.unwrap();
+        assert!(report.is_ok(), "{:?}", report.violations);
+    }
+
+    let mut header = IsoHeader::default();
+    header.set_logical_block_size(1000);
+
+    let writer = IsoFileWriter::new(Cursor::new(Vec::new()), header)
+        .await
+        .unwrap();
+    assert!(matches!(
+        writer.close().await.map_err(CloseError::into_error),
+        Err(IsoFileError::InvalidLogicalBlockSize(1000))
+    ));
+}
+
+#[tokio::test]
+async fn reserved_sectors_and_large_path_table() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+    writer.set_reserved_sectors(4);
+
+    // 38 bytes per path table record, over two sectors in total
+    for n in 0..120 {
+        let path = format!("/DIRECTORY_WITH_A_LONG_NAME_{n:03}/FILE.TXT");
+        writer
+            .append_file_owned(&path, vec![n as u8; 2], Utc::now())
+            .unwrap();
+    }
+
+    let estimated = writer.estimated_size();
+    writer.close().await.unwrap();
+    assert_eq!(buffer.get_ref().len() as u64, estimated);
+
+    // nothing written to the reserved sectors
+    let image = buffer.get_ref();
+    assert!(image[19 * 2048..23 * 2048].iter().all(|&t| t == 0));
+
+    let mut reader = IsoFileReader::read(buffer.clone()).await.unwrap();
+    let header = reader.header();
+    assert_eq!(header.loc_of_type_l_path_table, 23);
+    assert_eq!(header.loc_of_type_m_path_table, 26);
+    assert!(header.path_table_size > 2 * 2048);
+    assert_eq!(reader.path_table().entries().len(), 121);
+
+    assert_eq!(
+        reader
+            .read_file("/DIRECTORY_WITH_A_LONG_NAME_119/FILE.TXT")
+            .await
+            .unwrap(),
+        [119u8; 2]
+    );
+
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+}
+
+#[tokio::test]
+async fn thousands_of_directories() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    for a in 0..40 {
+        for b in 0..75 {
+            let path = format!("/D{a:02}/S{b:02}/F.TXT");
+            writer
+                .append_file_owned(&path, format!("{a}-{b}"), Utc::now())
+                .unwrap();
+        }
+    }
+
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.path_table().entries().len(), 1 + 40 + 40 * 75);
+    assert_eq!(reader.read_file("/D00/S00/F.TXT").await.unwrap(), b"0-0");
+    assert_eq!(reader.read_file("/D17/S42/F.TXT").await.unwrap(), b"17-42");
+    assert_eq!(reader.read_file("/D39/S74/F.TXT").await.unwrap(), b"39-74");
+
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+}
+
+#[tokio::test]
+async fn relocated_deep_directories() {
+    let path = "/A/B/C/D/E/F/G/H/I/J/DEEP.TXT";
+
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.set_interchange_level(InterchangeLevel::Level1);
+    writer.set_deep_directories(DeepDirectories::Relocate);
+    writer.append_file(path, b"DEEP", Utc::now()).unwrap();
+    writer
+        .append_file("/A/B/C/D/E/F/G/H/I/OTHER.TXT", b"OTHER", Utc::now())
+        .unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file(path).await.unwrap(), b"DEEP");
+    assert_eq!(
+        reader
+            .read_file("/A/B/C/D/E/F/G/H/I/OTHER.TXT")
+            .await
+            .unwrap(),
+        b"OTHER"
+    );
+    assert!(reader.exists("/RR_MOVED").await);
+    assert!(!reader.exists("/RR_MOVED/H").await);
+
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+
+    let mut writer = IsoFileWriter::new(Cursor::new(Vec::new()), IsoHeader::default())
+        .await
+        .unwrap();
+
+    // kept while appended, rejected once a level is chosen
+    writer.append_file(path, b"DEEP", Utc::now()).unwrap();
+    writer.set_interchange_level(InterchangeLevel::Level1);
+
+    assert!(matches!(
+        writer.close().await.map_err(CloseError::into_error),
+        Err(IsoFileError::DepthLimit(8))
+    ));
+}
+
+#[tokio::test]
+async fn trailing_padding() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/A.TXT", b"A", Utc::now()).unwrap();
+    let unpadded = writer.estimated_size();
+
+    writer.set_padding(150, 16);
+    let estimated = writer.estimated_size();
+    writer.close().await.unwrap();
+
+    let image = buffer.into_inner();
+    assert_eq!(image.len() as u64, estimated);
+    assert!(estimated >= unpadded + 150 * 2048);
+    assert_eq!(image.len() % (16 * 2048), 0);
+    assert!(image[unpadded as usize..].iter().all(|t| *t == 0));
+
+    let mut reader = IsoFileReader::read(Cursor::new(image)).await.unwrap();
+    assert_eq!(reader.header().volume_space_size as u64 * 2048, estimated);
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"A");
+}
+
+#[tokio::test]
+async fn aligned_extents() {
+    let big = vec![3u8; 5000];
+
+    for seekable in [false, true] {
+        let mut buffer = Cursor::new(Vec::new());
+
+        let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+            .await
+            .unwrap();
+
+        writer.set_extent_alignment(64 * 1024);
+
+        if seekable {
+            writer
+                .stream_file("/STREAMED.BIN", &big[..], Utc::now())
+                .await
+                .unwrap();
+        }
+
+        writer.append_file("/A.TXT", b"A", Utc::now()).unwrap();
+        writer
+            .append_file("/DIR/BIG.BIN", &big, Utc::now())
+            .unwrap();
+
+        let estimated = writer.estimated_size();
+
+        if seekable {
+            writer.close_seekable().await.unwrap();
+        } else {
+            writer.close().await.unwrap();
+        }
+
+        assert_eq!(buffer.get_ref().len() as u64, estimated);
+
+        let mut reader = IsoFileReader::read(buffer).await.unwrap();
+
+        let streamed = seekable.then_some("/STREAMED.BIN");
+
+        for path in ["/A.TXT", "/DIR/BIG.BIN"].into_iter().chain(streamed) {
+            let location = reader.metadata(path).await.unwrap().location;
+            assert_eq!(location * 2048 % (64 * 1024), 0, "{path}");
+        }
+
+        assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"A");
+        assert_eq!(reader.read_file("/DIR/BIG.BIN").await.unwrap(), big);
+
+        let report = reader.verify().await.unwrap();
+        assert!(report.is_ok(), "{:?}", report.violations);
+    }
+}
+
+#[tokio::test]
+async fn hard_links() {
+    let content = vec![7u8; 5000];
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/A/X.TXT", &content, Utc::now())
+        .unwrap();
+    let unlinked = writer.estimated_size();
+
+    writer.append_hard_link("/A/X.TXT", "/B/Y.TXT").unwrap();
+    writer.append_hard_link("/B/Y.TXT", "/Z.TXT").unwrap();
+    writer.create_dir("/C", Utc::now()).unwrap();
+
+    assert!(matches!(
+        writer.append_hard_link("/C", "/D"),
+        Err(IsoFileError::EntryDirectory)
+    ));
+    assert!(matches!(
+        writer.append_hard_link("/MISSING", "/E"),
+        Err(IsoFileError::FileNotFound)
+    ));
+
+    // only the directory of the link takes a sector
+    assert_eq!(writer.estimated_size(), unlinked + 2 * 2048);
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let location = |path: &str| {
+        reader
+            .entries()
+            .get(Path::new(path))
+            .unwrap()
+            .metadata()
+            .location
+    };
+    assert_eq!(location("/A/X.TXT"), location("/B/Y.TXT"));
+    assert_eq!(location("/A/X.TXT"), location("/Z.TXT"));
+
+    for path in ["/A/X.TXT", "/B/Y.TXT", "/Z.TXT"] {
+        assert_eq!(reader.read_file(path).await.unwrap(), content);
+    }
+
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+}
+
+#[tokio::test]
+async fn zero_filled_files() {
+    let mut content = vec![0u8; 64 * 1024 + 100];
+    content[5000] = 1;
+
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.set_reserved_sectors(3);
+    writer.set_padding(150, 16);
+    writer
+        .append_file("/DISK.IMG", &content, Utc::now())
+        .unwrap();
+    writer
+        .append_file_from_reader("/EMPTY.IMG", &[0u8; 3000][..], 3000, Utc::now())
+        .unwrap();
+
+    let estimated = writer.estimated_size();
+    writer.close().await.unwrap();
+    assert_eq!(buffer.get_ref().len() as u64, estimated);
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/DISK.IMG").await.unwrap(), content);
+    assert_eq!(reader.read_file("/EMPTY.IMG").await.unwrap(), [0u8; 3000]);
+
+    // all-zero contents of as many blocks point at one extent
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file_owned("/A.IMG", vec![0u8; 8192], Utc::now())
+        .unwrap();
+    let unshared = writer.estimated_size();
+    writer
+        .append_file_owned("/B.IMG", vec![0u8; 8000], Utc::now())
+        .unwrap();
+    writer
+        .append_file_owned("/C.IMG", vec![0u8; 9000], Utc::now())
+        .unwrap();
+    assert_eq!(writer.estimated_size(), unshared + 5 * 2048);
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let a = reader.metadata("/A.IMG").await.unwrap().location;
+    let b = reader.metadata("/B.IMG").await.unwrap().location;
+    let c = reader.metadata("/C.IMG").await.unwrap().location;
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    assert_eq!(reader.read_file("/B.IMG").await.unwrap(), [0u8; 8000]);
+    assert_eq!(reader.read_file("/C.IMG").await.unwrap(), [0u8; 9000]);
+
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+}
+
+#[tokio::test]
+async fn sort_weights() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer
+        .append_file("/A.TXT", &[1u8; 3000], Utc::now())
+        .unwrap();
+    writer
+        .append_file("/BOOT/KERNEL", &[2u8; 5000], Utc::now())
+        .unwrap();
+    writer.append_file("/B.TXT", b"B", Utc::now()).unwrap();
+    writer
+        .append_file("/BOOT/LOADER", b"L", Utc::now())
+        .unwrap();
+    writer.set_sort_weight("/BOOT/LOADER", 20).unwrap();
+    writer.set_sort_weight("/BOOT/KERNEL", 10).unwrap();
+    assert!(matches!(
+        writer.set_sort_weight("/MISSING", 1),
+        Err(IsoFileError::FileNotFound)
+    ));
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let location = |path: &str| {
+        reader
+            .entries()
+            .get(Path::new(path))
+            .unwrap()
+            .metadata()
+            .location
+    };
+    let (loader, kernel, a, b) = (
+        location("/BOOT/LOADER"),
+        location("/BOOT/KERNEL"),
+        location("/A.TXT"),
+        location("/B.TXT"),
+    );
+    assert_eq!(kernel, loader + 1);
+    assert_eq!(a, kernel + 3);
+    assert_eq!(b, a + 2);
+
+    assert_eq!(reader.read_file("/BOOT/KERNEL").await.unwrap(), [2u8; 5000]);
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), [1u8; 3000]);
+
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+}
+
+#[tokio::test]
+async fn pinned_locations() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/A.TXT", b"A", Utc::now()).unwrap();
+    writer
+        .append_file("/PAYLOAD.BIN", &[5u8; 5000], Utc::now())
+        .unwrap();
+    writer
+        .append_file("/B.TXT", &[6u8; 4096], Utc::now())
+        .unwrap();
+    writer.append_file("/C.TXT", b"C", Utc::now()).unwrap();
+    writer.set_location("/PAYLOAD.BIN", 25).unwrap();
+    writer.set_location("/C.TXT", 100).unwrap();
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    let location = |path: &str| {
+        reader
+            .entries()
+            .get(Path::new(path))
+            .unwrap()
+            .metadata()
+            .location
+    };
+    // A takes block 24, B does not fit before the payload and follows it
+    assert_eq!(location("/A.TXT"), 24);
+    assert_eq!(location("/PAYLOAD.BIN"), 25);
+    assert_eq!(location("/B.TXT"), 28);
+    assert_eq!(location("/C.TXT"), 100);
+    assert_eq!(reader.header().volume_space_size, 101);
+
+    assert_eq!(reader.read_file("/PAYLOAD.BIN").await.unwrap(), [5u8; 5000]);
+    assert_eq!(reader.read_file("/B.TXT").await.unwrap(), [6u8; 4096]);
+    assert_eq!(reader.read_file("/C.TXT").await.unwrap(), b"C");
+
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+
+    // taken by the directories or by another pinned file
+    for (path, location) in [("/B.TXT", 23), ("/B.TXT", 26)] {
+        let mut writer = IsoFileWriter::new(Cursor::new(Vec::new()), IsoHeader::default())
+            .await
+            .unwrap();
+
+        writer
+            .append_file("/PAYLOAD.BIN", &[5u8; 5000], Utc::now())
+            .unwrap();
+        writer.append_file("/B.TXT", b"B", Utc::now()).unwrap();
+        writer.set_location("/PAYLOAD.BIN", 25).unwrap();
+        writer.set_location(path, location).unwrap();
+
+        assert!(matches!(
+            writer.close().await.map_err(CloseError::into_error),
+            Err(IsoFileError::LocationConflict { location: t, .. }) if t == location
+        ));
+    }
+}
+
+#[tokio::test]
+async fn write_progress() {
+    for seekable in [false, true] {
+        let mut buffer = Cursor::new(Vec::new());
+
+        let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+            .await
+            .unwrap();
+
+        let mut progress = writer.subscribe_progress();
+
+        if seekable {
+            writer
+                .stream_file("/STREAMED.BIN", &[1u8; 5000][..], Utc::now())
+                .await
+                .unwrap();
+        }
+
+        writer
+            .append_file("/DIR/A.TXT", &[2u8; 3000], Utc::now())
+            .unwrap();
+        writer
+            .append_file_from_reader("/B.TXT", &[3u8; 10][..], 10, Utc::now())
+            .unwrap();
+        writer.set_padding(150, 16);
+
+        if seekable {
+            writer.close_seekable().await.unwrap();
+        } else {
+            writer.close().await.unwrap();
+        }
+
+        let length = buffer.get_ref().len() as u64;
+
+        // the sender is gone with the writer, the last update stays
+        let last = progress.borrow_and_update();
+        assert!(last.has_changed());
+        assert_eq!(
+            *last,
+            WriteProgress {
+                path: None,
+                written: length,
+                total: length,
+            }
+        );
+    }
+}
+
+#[tokio::test]
+async fn cancelled_close() {
+    let flag = Arc::new(AtomicBool::new(false));
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.set_cancel_flag(flag.clone());
+    let progress = writer.subscribe_progress();
+
+    let setter = flag.clone();
+    let chunks = futures::stream::iter(vec![Bytes::from(vec![1u8; 2048]); 4]).map(move |t| {
+        setter.store(true, Ordering::Relaxed);
+        t
+    });
+
+    writer.append_file("/A.TXT", b"A", Utc::now()).unwrap();
+    writer
+        .append_file_from_stream("/B.BIN", chunks, 4 * 2048, Utc::now())
+        .unwrap();
+
+    let error = writer.close().await.unwrap_err();
+    assert!(matches!(error.error(), IsoFileError::Cancelled));
+
+    let written = progress.borrow().clone();
+    assert_eq!(written.path.as_deref(), Some(Path::new("/B.BIN")));
+    assert!(written.written < written.total);
+
+    // latched even once the flag is cleared
+    flag.store(false, Ordering::Relaxed);
+    let error = error.into_writer().close().await.unwrap_err();
+    assert!(matches!(error.error(), IsoFileError::Cancelled));
+
+    // the sink holds what was written until then
+    let buffer = error.into_writer().into_inner();
+    assert_eq!(buffer.get_ref().len() as u64, written.written);
+}
+
+#[tokio::test]
+async fn validated_paths() {
+    let mut writer = IsoFileWriter::new(Cursor::new(Vec::new()), IsoHeader::default())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        writer
+            .append_file("/docs/read me.txt", b"R", Utc::now())
+            .unwrap(),
+        Path::new("/DOCS/README.TXT")
+    );
+    assert_eq!(
+        writer.create_dir("/docs/", Utc::now()).unwrap(),
+        Path::new("/DOCS")
+    );
+
+    for path in ["", "/", "//"] {
+        assert!(matches!(
+            writer.append_file(path, b"E", Utc::now()),
+            Err(IsoFileError::EmptyPath)
+        ));
+    }
+
+    for (path, name) in [("/a/../b", ".."), ("/./b", "."), ("/a\tb", "a\tb")] {
+        assert!(matches!(
+            writer.append_file(path, b"I", Utc::now()),
+            Err(IsoFileError::InvalidName(t)) if t == name
+        ));
+    }
+
+    writer.set_deep_directories(DeepDirectories::Reject);
+    writer
+        .append_file("/1/2/3/4/5/6/7/a.txt", b"D", Utc::now())
+        .unwrap();
+
+    assert!(matches!(
+        writer.append_file("/1/2/3/4/5/6/7/8/a.txt", b"D", Utc::now()),
+        Err(IsoFileError::DepthLimit(8))
+    ));
+
+    // nothing rejected was registered
+    assert_eq!(writer.name_mapping().len(), 10);
+    writer.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn duplicate_paths() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/dir/a.txt", b"A", Utc::now()).unwrap();
+    writer.append_file("/file", b"F", Utc::now()).unwrap();
+    writer.create_dir("/dir", Utc::now()).unwrap();
+
+    let duplicates = [
+        ("/dir/a.txt", false, "/DIR/A.TXT"),
+        ("dir//a.txt", false, "/DIR/A.TXT"),
+        ("/dir", true, "/DIR"),
+        ("/dir", false, "/DIR"),
+        ("/file/b.txt", false, "/FILE"),
+    ];
+
+    for (path, is_dir, stored) in duplicates {
+        let result = if is_dir {
+            writer.create_dir(path, Utc::now())
+        } else {
+            writer.append_file(path, b"B", Utc::now())
+        };
+
+        assert!(
+            matches!(&result, Err(IsoFileError::DuplicatePath(t)) if t == Path::new(stored)),
+            "{path}: {result:?}"
+        );
+    }
+
+    // other names are mangled apart
+    assert_eq!(
+        writer.append_file("/DIR/a.txt", b"C", Utc::now()).unwrap(),
+        Path::new("/DIR~1/A.TXT")
+    );
+
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/DIR/A.TXT").await.unwrap(), b"A");
+
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+}
+
+#[tokio::test]
+async fn overwritten_paths() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/dir/a.txt", b"A", Utc::now()).unwrap();
+    writer.append_file("/file", b"F", Utc::now()).unwrap();
+    writer.append_file("/keep.txt", b"K", Utc::now()).unwrap();
+
+    writer.set_overwrite(true);
+
+    // later layers win
+    assert_eq!(
+        writer.append_file("/dir/a.txt", b"B", Utc::now()).unwrap(),
+        Path::new("/DIR/A.TXT")
+    );
+    writer.append_file("/file/b.txt", b"C", Utc::now()).unwrap();
+    writer.append_file("/dir", b"D", Utc::now()).unwrap();
+
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/DIR").await.unwrap(), b"D");
+    assert!(reader.read_file("/DIR/A.TXT").await.is_err());
+    assert_eq!(reader.read_file("/FILE/B.TXT").await.unwrap(), b"C");
+    assert_eq!(reader.read_file("/KEEP.TXT").await.unwrap(), b"K");
+
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
+}
+
+#[tokio::test]
+async fn closed_sink() {
+    let mut writer = IsoFileWriter::new(Cursor::new(Vec::new()), IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/A.TXT", b"A", Utc::now()).unwrap();
+    let estimated = writer.estimated_size();
+
+    let mut buffer = writer.close().await.unwrap();
+    assert_eq!(buffer.get_ref().len() as u64, estimated);
+
+    buffer.set_position(0);
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"A");
+}
+
+#[tokio::test]
+async fn overwritten_links() {
+    let mut buffer = Cursor::new(Vec::new());
+
+    let mut writer = IsoFileWriter::new(&mut buffer, IsoHeader::default())
+        .await
+        .unwrap();
+
+    writer.append_file("/a.txt", b"01234", Utc::now()).unwrap();
+    writer.append_hard_link("/a.txt", "/b.txt").unwrap();
+    writer.append_file("/z.txt", b"ZZZ", Utc::now()).unwrap();
+
+    writer.set_overwrite(true);
+
+    // links follow a replaced file
+    writer
+        .append_file("/a.txt", b"0123456789", Utc::now())
+        .unwrap();
+
+    // but keep it from being removed
+    for result in [
+        writer.create_dir("/a.txt", Utc::now()),
+        writer.append_file("/a.txt/c.txt", b"C", Utc::now()),
+        writer.append_hard_link("/b.txt", "/a.txt"),
+    ] {
+        assert!(
+            matches!(&result, Err(IsoFileError::LinkTarget(t)) if t == Path::new("/A.TXT")),
+            "{result:?}"
+        );
+    }
+
+    writer.close().await.unwrap();
+
+    let mut reader = IsoFileReader::read(buffer).await.unwrap();
+    assert_eq!(reader.read_file("/A.TXT").await.unwrap(), b"0123456789");
+    assert_eq!(reader.read_file("/B.TXT").await.unwrap(), b"0123456789");
+    assert_eq!(reader.read_file("/Z.TXT").await.unwrap(), b"ZZZ");
 
-    let reader = IsoFileReader::read(&mut buffer2).await.unwrap();
+    let report = reader.verify().await.unwrap();
+    assert!(report.is_ok(), "{:?}", report.violations);
 }
@@ -0,0 +1,25 @@
+//! `TRANS.TBL` files left by mkisofs, mapping ISO 9660 names to original names.
+
+/// Pairs of ISO 9660 identifier and original name, one per line of the form
+/// `F NAME.TXT;1    name.txt`. Link lines carry their target after `-->`.
+pub(crate) fn parse(content: &[u8]) -> Vec<(String, String)> {
+    String::from_utf8_lossy(content)
+        .lines()
+        .filter_map(|line| {
+            let (kind, rest) = line.split_once(' ')?;
+
+            if kind.len() != 1 {
+                return None;
+            }
+
+            let (id, name) = rest.trim_start().split_once(char::is_whitespace)?;
+            let name = name.trim_start().trim_end_matches('\0');
+            let name = match kind {
+                "L" => name.split_once("-->").map_or(name, |t| t.0).trim_end(),
+                _ => name,
+            };
+
+            (!name.is_empty()).then(|| (id.to_string(), name.to_string()))
+        })
+        .collect()
+}
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Offset, TimeZone, Timelike, Utc};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeZone, Timelike, Utc};
 
 use crate::{IsoFileError, Result};
 
@@ -22,6 +22,11 @@ impl LsbMsb<u16> {
             msb: lsb.to_be(),
         }
     }
+
+    /// Both halves hold the same value.
+    pub fn is_consistent(&self) -> bool {
+        self.msb == self.lsb.to_be()
+    }
 }
 
 impl LsbMsb<u32> {
@@ -31,6 +36,11 @@ impl LsbMsb<u32> {
             msb: lsb.to_be(),
         }
     }
+
+    /// Both halves hold the same value.
+    pub fn is_consistent(&self) -> bool {
+        self.msb == self.lsb.to_be()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -76,46 +86,57 @@ impl Default for DecDateTime {
     }
 }
 
-impl TryInto<DateTime<Utc>> for DecDateTime {
+impl TryInto<DateTime<FixedOffset>> for DecDateTime {
     type Error = IsoFileError;
 
-    fn try_into(self) -> Result<DateTime<Utc>> {
-        let year_str = String::from_utf8_lossy(&self.year);
-        let month_str = String::from_utf8_lossy(&self.month);
-        let day_str = String::from_utf8_lossy(&self.day);
-        let hour_str = String::from_utf8_lossy(&self.hour);
-        let minute_str = String::from_utf8_lossy(&self.minute);
-        let second_str = String::from_utf8_lossy(&self.second);
-        let milli_str = String::from_utf8_lossy(&self.milli);
-
-        let tz_offset_str = String::from_utf8_lossy(&self.tz_offset);
-        let tz_offset = tz_offset_str
-            .parse::<i32>()
-            .map_err(|_| IsoFileError::InvalidTimezone)?;
-
-        // Convert 15-minute intervals to seconds
+    fn try_into(self) -> Result<DateTime<FixedOffset>> {
+        let field = |digits: &[u8]| -> Result<u32> {
+            std::str::from_utf8(digits)
+                .ok()
+                .and_then(|t| t.parse().ok())
+                .ok_or(IsoFileError::InvalidDatetime)
+        };
+
+        // Offset from GMT in 15-minute intervals, as a signed byte, positive east
+        let tz_offset = self.tz_offset[0] as i8 as i32;
         let fixed_offset =
-            FixedOffset::west_opt(tz_offset * 15 * 60).ok_or(IsoFileError::InvalidTimezone)?;
+            FixedOffset::east_opt(tz_offset * 15 * 60).ok_or(IsoFileError::InvalidTimezone)?;
+
+        let naive_datetime = NaiveDate::from_ymd_opt(
+            field(&self.year)? as i32,
+            field(&self.month)?,
+            field(&self.day)?,
+        )
+        .and_then(|date| {
+            date.and_hms_milli_opt(
+                field(&self.hour).ok()?,
+                field(&self.minute).ok()?,
+                field(&self.second).ok()?,
+                field(&self.milli).ok()? * 10,
+            )
+        })
+        .ok_or(IsoFileError::InvalidDatetime)?;
 
-        let datetime_str = format!(
-            "{}-{}-{}T{}:{}:{}.{}",
-            year_str, month_str, day_str, hour_str, minute_str, second_str, milli_str
-        );
+        fixed_offset
+            .from_local_datetime(&naive_datetime)
+            .single()
+            .ok_or(IsoFileError::InvalidDatetime)
+    }
+}
 
-        // Parse into DateTime<FixedOffset>
-        let datetime = DateTime::parse_from_str(&datetime_str, "%Y-%m-%dT%H:%M:%S%.3f")
-            .map_err(|_| IsoFileError::InvalidDatetime)?
-            .with_timezone(&fixed_offset);
+impl TryInto<DateTime<Utc>> for DecDateTime {
+    type Error = IsoFileError;
 
-        // Convert to UTC
+    fn try_into(self) -> Result<DateTime<Utc>> {
+        let datetime: DateTime<FixedOffset> = self.try_into()?;
         Ok(datetime.with_timezone(&Utc))
     }
 }
 
-impl TryFrom<&DateTime<Utc>> for DecDateTime {
+impl TryFrom<&DateTime<FixedOffset>> for DecDateTime {
     type Error = IsoFileError;
 
-    fn try_from(value: &DateTime<Utc>) -> Result<Self> {
+    fn try_from(value: &DateTime<FixedOffset>) -> Result<Self> {
         let year = format!("{:04}", value.year()).into_bytes();
         let month = format!("{:02}", value.month()).into_bytes();
         let day = format!("{:02}", value.day()).into_bytes();
@@ -124,8 +145,8 @@ impl TryFrom<&DateTime<Utc>> for DecDateTime {
         let second = format!("{:02}", value.second()).into_bytes();
         let milli = format!("{:03}", value.timestamp_subsec_millis()).into_bytes();
 
-        let offset = value.offset().fix().local_minus_utc();
-        let tz_offset = (offset / (15 * 60)) as u8; // Convert seconds to 15-minute intervals
+        let offset = value.offset().local_minus_utc();
+        let tz_offset = (offset / (15 * 60)) as i8 as u8; // Convert seconds to 15-minute intervals
 
         Ok(Self {
             year: [year[0], year[1], year[2], year[3]],
@@ -140,6 +161,14 @@ impl TryFrom<&DateTime<Utc>> for DecDateTime {
     }
 }
 
+impl TryFrom<&DateTime<Utc>> for DecDateTime {
+    type Error = IsoFileError;
+
+    fn try_from(value: &DateTime<Utc>) -> Result<Self> {
+        (&value.fixed_offset()).try_into()
+    }
+}
+
 impl TryFrom<DateTime<Utc>> for DecDateTime {
     type Error = IsoFileError;
 
@@ -148,10 +177,18 @@ impl TryFrom<DateTime<Utc>> for DecDateTime {
     }
 }
 
-impl TryFrom<Option<DateTime<Utc>>> for DecDateTime {
+impl TryFrom<DateTime<FixedOffset>> for DecDateTime {
     type Error = IsoFileError;
 
-    fn try_from(value: Option<DateTime<Utc>>) -> Result<Self> {
+    fn try_from(value: DateTime<FixedOffset>) -> Result<Self> {
+        (&value).try_into()
+    }
+}
+
+impl TryFrom<Option<DateTime<FixedOffset>>> for DecDateTime {
+    type Error = IsoFileError;
+
+    fn try_from(value: Option<DateTime<FixedOffset>>) -> Result<Self> {
         match value {
             Some(t) => Ok(t.try_into()?),
             None => Ok(DecDateTime::default()),
@@ -171,11 +208,12 @@ pub struct IsoDateTime {
     pub gmt_offset: u8,
 }
 
-impl TryInto<DateTime<Utc>> for IsoDateTime {
+impl TryInto<DateTime<FixedOffset>> for IsoDateTime {
     type Error = IsoFileError;
 
-    fn try_into(self) -> Result<DateTime<Utc>> {
-        let fixed_offset = FixedOffset::west_opt((self.gmt_offset as i32) * 15 * 60)
+    fn try_into(self) -> Result<DateTime<FixedOffset>> {
+        // signed 15-minute intervals, positive east of GMT
+        let fixed_offset = FixedOffset::east_opt(i32::from(self.gmt_offset as i8) * 15 * 60)
             .ok_or(IsoFileError::InvalidTimezone)?;
 
         let naive_datetime =
@@ -185,11 +223,18 @@ impl TryInto<DateTime<Utc>> for IsoDateTime {
                 })
                 .ok_or(IsoFileError::InvalidDatetime)?;
 
-        let datetime = fixed_offset
+        fixed_offset
             .from_local_datetime(&naive_datetime)
             .single()
-            .ok_or(IsoFileError::InvalidDatetime)?;
+            .ok_or(IsoFileError::InvalidDatetime)
+    }
+}
 
+impl TryInto<DateTime<Utc>> for IsoDateTime {
+    type Error = IsoFileError;
+
+    fn try_into(self) -> Result<DateTime<Utc>> {
+        let datetime: DateTime<FixedOffset> = self.try_into()?;
         Ok(datetime.with_timezone(&Utc))
     }
 }
@@ -198,8 +243,16 @@ impl TryFrom<&DateTime<Utc>> for IsoDateTime {
     type Error = IsoFileError;
 
     fn try_from(value: &DateTime<Utc>) -> std::result::Result<Self, Self::Error> {
-        let offset = value.offset().fix().local_minus_utc();
-        let gmt_offset = (offset / (15 * 60)) as u8;
+        (&value.fixed_offset()).try_into()
+    }
+}
+
+impl TryFrom<&DateTime<FixedOffset>> for IsoDateTime {
+    type Error = IsoFileError;
+
+    fn try_from(value: &DateTime<FixedOffset>) -> std::result::Result<Self, Self::Error> {
+        let offset = value.offset().local_minus_utc();
+        let gmt_offset = (offset / (15 * 60)) as i8 as u8;
 
         Ok(IsoDateTime {
             year: (value.year() - 1900) as u8,
@@ -0,0 +1,468 @@
+//! Universal Disk Format (ECMA-167) file system of UDF bridge discs.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+use crate::core::{BlockLayout, FileMetadata, LOGICAL_BLOCK_SIZE};
+use crate::descriptor::read_sector;
+use crate::handle::FileData;
+use crate::options::Limits;
+use crate::{IsoFileError, Result};
+
+// block of the anchor volume descriptor pointer
+const ANCHOR_BLOCK: u32 = 256;
+
+// gives up on descriptor sequences without a terminator
+const DESCRIPTOR_LIMIT: u32 = 64;
+
+// descriptor tag identifiers
+const TAG_ANCHOR: u16 = 2;
+const TAG_PARTITION: u16 = 5;
+const TAG_LOGICAL_VOLUME: u16 = 6;
+const TAG_TERMINATING: u16 = 8;
+const TAG_FILE_SET: u16 = 256;
+const TAG_FILE_IDENTIFIER: u16 = 257;
+const TAG_FILE_ENTRY: u16 = 261;
+const TAG_EXTENDED_FILE_ENTRY: u16 = 266;
+
+// file characteristics of a file identifier
+const FID_HIDDEN: u8 = 0x01;
+const FID_DELETED: u8 = 0x04;
+const FID_PARENT: u8 = 0x08;
+
+// allocation descriptor types in the ICB flags
+const AD_SHORT: u16 = 0;
+const AD_LONG: u16 = 1;
+const AD_EMBEDDED: u16 = 3;
+
+/// File or directory of the UDF file system.
+#[derive(Debug, Clone)]
+pub struct UdfEntry {
+    is_dir: bool,
+    hidden: bool,
+    size: u64,
+    datetime: Option<DateTime<Utc>>,
+    location: u32,
+    pub(crate) data: FileData,
+}
+
+impl UdfEntry {
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Information length in bytes, not limited to 4 GiB.
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Modification date and time of the file entry.
+    pub fn datetime(&self) -> Option<DateTime<Utc>> {
+        self.datetime
+    }
+
+    pub fn metadata(&self) -> FileMetadata {
+        FileMetadata {
+            size: self.size,
+            datetime: self.datetime,
+            is_dir: self.is_dir,
+            is_symlink: false,
+            hidden: self.hidden,
+            location: self.location,
+        }
+    }
+}
+
+/// Tree of a UDF file system keyed by path, see
+/// [`IsoReadOptions::set_udf`](crate::IsoReadOptions::set_udf).
+#[derive(Debug, Clone, Default)]
+pub struct UdfEntries {
+    entries: BTreeMap<PathBuf, UdfEntry>,
+}
+
+impl UdfEntries {
+    pub fn get(&self, path: &Path) -> Option<&UdfEntry> {
+        self.entries.get(path)
+    }
+
+    /// Every entry in path order, the root first and each directory before
+    /// its contents.
+    pub fn walk(&self) -> impl Iterator<Item = (&Path, &UdfEntry)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_path(), value))
+    }
+
+    /// Reads the file system of a UDF bridge disc, `None` without an anchor.
+    pub(crate) async fn read<R: AsyncRead + AsyncSeekExt + Unpin>(
+        reader: &mut R,
+        base: u64,
+        limits: Limits,
+        include_hidden: bool,
+    ) -> Result<Option<Self>> {
+        let layout = BlockLayout::new(base, LOGICAL_BLOCK_SIZE as u16);
+
+        let Some(volume) = Volume::read(reader, layout).await? else {
+            return Ok(None);
+        };
+
+        let mut udf = Self::default();
+        let mut stack = vec![(PathBuf::from("/"), volume.root, false)];
+
+        while let Some((path, icb, hidden)) = stack.pop() {
+            if udf.entries.len() >= limits.max_entries {
+                return Err(IsoFileError::EntryLimit(limits.max_entries));
+            }
+
+            // the root is at depth 0
+            if path.components().count() > limits.max_depth + 1 {
+                return Err(IsoFileError::DepthLimit(limits.max_depth));
+            }
+
+            let mut entry = volume.read_entry(reader, icb).await?;
+            entry.hidden = hidden;
+
+            if entry.is_dir {
+                if entry.size > limits.max_allocation {
+                    return Err(IsoFileError::AllocationLimit(entry.size));
+                }
+
+                let content = read_data(reader, &entry.data).await?;
+
+                for (name, icb, characteristics) in file_identifiers(&content) {
+                    if characteristics & (FID_PARENT | FID_DELETED) != 0 || name.is_empty() {
+                        continue;
+                    }
+
+                    let is_hidden = characteristics & FID_HIDDEN != 0;
+
+                    if is_hidden && !include_hidden {
+                        continue;
+                    }
+
+                    stack.push((path.join(name), icb, is_hidden));
+                }
+            }
+
+            udf.entries.insert(path, entry);
+        }
+
+        Ok(Some(udf))
+    }
+}
+
+/// Location of a block in a partition, from a `long_ad`.
+#[derive(Debug, Clone, Copy)]
+struct LongAd {
+    block: u32,
+    partition: u16,
+}
+
+impl LongAd {
+    fn parse(raw: &[u8]) -> Self {
+        Self {
+            block: u32_le(raw, 4),
+            partition: u16_le(raw, 8),
+        }
+    }
+}
+
+// logical volume with its type 1 partition maps
+struct Volume {
+    layout: BlockLayout,
+    // start block of the partition of each partition reference number
+    partitions: Vec<u32>,
+    root: LongAd,
+}
+
+impl Volume {
+    async fn read<R: AsyncRead + AsyncSeekExt + Unpin>(
+        reader: &mut R,
+        layout: BlockLayout,
+    ) -> Result<Option<Self>> {
+        let anchor = read_sector(reader, layout.offset(ANCHOR_BLOCK)).await?;
+
+        let Some(anchor) = anchor.filter(|t| tag(t) == Some(TAG_ANCHOR)) else {
+            return Ok(None);
+        };
+
+        // main volume descriptor sequence
+        let length = u32_le(&anchor, 16) / LOGICAL_BLOCK_SIZE as u32;
+        let start = u32_le(&anchor, 20);
+
+        let mut partition_starts = BTreeMap::new();
+        let mut logical_volume = None;
+
+        for block in start..start + length.min(DESCRIPTOR_LIMIT) {
+            let Some(raw) = read_sector(reader, layout.offset(block)).await? else {
+                break;
+            };
+
+            match tag(&raw) {
+                Some(TAG_PARTITION) => {
+                    _ = partition_starts.insert(u16_le(&raw, 22), u32_le(&raw, 188))
+                }
+                Some(TAG_LOGICAL_VOLUME) => logical_volume = Some(raw),
+                Some(TAG_TERMINATING) | None => break,
+                _ => {}
+            }
+        }
+
+        let lvd = logical_volume.ok_or(IsoFileError::InvalidUdf)?;
+
+        if u32_le(&lvd, 212) != LOGICAL_BLOCK_SIZE as u32 {
+            return Err(IsoFileError::InvalidUdf);
+        }
+
+        // only type 1 maps, virtual, sparable and metadata partitions are not supported
+        let mut partitions = Vec::new();
+        let mut maps = lvd
+            .get(440..440 + u32_le(&lvd, 264) as usize)
+            .unwrap_or_default();
+
+        for _ in 0..u32_le(&lvd, 268) {
+            let [1, 6, _, _, low, high, rest @ ..] = maps else {
+                return Err(IsoFileError::InvalidUdf);
+            };
+
+            let start = partition_starts
+                .get(&u16::from_le_bytes([*low, *high]))
+                .ok_or(IsoFileError::InvalidUdf)?;
+
+            partitions.push(*start);
+            maps = rest;
+        }
+
+        let mut volume = Self {
+            layout,
+            partitions,
+            root: LongAd::parse(&lvd[248..264]),
+        };
+
+        // the file set descriptor is at the start of the logical volume contents
+        let file_set = volume.read_block(reader, volume.root).await?;
+
+        if tag(&file_set) != Some(TAG_FILE_SET) {
+            return Err(IsoFileError::InvalidUdf);
+        }
+
+        volume.root = LongAd::parse(&file_set[400..416]);
+
+        Ok(Some(volume))
+    }
+
+    fn block(&self, location: LongAd) -> Result<u32> {
+        self.partitions
+            .get(usize::from(location.partition))
+            .map(|start| start + location.block)
+            .ok_or(IsoFileError::InvalidUdf)
+    }
+
+    async fn read_block<R: AsyncRead + AsyncSeekExt + Unpin>(
+        &self,
+        reader: &mut R,
+        location: LongAd,
+    ) -> Result<Vec<u8>> {
+        read_sector(reader, self.layout.offset(self.block(location)?))
+            .await?
+            .ok_or(IsoFileError::InvalidUdf)
+    }
+
+    // decodes the file entry or extended file entry of an ICB
+    async fn read_entry<R: AsyncRead + AsyncSeekExt + Unpin>(
+        &self,
+        reader: &mut R,
+        icb: LongAd,
+    ) -> Result<UdfEntry> {
+        let raw = self.read_block(reader, icb).await?;
+
+        // offsets of the modification time, the extended attribute length and the
+        // allocation descriptors
+        let (modified, lengths, descriptors) = match tag(&raw) {
+            Some(TAG_FILE_ENTRY) => (84, 168, 176),
+            Some(TAG_EXTENDED_FILE_ENTRY) => (92, 208, 216),
+            _ => return Err(IsoFileError::InvalidUdf),
+        };
+
+        let size = u64::from_le_bytes(raw[56..64].try_into().unwrap());
+        let start = descriptors + u32_le(&raw, lengths) as usize;
+        let descriptors = raw
+            .get(start..start + u32_le(&raw, lengths + 4) as usize)
+            .ok_or(IsoFileError::InvalidUdf)?;
+
+        let mut location = self.block(icb)?;
+        let data = match u16_le(&raw, 34) & 0x07 {
+            AD_EMBEDDED => FileData::Embedded(
+                descriptors
+                    .get(..size as usize)
+                    .ok_or(IsoFileError::InvalidUdf)?
+                    .to_vec(),
+            ),
+            kind @ (AD_SHORT | AD_LONG) => {
+                let ad_size = if kind == AD_SHORT { 8 } else { 16 };
+                let mut extents: Vec<(u64, u64)> = Vec::new();
+                let mut remaining = size;
+
+                for raw in descriptors.chunks_exact(ad_size) {
+                    let length = u64::from(u32_le(raw, 0) & 0x3fff_ffff);
+
+                    if length == 0 || remaining == 0 {
+                        break;
+                    }
+
+                    // unrecorded, sparse and continuation extents are not supported
+                    if u32_le(raw, 0) >> 30 != 0 {
+                        return Err(IsoFileError::InvalidUdf);
+                    }
+
+                    let block = match kind {
+                        AD_SHORT => self.block(LongAd {
+                            block: u32_le(raw, 4),
+                            partition: icb.partition,
+                        })?,
+                        _ => self.block(LongAd::parse(raw))?,
+                    };
+
+                    if extents.is_empty() {
+                        location = block;
+                    }
+
+                    let offset = self.layout.offset(block);
+                    let length = length.min(remaining);
+                    remaining -= length;
+
+                    extents.push((offset, length));
+                }
+
+                FileData::from_extents(extents)
+            }
+            _ => return Err(IsoFileError::InvalidUdf),
+        };
+
+        Ok(UdfEntry {
+            // file type in the ICB tag
+            is_dir: raw[27] == 4,
+            hidden: false,
+            size: data.len(),
+            datetime: timestamp(&raw[modified..modified + 12]),
+            location,
+            data,
+        })
+    }
+}
+
+async fn read_data<R: AsyncRead + AsyncSeekExt + Unpin>(
+    reader: &mut R,
+    data: &FileData,
+) -> Result<Vec<u8>> {
+    match data {
+        FileData::Embedded(content) => Ok(content.clone()),
+        FileData::Extents(extents) => {
+            let mut content = Vec::new();
+
+            for &(offset, length) in extents {
+                let start = content.len();
+                content.resize(start + length as usize, 0);
+
+                reader.seek(SeekFrom::Start(offset)).await?;
+                reader.read_exact(&mut content[start..]).await?;
+            }
+
+            Ok(content)
+        }
+    }
+}
+
+// name, ICB and characteristics of the file identifier descriptors of a directory
+fn file_identifiers(content: &[u8]) -> impl Iterator<Item = (String, LongAd, u8)> + '_ {
+    let mut rest = content;
+
+    std::iter::from_fn(move || {
+        if rest.len() < 38 || tag(rest) != Some(TAG_FILE_IDENTIFIER) {
+            return None;
+        }
+
+        let characteristics = rest[18];
+        let id_length = usize::from(rest[19]);
+        let icb = LongAd::parse(&rest[20..36]);
+        let id_start = 38 + usize::from(u16_le(rest, 36));
+        let name = dstring(rest.get(id_start..id_start + id_length)?);
+
+        // descriptors are padded to a multiple of 4 bytes
+        let size = (id_start + id_length).next_multiple_of(4);
+        rest = rest.get(size..).unwrap_or_default();
+
+        Some((name.unwrap_or_default(), icb, characteristics))
+    })
+}
+
+// OSTA compressed unicode, 8 or 16 bits per character
+fn dstring(raw: &[u8]) -> Option<String> {
+    match raw.split_first()? {
+        (8, chars) => Some(chars.iter().map(|&t| char::from(t)).collect()),
+        (16, chars) => {
+            let units: Vec<u16> = chars
+                .chunks_exact(2)
+                .map(|t| u16::from_be_bytes([t[0], t[1]]))
+                .collect();
+
+            Some(String::from_utf16_lossy(&units))
+        }
+        _ => None,
+    }
+}
+
+fn timestamp(raw: &[u8]) -> Option<DateTime<Utc>> {
+    let type_and_timezone = u16_le(raw, 0);
+
+    // 12 bit signed offset from UTC in minutes, -2047 if unspecified
+    let minutes = ((type_and_timezone << 4) as i16 >> 4) as i32;
+    let offset = if minutes == -2047 { 0 } else { minutes * 60 };
+
+    let microseconds = u32::from(raw[9]) * 10_000 + u32::from(raw[10]) * 100 + u32::from(raw[11]);
+
+    let naive = NaiveDate::from_ymd_opt(
+        i16::from_le_bytes([raw[2], raw[3]]).into(),
+        raw[4].into(),
+        raw[5].into(),
+    )?
+    .and_hms_micro_opt(raw[6].into(), raw[7].into(), raw[8].into(), microseconds)?;
+
+    let datetime = FixedOffset::east_opt(offset)?
+        .from_local_datetime(&naive)
+        .single()?;
+
+    Some(datetime.with_timezone(&Utc))
+}
+
+// identifier of a descriptor tag with a valid checksum
+fn tag(raw: &[u8]) -> Option<u16> {
+    let raw = raw.get(..16)?;
+
+    let checksum = raw
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != 4)
+        .fold(0u8, |sum, (_, t)| sum.wrapping_add(*t));
+
+    (checksum == raw[4]).then(|| u16_le(raw, 0))
+}
+
+fn u16_le(raw: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([raw[at], raw[at + 1]])
+}
+
+fn u32_le(raw: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(raw[at..at + 4].try_into().unwrap())
+}
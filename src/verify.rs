@@ -0,0 +1,146 @@
+//! Structural checks of a parsed image, see [`IsoFileReader::verify`](crate::IsoFileReader::verify).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::IsoIndex;
+
+/// Inconsistency found by [`IsoFileReader::verify`](crate::IsoFileReader::verify).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The image is shorter than the volume space size of the descriptor.
+    ImageTruncated { expected: u64, actual: u64 },
+    /// An extent ends past the volume space size, `end` is its last block + 1.
+    OutOfVolume { path: PathBuf, end: u64 },
+    /// Extents of two entries share blocks without being the same extent.
+    Overlap { first: PathBuf, second: PathBuf },
+    /// Both-endian field whose halves differ, in the descriptor if `path` is `None`.
+    EndianMismatch {
+        field: &'static str,
+        path: Option<PathBuf>,
+    },
+    /// A path table entry points where the tree has no directory.
+    PathTableMismatch { path: PathBuf, location: u32 },
+    /// A directory of the tree is missing from the path table.
+    MissingFromPathTable { path: PathBuf },
+    /// An optional path table is unreadable or disagrees with the one in use.
+    OptionalPathTableMismatch { location: u32 },
+    /// The UDF tree asked for could not be read, only the ISO 9660 tree is.
+    UnsupportedUdf,
+}
+
+/// Result of [`IsoFileReader::verify`](crate::IsoFileReader::verify), empty for
+/// a consistent image.
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub violations: Vec<Violation>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// Checks the loaded tree of `index` against its descriptor and path table.
+    pub(crate) fn check(index: &IsoIndex, image_len: u64) -> Self {
+        let mut violations = Vec::new();
+        let block_size = u64::from(index.layout.logical_block_size());
+
+        let volume_blocks = u64::from(index.header.volume_space_size());
+        let expected = index.layout.offset(0) + volume_blocks * block_size;
+
+        if image_len < expected {
+            violations.push(Violation::ImageTruncated {
+                expected,
+                actual: image_len,
+            });
+        }
+
+        for field in index.header.endian_mismatches() {
+            violations.push(Violation::EndianMismatch { field, path: None });
+        }
+
+        for &location in &index.rejected_path_tables {
+            violations.push(Violation::OptionalPathTableMismatch { location });
+        }
+
+        if index.udf_unsupported {
+            violations.push(Violation::UnsupportedUdf);
+        }
+
+        let root = index
+            .entries
+            .get(Path::new("/"))
+            .map(|t| (PathBuf::from("/"), t));
+        let mut extents = Vec::new();
+        let mut directories = BTreeMap::new();
+
+        for (path, value) in root.into_iter().chain(index.entries.walk(true)) {
+            for field in value.record().endian_mismatches() {
+                violations.push(Violation::EndianMismatch {
+                    field,
+                    path: Some(path.clone()),
+                });
+            }
+
+            if !value.entry().is_file() {
+                directories.insert(value.record().location(None), path.clone());
+            }
+
+            for (block, length) in value.extents() {
+                let end = u64::from(block) + u64::from(length).div_ceil(block_size);
+
+                if end > volume_blocks {
+                    violations.push(Violation::OutOfVolume {
+                        path: path.clone(),
+                        end,
+                    });
+                }
+
+                if length > 0 {
+                    extents.push((u64::from(block), end, path.clone()));
+                }
+            }
+        }
+
+        // identical extents are shared on purpose, partial overlaps are not
+        extents.sort();
+        extents.dedup_by(|t, previous| t.0 == previous.0 && t.1 == previous.1);
+
+        let mut furthest: Option<(u64, &PathBuf)> = None;
+
+        for (start, end, path) in &extents {
+            match furthest {
+                Some((previous_end, previous)) if *start < previous_end => {
+                    violations.push(Violation::Overlap {
+                        first: previous.clone(),
+                        second: path.clone(),
+                    });
+                }
+                _ => {}
+            }
+
+            if furthest.is_none_or(|(t, _)| *end > t) {
+                furthest = Some((*end, path));
+            }
+        }
+
+        let mut listed = BTreeSet::new();
+
+        for (path, location) in index.path_table.paths() {
+            if !directories.contains_key(&location) {
+                violations.push(Violation::PathTableMismatch { path, location });
+            }
+
+            listed.insert(location);
+        }
+
+        for (location, path) in directories {
+            if !listed.contains(&location) {
+                violations.push(Violation::MissingFromPathTable { path });
+            }
+        }
+
+        Self { violations }
+    }
+}
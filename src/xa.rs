@@ -0,0 +1,71 @@
+//! CD-ROM XA records at the start of the system use area, as found on
+//! PlayStation and Video CD discs.
+
+/// Size of the record, system use entries of other extensions follow it.
+pub(crate) const XA_SIZE: usize = 14;
+
+// attribute bits
+const MODE2_FORM1: u16 = 0x0800;
+const MODE2_FORM2: u16 = 0x1000;
+const INTERLEAVED: u16 = 0x2000;
+const CDDA: u16 = 0x4000;
+const DIRECTORY: u16 = 0x8000;
+
+/// Owner, attributes and file number from an XA record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XaAttributes {
+    pub group: u16,
+    pub owner: u16,
+    /// Raw attribute bits, the low ones are permissions as in [`mode`](Self::mode).
+    pub attributes: u16,
+    /// Number of the file in an interleaved stream.
+    pub file_number: u8,
+}
+
+impl XaAttributes {
+    pub(crate) fn parse(area: &[u8]) -> Option<Self> {
+        match area.get(..XA_SIZE)? {
+            [g0, g1, o0, o1, a0, a1, b'X', b'A', file_number, ..] => Some(Self {
+                group: u16::from_be_bytes([*g0, *g1]),
+                owner: u16::from_be_bytes([*o0, *o1]),
+                attributes: u16::from_be_bytes([*a0, *a1]),
+                file_number: *file_number,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Permissions as POSIX mode bits, XA has no write permission.
+    pub fn mode(&self) -> u32 {
+        // read and execute for owner, group and world, two bits apart
+        [0o400, 0o100, 0o040, 0o010, 0o004, 0o001]
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| self.attributes & (1 << (index * 2)) != 0)
+            .fold(0, |mode, (_, t)| mode | t)
+    }
+
+    /// Sectors are recorded in Mode 2 Form 1, 2048 bytes of data each.
+    pub fn is_form1(&self) -> bool {
+        self.attributes & MODE2_FORM1 != 0
+    }
+
+    /// Sectors are recorded in Mode 2 Form 2, 2324 bytes of data each without
+    /// error correction, as used for audio and video streams.
+    pub fn is_form2(&self) -> bool {
+        self.attributes & MODE2_FORM2 != 0
+    }
+
+    pub fn is_interleaved(&self) -> bool {
+        self.attributes & INTERLEAVED != 0
+    }
+
+    /// The file is a CD-DA audio track.
+    pub fn is_cdda(&self) -> bool {
+        self.attributes & CDDA != 0
+    }
+
+    pub fn is_directory(&self) -> bool {
+        self.attributes & DIRECTORY != 0
+    }
+}
@@ -0,0 +1,62 @@
+//! Extended attribute records (ECMA-119 9.5) at the start of an extent.
+
+use chrono::{DateTime, Utc};
+
+use crate::rock_ridge;
+
+/// Size of the fixed part of a record, before the escape sequences and the
+/// application use area.
+pub(crate) const XAR_SIZE: usize = 250;
+
+// permission bits, a set bit denies the access
+const OWNER_READ: u16 = 1 << 4;
+const OWNER_EXECUTE: u16 = 1 << 6;
+const GROUP_READ: u16 = 1 << 8;
+const GROUP_EXECUTE: u16 = 1 << 10;
+const OTHER_READ: u16 = 1 << 12;
+const OTHER_EXECUTE: u16 = 1 << 14;
+
+/// Owner, permissions and timestamps from an extended attribute record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtendedAttributes {
+    pub owner: u16,
+    pub group: u16,
+    /// Raw permission bits, a set bit denies the access.
+    pub permissions: u16,
+    pub created: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+    pub expires: Option<DateTime<Utc>>,
+    pub effective: Option<DateTime<Utc>>,
+}
+
+impl ExtendedAttributes {
+    pub(crate) fn parse(record: &[u8]) -> Option<Self> {
+        let record = record.get(..XAR_SIZE)?;
+        let lsb_u16 = |index: usize| u16::from_le_bytes([record[index], record[index + 1]]);
+
+        Some(Self {
+            owner: lsb_u16(0),
+            group: lsb_u16(4),
+            permissions: u16::from_be_bytes([record[8], record[9]]),
+            created: rock_ridge::timestamp(&record[10..27]),
+            modified: rock_ridge::timestamp(&record[27..44]),
+            expires: rock_ridge::timestamp(&record[44..61]),
+            effective: rock_ridge::timestamp(&record[61..78]),
+        })
+    }
+
+    /// Permissions as POSIX mode bits, ISO 9660 has no write permission.
+    pub fn mode(&self) -> u32 {
+        [
+            (OWNER_READ, 0o400),
+            (OWNER_EXECUTE, 0o100),
+            (GROUP_READ, 0o040),
+            (GROUP_EXECUTE, 0o010),
+            (OTHER_READ, 0o004),
+            (OTHER_EXECUTE, 0o001),
+        ]
+        .into_iter()
+        .filter(|(bit, _)| self.permissions & bit == 0)
+        .fold(0, |mode, (_, t)| mode | t)
+    }
+}
@@ -0,0 +1,78 @@
+//! zisofs, files compressed block by block with zlib and marked by a `ZF` entry.
+
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+use crate::{IsoFileError, Result};
+
+const MAGIC: [u8; 8] = [0x37, 0xe4, 0x53, 0x96, 0xc9, 0xdb, 0xd6, 0x07];
+
+/// Parameters of a compressed file from its `ZF` entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Zisofs {
+    /// Size of the original content.
+    pub size: u32,
+}
+
+impl Zisofs {
+    /// Reads the data of a `ZF` entry, `None` for algorithms other than `pz`.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        match data {
+            [b'p', b'z', _, _, size @ ..] => Some(Self {
+                size: u32::from_le_bytes(size.get(..4)?.try_into().unwrap()),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Inflates the stored content of the file, header and block pointers included.
+    pub fn decompress(&self, stored: &[u8]) -> Result<Vec<u8>> {
+        // magic, original size, header size in words, log2 of the block size
+        let header = stored.get(..16).ok_or(IsoFileError::InvalidZisofs)?;
+
+        if header[..8] != MAGIC || !(15..=17).contains(&header[13]) {
+            return Err(IsoFileError::InvalidZisofs);
+        }
+
+        let header_size = usize::from(header[12]) * 4;
+        let block_size = 1usize << header[13];
+        let size = self.size as usize;
+        let blocks = size.div_ceil(block_size);
+
+        // one pointer per block and one for the end of the last block
+        let pointers = stored
+            .get(header_size..header_size + (blocks + 1) * 4)
+            .ok_or(IsoFileError::InvalidZisofs)?
+            .chunks_exact(4)
+            .map(|t| u32::from_le_bytes(t.try_into().unwrap()) as usize)
+            .collect::<Vec<_>>();
+
+        let mut content = Vec::with_capacity(size);
+
+        for (index, pair) in pointers.windows(2).enumerate() {
+            let length = block_size.min(size - index * block_size);
+            let block = stored
+                .get(pair[0]..pair[1])
+                .ok_or(IsoFileError::InvalidZisofs)?;
+
+            // empty blocks stand for zeros
+            if block.is_empty() {
+                content.resize(content.len() + length, 0);
+                continue;
+            }
+
+            let start = content.len();
+            ZlibDecoder::new(block)
+                .take(length as u64)
+                .read_to_end(&mut content)
+                .map_err(|_| IsoFileError::InvalidZisofs)?;
+
+            if content.len() - start != length {
+                return Err(IsoFileError::InvalidZisofs);
+            }
+        }
+
+        Ok(content)
+    }
+}